@@ -0,0 +1,102 @@
+//Fixed workloads for timing instruction dispatch, run with cargo bench
+use std::time::{Duration, Instant};
+use gbi::{asm, boot::{BootMode, Model}, bus::FlatMemory, cpu::Cpu, mainboard::Mainboard, ppu};
+
+const INSTRUCTIONS:u32 = 200_000;
+const FRAMES:u32 = 4;
+//Each workload is timed in many short rounds and the fastest is reported. A round is short enough
+//to usually fit between preemptions, so the best one is repeatable even on a busy machine.
+const ROUNDS:u32 = 250;
+
+//Register and (HL) ALU ops, CB ops, branches, calls and stack traffic over a 2KiB buffer
+const WORKLOAD:&str = "
+    ld sp, $DFFF
+    ld hl, $C000
+    ld bc, 0
+    ld de, 0
+main:
+    ld a, [hl]
+    add a, b
+    adc a, c
+    xor d
+    ld [hl+], a
+    inc [hl]
+    rlc [hl]
+    bit 3, a
+    jr z, .skip
+    swap a
+.skip:
+    push bc
+    call work
+    pop bc
+    inc bc
+    ld a, h
+    cp $C8
+    jr nz, main
+    ld hl, $C000
+    jr main
+work:
+    ld d, a
+    srl d
+    sub a, e
+    ld e, a
+    and $0F
+    or l
+    ret
+";
+
+struct Headless;
+
+impl gbi::Frontend for Headless
+{
+    fn receive_rom_information(&mut self, _title: &str) {}
+    fn event_poll(&mut self) -> bool { true }
+    fn video_update(&mut self, _buffer: &[[u8; ppu::SCREEN_HEIGHT];ppu::SCREEN_WIDTH], _frame_count: u64) {}
+}
+
+fn fastest(mut run: impl FnMut()) -> Duration
+{
+    (0..ROUNDS).map(|_|
+    {
+        let start = Instant::now();
+        run();
+        start.elapsed()
+    }).min().unwrap()
+}
+
+fn report(name: &str, count: u32, unit: &str, elapsed: Duration)
+{
+    println!("{:<6} {} {} in {:.3?}, {:.0} {}/s", name, count, unit, elapsed, count as f64 / elapsed.as_secs_f64(), unit);
+}
+
+fn main()
+{
+    //The CPU on its own against flat memory
+    let mut memory = FlatMemory::new();
+    memory.load(0x0100, &asm::assemble(WORKLOAD, 0x0100).unwrap());
+    let mut cpu = Cpu::new();
+    let mut registers = cpu.registers();
+    registers.pc = 0x0100;
+    cpu.set_registers(registers);
+    let elapsed = fastest(||
+    {
+        for _ in 0..INSTRUCTIONS
+        {
+            cpu.step(&mut memory);
+        }
+    });
+    report("cpu", INSTRUCTIONS, "instructions", elapsed);
+
+    //Whole frames with the PPU and timer running
+    let mut board = Mainboard::new(Headless);
+    board.set_boot_mode(BootMode::Skip(Model::Dmg));
+    board.load_game_from_bytes(asm::assemble_rom(WORKLOAD, "BENCH").unwrap()).unwrap();
+    let elapsed = fastest(||
+    {
+        for _ in 0..FRAMES
+        {
+            board.execute_frame();
+        }
+    });
+    report("board", FRAMES, "frames", elapsed);
+}
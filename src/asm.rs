@@ -0,0 +1,715 @@
+#[cfg(test)]
+mod tests;
+
+use std::collections::HashMap;
+
+use crate::{disasm::{self, Operand, Register16}, rom::{self, HeaderValidation, NINTENDO_LOGO}};
+
+const CB_PREFIX:u8 = 0xCB;
+
+//----ROM Image Layout----
+///Where assemble_rom places the code, straight after the header
+pub const ROM_CODE_START:u16 = 0x0150;
+const ROM_SIZE:usize = 0x8000;
+const ENTRY_POINT:usize = 0x0100;
+//nop, jp $0150
+const ENTRY_CODE:[u8; 4] = [0x00, 0xC3, 0x50, 0x01];
+//0x0143 is the CGB flag on newer cartridges, keep the title clear of it
+const TITLE_LENGTH:usize = 15;
+
+//Operands that are always registers or conditions, never symbols
+const NAMES:[&str; 15] = ["a", "b", "c", "d", "e", "h", "l", "af", "bc", "de", "hl", "sp", "nz", "z", "nc"];
+
+//----Expressions----
+const OPERATORS:[&str; 12] = ["<<", ">>", "+", "-", "*", "/", "&", "|", "^", "~", "(", ")"];
+//Binary operators from loosest to tightest binding
+const PRECEDENCE:[&[&str]; 6] = [&["|"], &["^"], &["&"], &["<<", ">>"], &["+", "-"], &["*", "/"]];
+
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub enum AsmErrorKind
+{
+    UnknownMnemonic(String),
+    ///The mnemonic exists but no form of it takes these operands
+    InvalidOperands(String),
+    InvalidExpression(String),
+    UndefinedSymbol(String),
+    DuplicateSymbol(String),
+    ///Value doesn't fit where it's used, JR distances included
+    OutOfRange(i64),
+    ///ORG behind the current address
+    InvalidOrigin(i64),
+    ///Code runs past the end of a 32KiB ROM, holds the size it needed
+    RomTooLarge(usize)
+}
+
+impl std::fmt::Display for AsmErrorKind
+{
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result
+    {
+        match self
+        {
+            AsmErrorKind::UnknownMnemonic(x) => write!(f, "unknown mnemonic {}", x),
+            AsmErrorKind::InvalidOperands(x) => write!(f, "invalid operands for {}", x),
+            AsmErrorKind::InvalidExpression(x) => write!(f, "invalid expression {}", x),
+            AsmErrorKind::UndefinedSymbol(x) => write!(f, "undefined symbol {}", x),
+            AsmErrorKind::DuplicateSymbol(x) => write!(f, "symbol {} is already defined", x),
+            AsmErrorKind::OutOfRange(x) => write!(f, "value {} is out of range", x),
+            AsmErrorKind::InvalidOrigin(x) => write!(f, "origin 0x{:04X} is behind the current address", x),
+            AsmErrorKind::RomTooLarge(x) => write!(f, "code needs {} bytes, more than a 32KiB ROM holds", x)
+        }
+    }
+}
+
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct AsmError
+{
+    ///Source line counting from 1, 0 when the error isn't tied to a line
+    pub line: usize,
+    pub kind: AsmErrorKind
+}
+
+impl std::fmt::Display for AsmError
+{
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result
+    {
+        match self.line
+        {
+            0 => write!(f, "{}", self.kind),
+            x => write!(f, "line {}: {}", x, self.kind)
+        }
+    }
+}
+
+impl std::error::Error for AsmError {}
+
+///Assembles RGBDS style source placed at origin, returning everything from origin to the last byte emitted.
+///Parentheses around a whole operand mean memory like brackets do, and no$gmb's ldi/ldd and (FF00+c) are accepted.
+///Supports labels (.local ones scoped under the last global label), EQU, db, dw, ds and org.
+pub fn assemble(source: &str, origin: u16) -> Result<Vec<u8>, AsmError>
+{
+    let mut assembler = Assembler::new(origin);
+    assembler.run(source)?;
+    assembler.final_pass = true;
+    assembler.run(source)?;
+    Ok(assembler.output)
+}
+
+///Assembles source into a 32KiB cartridge with no mapper and a header that passes every check.
+///The code starts at ROM_CODE_START and the entry point jumps straight to it.
+pub fn assemble_rom(source: &str, title: &str) -> Result<Vec<u8>, AsmError>
+{
+    let code = assemble(source, ROM_CODE_START)?;
+    let start = ROM_CODE_START as usize;
+    if start + code.len() > ROM_SIZE
+    {
+        return Err(AsmError { line: 0, kind: AsmErrorKind::RomTooLarge(start + code.len()) });
+    }
+    let mut rom = vec![0; ROM_SIZE];
+    rom[ENTRY_POINT..ENTRY_POINT + ENTRY_CODE.len()].copy_from_slice(&ENTRY_CODE);
+    rom[rom::LOGO_START..rom::LOGO_START + NINTENDO_LOGO.len()].copy_from_slice(&NINTENDO_LOGO);
+    let title = &title.as_bytes()[..title.len().min(TITLE_LENGTH)];
+    rom[rom::TITLE..rom::TITLE + title.len()].copy_from_slice(title);
+    rom[start..start + code.len()].copy_from_slice(&code);
+
+    rom[rom::HEADER_CHECKSUM] = HeaderValidation::new(&rom).computed_header_checksum;
+    let global = HeaderValidation::new(&rom).computed_global_checksum;
+    rom[rom::GLOBAL_CHECKSUM..rom::GLOBAL_CHECKSUM + 2].copy_from_slice(&global.to_be_bytes());
+    Ok(rom)
+}
+
+//Operand as written, before it's matched against an encoding
+#[derive(Clone, PartialEq, Eq, Debug)]
+enum Arg
+{
+    ///Register, register pair or condition, lowercase
+    Name(String),
+    ///[bc], [de] or [hl]
+    Pointer(Register16),
+    HlIncrement,
+    HlDecrement,
+    HighC,
+    ///[expression]
+    Memory(String),
+    ///The expression after sp in sp+e
+    StackOffset(String),
+    Value(String)
+}
+
+#[derive(Clone, PartialEq, Eq, Debug)]
+enum Token
+{
+    Number(i64),
+    Symbol(String),
+    Operator(&'static str)
+}
+
+struct Assembler
+{
+    //Opcode bytes next to their decoded form, the disassembler doubles as the encoding table
+    templates: Vec<(Vec<u8>, disasm::Instruction)>,
+    symbols: HashMap<String, i64>,
+    //Last global label, local labels are stored as global.local
+    scope: String,
+    //The first pass only finds the labels, unknown symbols read as 0 and nothing is range checked
+    final_pass: bool,
+    origin: i64,
+    address: i64,
+    output: Vec<u8>
+}
+
+impl Assembler
+{
+    fn new(origin: u16) -> Assembler
+    {
+        let base = (0..=0xFF_u8).filter(|x| *x != CB_PREFIX).map(|x| vec![x]);
+        let cb = (0..=0xFF_u8).map(|x| vec![CB_PREFIX, x]);
+        let templates = base.chain(cb).filter_map(|opcode|
+        {
+            let mut bytes = opcode.clone();
+            bytes.resize(3, 0);
+            let instruction = disasm::decode(&bytes, 0)?;
+            (instruction.mnemonic != "db").then_some((opcode, instruction))
+        }).collect();
+        Assembler
+        {
+            templates,
+            symbols: HashMap::new(),
+            scope: String::new(),
+            final_pass: false,
+            origin: origin as i64,
+            address: origin as i64,
+            output: Vec::new()
+        }
+    }
+
+    fn run(&mut self, source: &str) -> Result<(), AsmError>
+    {
+        self.scope.clear();
+        self.address = self.origin;
+        self.output.clear();
+        for (i, text) in source.lines().enumerate()
+        {
+            self.line(text).map_err(|kind| AsmError { line: i + 1, kind })?;
+        }
+        Ok(())
+    }
+
+    fn line(&mut self, text: &str) -> Result<(), AsmErrorKind>
+    {
+        let mut text = strip_comment(text).trim();
+        let label_length = text.find(|x: char| !is_symbol_char(x)).unwrap_or(text.len());
+        if label_length > 0 && text[label_length..].starts_with(':')
+        {
+            let name = self.qualify(&text[..label_length]);
+            if !name.contains('.')
+            {
+                self.scope = name.clone();
+            }
+            self.define(name, self.address)?;
+            text = text[label_length..].trim_start_matches(':').trim();
+        }
+        if text.is_empty()
+        {
+            return Ok(());
+        }
+
+        let (word, rest) = text.split_once(char::is_whitespace).unwrap_or((text, ""));
+        let rest = rest.trim();
+        if let Some((keyword, expression)) = rest.split_once(char::is_whitespace)
+        {
+            if keyword.eq_ignore_ascii_case("equ")
+            {
+                let value = self.evaluate(expression)?;
+                return self.define(self.qualify(word), value);
+            }
+        }
+
+        let mnemonic = word.to_ascii_lowercase();
+        match mnemonic.as_str()
+        {
+            "db" =>
+            {
+                let mut bytes = Vec::new();
+                for x in split_operands(rest)
+                {
+                    match x.strip_prefix('"').and_then(|x| x.strip_suffix('"'))
+                    {
+                        Some(string) => bytes.extend_from_slice(string.as_bytes()),
+                        None => bytes.push(self.range(self.evaluate(x)?, -0x80, 0xFF)? as u8)
+                    }
+                }
+                self.emit(&bytes)
+            }
+            "dw" =>
+            {
+                let mut bytes = Vec::new();
+                for x in split_operands(rest)
+                {
+                    bytes.extend((self.range(self.evaluate(x)?, -0x8000, 0xFFFF)? as u16).to_le_bytes());
+                }
+                self.emit(&bytes)
+            }
+            "ds" =>
+            {
+                let operands = split_operands(rest);
+                let count = self.constant(operands[0])?;
+                if !(0..=0x10000).contains(&count)
+                {
+                    return Err(AsmErrorKind::OutOfRange(count));
+                }
+                let fill = match operands.get(1)
+                {
+                    Some(x) => self.range(self.evaluate(x)?, -0x80, 0xFF)? as u8,
+                    None => 0
+                };
+                self.emit(&vec![fill; count as usize])
+            }
+            "org" =>
+            {
+                let address = self.constant(rest)?;
+                if address < self.address || address > 0xFFFF
+                {
+                    return Err(AsmErrorKind::InvalidOrigin(address));
+                }
+                self.emit(&vec![0; (address - self.address) as usize])
+            }
+            _ => self.instruction(&mnemonic, rest)
+        }
+    }
+
+    fn instruction(&mut self, mnemonic: &str, operands: &str) -> Result<(), AsmErrorKind>
+    {
+        let mut args = match operands.is_empty()
+        {
+            true => Vec::new(),
+            false => split_operands(operands).into_iter().map(parse_arg).collect::<Vec<Arg>>()
+        };
+        let mnemonic = match mnemonic
+        {
+            "ldi" | "ldd" =>
+            {
+                let replacement = if mnemonic == "ldi" { Arg::HlIncrement } else { Arg::HlDecrement };
+                for x in args.iter_mut().filter(|x| **x == Arg::Pointer(Register16::HL))
+                {
+                    *x = replacement.clone();
+                }
+                "ld"
+            }
+            x => x
+        };
+        if mnemonic == "jp" && args == [Arg::Pointer(Register16::HL)]
+        {
+            args[0] = Arg::Name("hl".to_string());
+        }
+
+        //Spellings that mean the same instruction: ld for ldh, and A written out or left off the ALU ops
+        let mut forms = vec![(mnemonic, args.clone())];
+        match (mnemonic, args.as_slice())
+        {
+            ("ld", _) => forms.push(("ldh", args.clone())),
+            ("sub" | "and" | "xor" | "or" | "cp", [Arg::Name(a), x]) if a == "a" => forms.push((mnemonic, vec![x.clone()])),
+            ("add" | "adc" | "sbc", [x]) => forms.push((mnemonic, vec![Arg::Name("a".to_string()), x.clone()])),
+            _ => ()
+        }
+        for (mnemonic, args) in forms
+        {
+            if let Some(index) = self.find(mnemonic, &args)?
+            {
+                return self.encode(index, &args);
+            }
+        }
+
+        match self.templates.iter().any(|(_, x)| x.mnemonic == mnemonic)
+        {
+            true => Err(AsmErrorKind::InvalidOperands(format!("{} {}", mnemonic, operands))),
+            false => Err(AsmErrorKind::UnknownMnemonic(mnemonic.to_string()))
+        }
+    }
+
+    fn find(&self, mnemonic: &str, args: &[Arg]) -> Result<Option<usize>, AsmErrorKind>
+    {
+        'templates: for (index, (_, template)) in self.templates.iter().enumerate()
+        {
+            if template.mnemonic != mnemonic || template.operands.len() != args.len()
+            {
+                continue;
+            }
+            for (operand, arg) in template.operands.iter().zip(args)
+            {
+                if !self.matches(mnemonic, operand, arg)?
+                {
+                    continue 'templates;
+                }
+            }
+            return Ok(Some(index));
+        }
+        Ok(None)
+    }
+
+    fn matches(&self, mnemonic: &str, operand: &Operand, arg: &Arg) -> Result<bool, AsmErrorKind>
+    {
+        let named = |x: String| matches!(arg, Arg::Name(name) if x.eq_ignore_ascii_case(name));
+        Ok(match (operand, arg)
+        {
+            (Operand::Register8(x), _) => named(format!("{:?}", x)),
+            (Operand::Register16(x), _) => named(format!("{:?}", x)),
+            (Operand::Condition(x), _) => named(format!("{:?}", x)),
+            (Operand::Indirect(x), Arg::Pointer(y)) => x == y,
+            (Operand::IndirectIncrement, Arg::HlIncrement) => true,
+            (Operand::IndirectDecrement, Arg::HlDecrement) => true,
+            (Operand::HighC, Arg::HighC) => true,
+            (Operand::Immediate8(_) | Operand::Immediate16(_) | Operand::SignedImmediate(_), Arg::Value(_)) => true,
+            (Operand::Address(_) | Operand::HighAddress(_), Arg::Memory(_)) => true,
+            (Operand::StackOffset(_), Arg::StackOffset(_)) => true,
+            //RST vectors and bit numbers are part of the opcode, so they pick the encoding
+            (Operand::Target(x), Arg::Value(expression)) => mnemonic != "rst" || self.constant(expression)? == *x as i64,
+            (Operand::Bit(x), Arg::Value(expression)) => self.constant(expression)? == *x as i64,
+            _ => false
+        })
+    }
+
+    fn encode(&mut self, index: usize, args: &[Arg]) -> Result<(), AsmErrorKind>
+    {
+        let (opcode, template) = &self.templates[index];
+        let next = self.address + template.length as i64;
+        let mut bytes = opcode.clone();
+        for (operand, arg) in template.operands.iter().zip(args)
+        {
+            bytes.extend(self.operand_bytes(template.mnemonic, operand, arg, next)?);
+        }
+        //STOP's second byte
+        bytes.resize(template.length as usize, 0);
+        self.emit(&bytes)
+    }
+
+    fn operand_bytes(&self, mnemonic: &str, operand: &Operand, arg: &Arg, next: i64) -> Result<Vec<u8>, AsmErrorKind>
+    {
+        let expression = match arg
+        {
+            Arg::Value(x) | Arg::Memory(x) | Arg::StackOffset(x) => x,
+            _ => return Ok(Vec::new())
+        };
+        let value = self.evaluate(expression)?;
+        Ok(match operand
+        {
+            Operand::Immediate8(_) => vec![self.range(value, -0x80, 0xFF)? as u8],
+            Operand::SignedImmediate(_) | Operand::StackOffset(_) => vec![self.range(value, -0x80, 0x7F)? as u8],
+            Operand::HighAddress(_) =>
+            {
+                let offset = if value >= 0xFF00 { value - 0xFF00 } else { value };
+                vec![self.range(offset, 0, 0xFF)? as u8]
+            }
+            Operand::Target(_) if mnemonic == "jr" => vec![self.range(value - next, -0x80, 0x7F)? as u8],
+            Operand::Target(_) if mnemonic == "rst" => Vec::new(),
+            Operand::Immediate16(_) | Operand::Address(_) | Operand::Target(_) =>
+            {
+                (self.range(value, -0x8000, 0xFFFF)? as u16).to_le_bytes().to_vec()
+            }
+            _ => Vec::new()
+        })
+    }
+
+    fn emit(&mut self, bytes: &[u8]) -> Result<(), AsmErrorKind>
+    {
+        if self.address + bytes.len() as i64 > 0x10000
+        {
+            return Err(AsmErrorKind::OutOfRange(self.address + bytes.len() as i64));
+        }
+        self.address += bytes.len() as i64;
+        self.output.extend_from_slice(bytes);
+        Ok(())
+    }
+
+    fn define(&mut self, name: String, value: i64) -> Result<(), AsmErrorKind>
+    {
+        if !self.final_pass && self.symbols.contains_key(&name)
+        {
+            return Err(AsmErrorKind::DuplicateSymbol(name));
+        }
+        self.symbols.insert(name, value);
+        Ok(())
+    }
+
+    fn qualify(&self, name: &str) -> String
+    {
+        match name.starts_with('.')
+        {
+            true => format!("{}{}", self.scope, name),
+            false => name.to_string()
+        }
+    }
+
+    fn symbol(&self, name: &str, allow_unknown: bool) -> Result<i64, AsmErrorKind>
+    {
+        if name == "@"
+        {
+            return Ok(self.address);
+        }
+        let name = self.qualify(name);
+        match self.symbols.get(&name)
+        {
+            Some(x) => Ok(*x),
+            None if allow_unknown => Ok(0),
+            None => Err(AsmErrorKind::UndefinedSymbol(name))
+        }
+    }
+
+    fn range(&self, value: i64, min: i64, max: i64) -> Result<i64, AsmErrorKind>
+    {
+        if self.final_pass && !(min..=max).contains(&value)
+        {
+            return Err(AsmErrorKind::OutOfRange(value));
+        }
+        Ok(value)
+    }
+
+    fn evaluate(&self, text: &str) -> Result<i64, AsmErrorKind>
+    {
+        Parser { tokens: tokenize(text)?, position: 0, assembler: self, allow_unknown: !self.final_pass, text }.parse()
+    }
+
+    //For values that decide an instruction's size or encoding, these have to be known in the first pass
+    fn constant(&self, text: &str) -> Result<i64, AsmErrorKind>
+    {
+        Parser { tokens: tokenize(text)?, position: 0, assembler: self, allow_unknown: false, text }.parse()
+    }
+}
+
+struct Parser<'a>
+{
+    tokens: Vec<Token>,
+    position: usize,
+    assembler: &'a Assembler,
+    allow_unknown: bool,
+    text: &'a str
+}
+
+impl Parser<'_>
+{
+    fn parse(mut self) -> Result<i64, AsmErrorKind>
+    {
+        let value = self.binary(0)?;
+        match self.position == self.tokens.len()
+        {
+            true => Ok(value),
+            false => Err(self.invalid())
+        }
+    }
+
+    fn invalid(&self) -> AsmErrorKind
+    {
+        AsmErrorKind::InvalidExpression(self.text.trim().to_string())
+    }
+
+    fn next_operator(&self, operators: &[&str]) -> Option<&'static str>
+    {
+        match self.tokens.get(self.position)
+        {
+            Some(Token::Operator(x)) if operators.contains(x) => Some(x),
+            _ => None
+        }
+    }
+
+    fn binary(&mut self, level: usize) -> Result<i64, AsmErrorKind>
+    {
+        if level == PRECEDENCE.len()
+        {
+            return self.unary();
+        }
+        let mut value = self.binary(level + 1)?;
+        while let Some(operator) = self.next_operator(PRECEDENCE[level])
+        {
+            self.position += 1;
+            let right = self.binary(level + 1)?;
+            value = match operator
+            {
+                "|" => value | right,
+                "^" => value ^ right,
+                "&" => value & right,
+                "<<" => u32::try_from(right).ok().and_then(|x| value.checked_shl(x)).ok_or_else(|| self.invalid())?,
+                ">>" => u32::try_from(right).ok().and_then(|x| value.checked_shr(x)).ok_or_else(|| self.invalid())?,
+                "+" => value.wrapping_add(right),
+                "-" => value.wrapping_sub(right),
+                "*" => value.wrapping_mul(right),
+                _ => value.checked_div(right).ok_or_else(|| self.invalid())?
+            };
+        }
+        Ok(value)
+    }
+
+    fn unary(&mut self) -> Result<i64, AsmErrorKind>
+    {
+        if let Some(operator) = self.next_operator(&["-", "+", "~"])
+        {
+            self.position += 1;
+            let value = self.unary()?;
+            return Ok(match operator
+            {
+                "-" => value.wrapping_neg(),
+                "~" => !value,
+                _ => value
+            });
+        }
+        let token = self.tokens.get(self.position).cloned().ok_or_else(|| self.invalid())?;
+        self.position += 1;
+        match token
+        {
+            Token::Number(x) => Ok(x),
+            Token::Symbol(name) => self.assembler.symbol(&name, self.allow_unknown),
+            Token::Operator("(") =>
+            {
+                let value = self.binary(0)?;
+                self.next_operator(&[")"]).ok_or_else(|| self.invalid())?;
+                self.position += 1;
+                Ok(value)
+            }
+            _ => Err(self.invalid())
+        }
+    }
+}
+
+//Numbers are decimal, $ or 0x hex, % or 0b binary, or a 'c' character
+fn tokenize(text: &str) -> Result<Vec<Token>, AsmErrorKind>
+{
+    let invalid = || AsmErrorKind::InvalidExpression(text.trim().to_string());
+    let mut tokens = Vec::new();
+    let mut rest = text.trim_start();
+    while !rest.is_empty()
+    {
+        let prefix = |x: &'static str, radix: u32| rest.strip_prefix(x).map(|digits| (digits, x.len(), radix));
+        let number = prefix("$", 16).or_else(|| prefix("0x", 16)).or_else(|| prefix("%", 2)).or_else(|| prefix("0b", 2))
+            .or_else(|| rest.starts_with(|x: char| x.is_ascii_digit()).then_some((rest, 0, 10)));
+        let (token, length) = if let Some(x) = OPERATORS.iter().find(|x| rest.starts_with(**x))
+        {
+            (Token::Operator(x), x.len())
+        }
+        else if let Some((digits, skip, radix)) = number
+        {
+            let length = digits.find(|x: char| !x.is_digit(radix)).unwrap_or(digits.len());
+            let value = i64::from_str_radix(&digits[..length], radix).map_err(|_| invalid())?;
+            (Token::Number(value), skip + length)
+        }
+        else if let Some(x) = rest.strip_prefix('\'')
+        {
+            let character = x.chars().next().ok_or_else(invalid)?;
+            if !x[character.len_utf8()..].starts_with('\'')
+            {
+                return Err(invalid());
+            }
+            (Token::Number(character as i64), character.len_utf8() + 2)
+        }
+        else if rest.starts_with('@')
+        {
+            (Token::Symbol("@".to_string()), 1)
+        }
+        else if rest.starts_with(|x: char| x.is_ascii_alphabetic() || x == '_' || x == '.')
+        {
+            let length = rest.find(|x: char| !is_symbol_char(x)).unwrap_or(rest.len());
+            (Token::Symbol(rest[..length].to_string()), length)
+        }
+        else
+        {
+            return Err(invalid());
+        };
+        tokens.push(token);
+        rest = rest[length..].trim_start();
+    }
+    Ok(tokens)
+}
+
+fn is_symbol_char(x: char) -> bool
+{
+    x.is_ascii_alphanumeric() || x == '_' || x == '.'
+}
+
+fn parse_arg(text: &str) -> Arg
+{
+    let compact = text.split_whitespace().collect::<String>().to_ascii_lowercase();
+    if NAMES.contains(&compact.as_str())
+    {
+        return Arg::Name(compact);
+    }
+    if let Some(inner) = strip_brackets(text)
+    {
+        return match inner.split_whitespace().collect::<String>().to_ascii_lowercase().as_str()
+        {
+            "bc" => Arg::Pointer(Register16::BC),
+            "de" => Arg::Pointer(Register16::DE),
+            "hl" => Arg::Pointer(Register16::HL),
+            "hl+" | "hli" => Arg::HlIncrement,
+            "hl-" | "hld" => Arg::HlDecrement,
+            "c" | "$ff00+c" | "0xff00+c" | "ff00+c" => Arg::HighC,
+            _ => Arg::Memory(inner.to_string())
+        };
+    }
+    let text = text.trim();
+    match text.get(..2)
+    {
+        Some(x) if x.eq_ignore_ascii_case("sp") && text[2..].trim_start().starts_with(['+', '-']) => Arg::StackOffset(text[2..].to_string()),
+        _ => Arg::Value(text.to_string())
+    }
+}
+
+//Inside of [x] or (x) when the brackets wrap the whole operand, (x)+1 is still an expression
+fn strip_brackets(text: &str) -> Option<&str>
+{
+    let text = text.trim();
+    if let Some(inner) = text.strip_prefix('[')
+    {
+        return inner.strip_suffix(']');
+    }
+    let inner = text.strip_prefix('(')?.strip_suffix(')')?;
+    let mut depth = 0;
+    for x in inner.chars()
+    {
+        match x
+        {
+            '(' => depth += 1,
+            ')' if depth == 0 => return None,
+            ')' => depth -= 1,
+            _ => ()
+        }
+    }
+    Some(inner)
+}
+
+//Splits on commas outside of brackets and strings
+fn split_operands(text: &str) -> Vec<&str>
+{
+    let mut operands = Vec::new();
+    let mut depth = 0;
+    let mut quoted = false;
+    let mut start = 0;
+    for (i, x) in text.char_indices()
+    {
+        match x
+        {
+            '"' => quoted = !quoted,
+            '(' | '[' if !quoted => depth += 1,
+            ')' | ']' if !quoted => depth -= 1,
+            ',' if !quoted && depth == 0 =>
+            {
+                operands.push(text[start..i].trim());
+                start = i + 1;
+            }
+            _ => ()
+        }
+    }
+    operands.push(text[start..].trim());
+    operands
+}
+
+fn strip_comment(text: &str) -> &str
+{
+    let mut quoted = false;
+    for (i, x) in text.char_indices()
+    {
+        match x
+        {
+            '"' => quoted = !quoted,
+            ';' if !quoted => return &text[..i],
+            _ => ()
+        }
+    }
+    text
+}
@@ -0,0 +1,117 @@
+use super::*;
+use crate::rom::CartridgeHeader;
+
+fn error(source: &str) -> (usize, AsmErrorKind)
+{
+    let x = assemble(source, 0).unwrap_err();
+    (x.line, x.kind)
+}
+
+#[test]
+fn test_round_trip()
+{
+    //Every opcode's RGBDS disassembly assembles back to the same bytes, illegal ones as db
+    let base = (0..=0xFF_u8).filter(|x| *x != 0xCB).map(|x| if x == 0x10 { [x, 0x00, 0x00] } else { [x, 0x34, 0x12] });
+    let cb = (0..=0xFF_u8).map(|x| [0xCB, x, 0x00]);
+    for bytes in base.chain(cb)
+    {
+        let instruction = disasm::decode(&bytes, 0x0200).unwrap();
+        let text = instruction.to_string();
+        assert_eq!(assemble(&text, 0x0200), Ok(bytes[..instruction.length as usize].to_vec()), "{}", text);
+    }
+}
+
+#[test]
+fn test_labels_and_expressions()
+{
+    let source = "
+COUNT EQU 3
+start:
+    ld b, COUNT * 2         ; 06 06
+.loop:
+    dec b
+    jr nz, .loop
+    jp end
+    db 1, \"A;B\", -1
+    dw start, @
+end:
+    ld a, (end - start) >> 2 | %10000000
+second:
+.loop:
+    jr .loop
+    ld a, 'A' + -(1 + 1)
+";
+    let expected = vec![
+        0x06, 0x06,
+        0x05,
+        0x20, 0xFD,
+        0xC3, 0x11, 0x40,
+        0x01, 0x41, 0x3B, 0x42, 0xFF,
+        0x00, 0x40, 0x0D, 0x40,
+        0x3E, 0x84,
+        0x18, 0xFE,
+        0x3E, 0x3F
+    ];
+    assert_eq!(assemble(source, 0x4000), Ok(expected));
+}
+
+#[test]
+fn test_alternative_syntax()
+{
+    let cases:[(&str, &[u8]); 20] =
+    [
+        ("ldi a, (hl)", &[0x2A]),
+        ("ld a, [hli]", &[0x2A]),
+        ("ldd [hl], a", &[0x32]),
+        ("ld (hl-), a", &[0x32]),
+        ("ld [$FF00 + c], a", &[0xE2]),
+        ("ld a, (ff00+c)", &[0xF2]),
+        ("ldh a, [$FF44]", &[0xF0, 0x44]),
+        ("ldh a, [$44]", &[0xF0, 0x44]),
+        ("ld a, [$FF44]", &[0xFA, 0x44, 0xFF]),
+        ("ld a, ($C000)", &[0xFA, 0x00, 0xC0]),
+        ("ld a, ($10 + 1) * 2", &[0x3E, 0x22]),
+        ("sub a, b", &[0x90]),
+        ("cp a, $10", &[0xFE, 0x10]),
+        ("adc $10", &[0xCE, 0x10]),
+        ("jp (hl)", &[0xE9]),
+        ("LD A, B", &[0x78]),
+        ("ld hl, sp - 2", &[0xF8, 0xFE]),
+        ("rst 8 * 7", &[0xFF]),
+        ("ds 3, $FF", &[0xFF, 0xFF, 0xFF]),
+        ("org 3\nhalt", &[0x00, 0x00, 0x00, 0x76])
+    ];
+    for (source, bytes) in cases
+    {
+        assert_eq!(assemble(source, 0), Ok(bytes.to_vec()), "{}", source);
+    }
+}
+
+#[test]
+fn test_errors()
+{
+    assert_eq!(error("nop\nfoo a"), (2, AsmErrorKind::UnknownMnemonic("foo".to_string())));
+    assert_eq!(error("ld a, sp"), (1, AsmErrorKind::InvalidOperands("ld a, sp".to_string())));
+    assert_eq!(error("jp nowhere"), (1, AsmErrorKind::UndefinedSymbol("nowhere".to_string())));
+    assert_eq!(error("x:\nx:"), (2, AsmErrorKind::DuplicateSymbol("x".to_string())));
+    assert_eq!(error("jr far\nds 200\nfar:"), (1, AsmErrorKind::OutOfRange(200)));
+    assert_eq!(error("ld a, 256"), (1, AsmErrorKind::OutOfRange(256)));
+    assert_eq!(error("ld a, 1 +"), (1, AsmErrorKind::InvalidExpression("1 +".to_string())));
+    assert_eq!(error("db 1\norg 0"), (2, AsmErrorKind::InvalidOrigin(0)));
+    assert_eq!(error("rst 1"), (1, AsmErrorKind::InvalidOperands("rst 1".to_string())));
+
+    let x = assemble_rom("ds $8000", "BIG").unwrap_err();
+    assert_eq!((x.line, x.kind), (0, AsmErrorKind::RomTooLarge(0x8150)));
+    assert_eq!(assemble("ld a, [de", 0).unwrap_err().to_string(), "line 1: invalid expression [de");
+}
+
+#[test]
+fn test_rom_image()
+{
+    let rom = assemble_rom("ld a, 1\nhalt", "ASSEMBLED").unwrap();
+    assert_eq!(rom.len(), 0x8000);
+    assert!(HeaderValidation::new(&rom).is_valid());
+    assert_eq!(CartridgeHeader::new(&rom).unwrap().title, "ASSEMBLED");
+    assert_eq!(rom[0x0100..0x0104], [0x00, 0xC3, 0x50, 0x01]);
+    assert_eq!(rom[0x0150..0x0153], [0x3E, 0x01, 0x76]);
+}
@@ -0,0 +1,161 @@
+use crate::{cpu::Cpu, ram::{self, Ram}, rom::CartridgeHeader};
+
+//DMG style boot ROMs are mapped over 0x0000-0x00FF. The CGB one also fills
+//0x0200-0x08FF, leaving the cartridge header visible in between.
+pub const BOOT_ROM_SIZE:usize = 0x100;
+pub const CGB_BOOT_ROM_SIZE:usize = 0x900;
+
+///Console revision, decides the boot ROM size and the state left behind after boot
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub enum Model
+{
+    Dmg0,
+    #[default]
+    Dmg,
+    Mgb,
+    Sgb,
+    Cgb
+}
+
+impl Model
+{
+    pub fn boot_rom_size(&self) -> usize
+    {
+        match self
+        {
+            Model::Cgb => CGB_BOOT_ROM_SIZE,
+            _ => BOOT_ROM_SIZE
+        }
+    }
+}
+
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub enum BootRomError
+{
+    ///Image size doesn't match the selected model, holds (expected, actual)
+    InvalidSize(usize, usize)
+}
+
+impl std::fmt::Display for BootRomError
+{
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result
+    {
+        match self
+        {
+            BootRomError::InvalidSize(expected, actual) => write!(f, "Boot ROM is {} bytes, expected {}", actual, expected)
+        }
+    }
+}
+
+impl std::error::Error for BootRomError {}
+
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct BootRom
+{
+    pub model: Model,
+    pub bytes: Vec<u8>
+}
+
+impl BootRom
+{
+    pub fn new(model: Model, bytes: Vec<u8>) -> Result<BootRom, BootRomError>
+    {
+        if bytes.len() != model.boot_rom_size()
+        {
+            return Err(BootRomError::InvalidSize(model.boot_rom_size(), bytes.len()));
+        }
+        Ok(BootRom { model, bytes })
+    }
+}
+
+///How a cartridge is started when it's loaded
+#[derive(Clone, PartialEq, Eq, Debug, Default)]
+pub enum BootMode
+{
+    ///The DMG boot ROM bundled with the library
+    #[default]
+    Builtin,
+    ///A dumped boot ROM supplied by the user
+    Rom(BootRom),
+    ///No boot ROM, start at 0x0100 with the state the given model's boot ROM leaves behind
+    Skip(Model)
+}
+
+//----Post Boot I/O Registers----
+//Values shared by every model, from the Pan Docs power up sequence tables
+const IO_STATE:[(u16, u8); 37] =
+[
+    (0xFF00, 0xCF), //P1
+    (0xFF01, 0x00), //SB
+    (ram::TIMA, 0x00),
+    (ram::TMA, 0x00),
+    (ram::TAC, 0xF8),
+    (ram::IF, 0xE1),
+    (0xFF10, 0x80), //NR10
+    (0xFF11, 0xBF), //NR11
+    (0xFF12, 0xF3), //NR12
+    (0xFF13, 0xFF), //NR13
+    (0xFF14, 0xBF), //NR14
+    (0xFF16, 0x3F), //NR21
+    (0xFF17, 0x00), //NR22
+    (0xFF18, 0xFF), //NR23
+    (0xFF19, 0xBF), //NR24
+    (0xFF1A, 0x7F), //NR30
+    (0xFF1B, 0xFF), //NR31
+    (0xFF1C, 0x9F), //NR32
+    (0xFF1D, 0xFF), //NR33
+    (0xFF1E, 0xBF), //NR34
+    (0xFF20, 0xFF), //NR41
+    (0xFF21, 0x00), //NR42
+    (0xFF22, 0x00), //NR43
+    (0xFF23, 0xBF), //NR44
+    (0xFF24, 0x77), //NR50
+    (0xFF25, 0xF3), //NR51
+    (ram::LCDC, 0x91),
+    (ram::SCY, 0x00),
+    (ram::SCX, 0x00),
+    (ram::LY, 0x00),
+    (ram::LYC, 0x00),
+    (ram::BGP, 0xFC),
+    (ram::OBP0, 0xFF),
+    (ram::OBP1, 0xFF),
+    (ram::WY, 0x00),
+    (ram::WX, 0x00),
+    (ram::IE, 0x00)
+];
+
+const SC:u16 = 0xFF02;
+const NR52:u16 = 0xFF26;
+
+///Puts the CPU and I/O registers in the state the model's boot ROM hands over with, and unmaps the boot ROM
+pub fn skip_boot(model: Model, header: &CartridgeHeader, cpu: &mut Cpu, ram: &mut Ram)
+{
+    //The DMG boot ROM leaves H and C set from its header checksum pass
+    let dmg_flags = if header.header_checksum == 0 { 0x80 } else { 0xB0 };
+    let (af, bc, de, hl) = match model
+    {
+        Model::Dmg0 => (0x0100, 0xFF13, 0x00C1, 0x8403),
+        Model::Dmg => (0x0100 | dmg_flags, 0x0013, 0x00D8, 0x014D),
+        Model::Mgb => (0xFF00 | dmg_flags, 0x0013, 0x00D8, 0x014D),
+        Model::Sgb => (0x0100, 0x0014, 0x0000, 0xC060),
+        Model::Cgb => (0x1180, 0x0000, 0xFF56, 0x000D)
+    };
+    cpu.set_post_boot_registers(af, bc, de, hl);
+
+    for (address, data) in IO_STATE
+    {
+        ram.write_register(address, data);
+    }
+    let (div, sc, stat, nr52) = match model
+    {
+        Model::Dmg0 => (0x18, 0x7E, 0x81, 0xF1),
+        Model::Dmg | Model::Mgb => (0xAB, 0x7E, 0x85, 0xF1),
+        Model::Sgb => (0x00, 0x7E, 0x85, 0xF0),
+        Model::Cgb => (0x00, 0x7F, 0x85, 0xF1)
+    };
+    ram.write_register(ram::DIV, div);
+    ram.write_register(SC, sc);
+    ram.write_register(ram::STAT, stat);
+    ram.write_register(NR52, nr52);
+    ram.write(ram::SC_BOOT_ROM_DISABLE, 0x01);
+}
@@ -0,0 +1,249 @@
+#[cfg(test)]
+mod tests;
+
+use crate::ram::Ram;
+
+///Memory as the CPU sees it, each read or write takes one M-cycle.
+///Ram is the real memory map, FlatMemory and TracingBus are for tests and debugging.
+pub trait Bus
+{
+    fn read(&mut self, address: u16) -> u8;
+    fn write(&mut self, address: u16, data: u8);
+    ///Advances the rest of the board by one M-cycle
+    fn tick(&mut self) {}
+    ///Reads a line the CPU watches outside of its memory accesses, without spending a cycle
+    fn peek(&mut self, address: u16) -> u8
+    {
+        self.read(address)
+    }
+    ///Writes a line the CPU drives outside of its memory accesses, like acknowledging an interrupt in IF
+    fn poke(&mut self, address: u16, data: u8)
+    {
+        self.write(address, data);
+    }
+
+    fn read_rp(&mut self, msh: u8, lsh: u8) -> u8
+    {
+        self.read(u16::from_le_bytes([lsh, msh]))
+    }
+
+    fn write_rp(&mut self, msh: u8, lsh: u8, data: u8)
+    {
+        self.write(u16::from_le_bytes([lsh, msh]), data);
+    }
+}
+
+impl Bus for Ram
+{
+    fn read(&mut self, address: u16) -> u8
+    {
+        Ram::read(self, address)
+    }
+
+    fn write(&mut self, address: u16, data: u8)
+    {
+        Ram::write(self, address, data);
+    }
+}
+
+impl<B: Bus + ?Sized> Bus for &mut B
+{
+    fn read(&mut self, address: u16) -> u8
+    {
+        (**self).read(address)
+    }
+
+    fn write(&mut self, address: u16, data: u8)
+    {
+        (**self).write(address, data);
+    }
+
+    fn tick(&mut self)
+    {
+        (**self).tick();
+    }
+
+    fn peek(&mut self, address: u16) -> u8
+    {
+        (**self).peek(address)
+    }
+
+    fn poke(&mut self, address: u16, data: u8)
+    {
+        (**self).poke(address, data);
+    }
+}
+
+///64KiB of plain memory with no mapping or I/O side effects
+pub struct FlatMemory
+{
+    pub mem: Box<[u8; 0x10000]>
+}
+
+impl FlatMemory
+{
+    pub fn new() -> FlatMemory
+    {
+        FlatMemory { mem: Box::new([0; 0x10000]) }
+    }
+
+    ///Copies bytes in starting at the given address
+    pub fn load(&mut self, address: u16, bytes: &[u8])
+    {
+        let start = address as usize;
+        self.mem[start..start + bytes.len()].copy_from_slice(bytes);
+    }
+}
+
+impl Default for FlatMemory
+{
+    fn default() -> Self { Self::new() }
+}
+
+impl Bus for FlatMemory
+{
+    fn read(&mut self, address: u16) -> u8
+    {
+        self.mem[address as usize]
+    }
+
+    fn write(&mut self, address: u16, data: u8)
+    {
+        self.mem[address as usize] = data;
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum AccessKind {Read, Write}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct BusAccess
+{
+    ///Ticks seen before the access
+    pub cycle: u64,
+    pub kind: AccessKind,
+    pub address: u16,
+    pub data: u8
+}
+
+///Passes everything through to another bus and logs each access with the cycle it landed on.
+///Peeks and pokes aren't bus accesses and stay out of the log.
+pub struct TracingBus<B: Bus>
+{
+    bus: B,
+    cycle: u64,
+    accesses: Vec<BusAccess>
+}
+
+impl<B: Bus> TracingBus<B>
+{
+    pub fn new(bus: B) -> TracingBus<B>
+    {
+        TracingBus { bus, cycle: 0, accesses: Vec::new() }
+    }
+
+    pub fn accesses(&self) -> &[BusAccess]
+    {
+        &self.accesses
+    }
+
+    ///Empties the log, the cycle count keeps going
+    pub fn clear(&mut self)
+    {
+        self.accesses.clear();
+    }
+
+    pub fn cycle(&self) -> u64
+    {
+        self.cycle
+    }
+
+    pub fn inner(&mut self) -> &mut B
+    {
+        &mut self.bus
+    }
+
+    pub fn into_inner(self) -> B
+    {
+        self.bus
+    }
+}
+
+impl<B: Bus> Bus for TracingBus<B>
+{
+    fn read(&mut self, address: u16) -> u8
+    {
+        let data = self.bus.read(address);
+        self.accesses.push(BusAccess { cycle: self.cycle, kind: AccessKind::Read, address, data });
+        data
+    }
+
+    fn write(&mut self, address: u16, data: u8)
+    {
+        self.bus.write(address, data);
+        self.accesses.push(BusAccess { cycle: self.cycle, kind: AccessKind::Write, address, data });
+    }
+
+    fn tick(&mut self)
+    {
+        self.bus.tick();
+        self.cycle += 1;
+    }
+
+    fn peek(&mut self, address: u16) -> u8
+    {
+        self.bus.peek(address)
+    }
+
+    fn poke(&mut self, address: u16, data: u8)
+    {
+        self.bus.poke(address, data);
+    }
+}
+
+///Wraps a bus for the length of one instruction, ticking it after every access and counting the cycles used
+pub(crate) struct Clocked<'a, B: Bus>
+{
+    bus: &'a mut B,
+    pub cycles: u8
+}
+
+impl<'a, B: Bus> Clocked<'a, B>
+{
+    pub fn new(bus: &'a mut B) -> Clocked<'a, B>
+    {
+        Clocked { bus, cycles: 0 }
+    }
+}
+
+impl<B: Bus> Bus for Clocked<'_, B>
+{
+    fn read(&mut self, address: u16) -> u8
+    {
+        let data = self.bus.read(address);
+        self.tick();
+        data
+    }
+
+    fn write(&mut self, address: u16, data: u8)
+    {
+        self.bus.write(address, data);
+        self.tick();
+    }
+
+    fn tick(&mut self)
+    {
+        self.bus.tick();
+        self.cycles += 1;
+    }
+
+    fn peek(&mut self, address: u16) -> u8
+    {
+        self.bus.peek(address)
+    }
+
+    fn poke(&mut self, address: u16, data: u8)
+    {
+        self.bus.poke(address, data);
+    }
+}
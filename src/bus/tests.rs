@@ -0,0 +1,63 @@
+use super::*;
+use crate::cpu::Cpu;
+
+#[test]
+fn test_flat_memory()
+{
+    let mut mem = FlatMemory::new();
+    mem.load(0xFFFE, &[0x12, 0x34]);
+    assert_eq!((mem.read(0xFFFE), mem.read(0xFFFF)), (0x12, 0x34));
+    //No boot ROM, cartridge or I/O registers in the way
+    mem.write(0x0000, 0x56);
+    mem.write_rp(0xFF, 0x50, 0x01);
+    assert_eq!(mem.read(0x0000), 0x56);
+    assert_eq!(mem.read_rp(0xFF, 0x50), 0x01);
+}
+
+#[test]
+fn test_tracing_bus()
+{
+    //LD A,(0xC000) run against flat memory
+    let mut mem = FlatMemory::new();
+    mem.load(0x0000, &[0xFA, 0x00, 0xC0]);
+    mem.write(0xC000, 0x99);
+    let mut bus = TracingBus::new(&mut mem);
+    let mut cpu = Cpu::new();
+    assert_eq!(cpu.step(&mut bus), 4);
+    assert_eq!(bus.cycle(), 4);
+
+    let accesses = bus.accesses().iter()
+        .map(|x| (x.cycle, x.kind, x.address, x.data))
+        .collect::<Vec<_>>();
+    assert_eq!(accesses, vec![
+        (0, AccessKind::Read, 0x0000, 0xFA),
+        (1, AccessKind::Read, 0x0001, 0x00),
+        (2, AccessKind::Read, 0x0002, 0xC0),
+        (3, AccessKind::Read, 0xC000, 0x99)
+    ]);
+    bus.clear();
+    assert!(bus.accesses().is_empty());
+    //Peeks and pokes pass through without being logged or spending a cycle
+    bus.poke(0xC000, 0x42);
+    assert_eq!(bus.peek(0xC000), 0x42);
+    assert!(bus.accesses().is_empty());
+    assert_eq!(bus.cycle(), 4);
+}
+
+#[test]
+fn test_read_modify_write_side_effects()
+{
+    //LD HL,0xFF50 then INC (HL) or SET 0,(HL) both unmap the boot ROM like a plain write would
+    for op in [&[0x34][..], &[0xCB, 0xC6][..]]
+    {
+        let mut boot_rom = vec![0x21, 0x50, 0xFF];
+        boot_rom.extend_from_slice(op);
+        boot_rom.resize(0x100, 0x00);
+        let mut ram = Ram::new();
+        ram.set_boot_rom(boot_rom);
+        let mut cpu = Cpu::new();
+        cpu.step(&mut ram);
+        cpu.step(&mut ram);
+        assert_eq!(ram.read(0x0000), 0x00);
+    }
+}
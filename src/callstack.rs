@@ -0,0 +1,110 @@
+#[cfg(test)]
+mod tests;
+
+//Frames past this are dropped oldest first, code that jumps out of calls instead of returning would grow it forever
+const MAX_FRAMES:usize = 1024;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum FrameKind {Call, Rst, Interrupt}
+
+///One return address the CPU pushed on its way into a routine
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct CallFrame
+{
+    pub kind: FrameKind,
+    ///Address of the CALL or RST, or of the instruction an interrupt cut in before
+    pub source: u16,
+    pub target: u16,
+    ///SP after the push, where the return address sits
+    pub sp: u16
+}
+
+///A return that didn't line up with the innermost frame, or a push that landed on top of live frames.
+///Either means SP was moved by hand, or a return address was pushed or dropped without a CALL or RET.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct StackMismatch
+{
+    ///Address of the RET, RETI, CALL or RST, or of the instruction an interrupt cut in before
+    pub pc: u16,
+    pub sp: u16,
+    ///Innermost frame at the time, None when the stack was empty
+    pub expected: Option<CallFrame>
+}
+
+///Shadow of the call stack kept alongside the real one, so a debugger can show a backtrace
+#[derive(Clone, Default, Debug)]
+pub struct CallStack
+{
+    frames: Vec<CallFrame>,
+    mismatch_count: u64,
+    last_mismatch: Option<StackMismatch>
+}
+
+impl CallStack
+{
+    pub fn new() -> CallStack
+    {
+        CallStack::default()
+    }
+
+    ///Outermost frame first
+    pub fn frames(&self) -> &[CallFrame]
+    {
+        &self.frames
+    }
+
+    ///Innermost frame first
+    pub fn backtrace(&self) -> impl Iterator<Item = &CallFrame>
+    {
+        self.frames.iter().rev()
+    }
+
+    pub fn mismatch_count(&self) -> u64
+    {
+        self.mismatch_count
+    }
+
+    pub fn last_mismatch(&self) -> Option<&StackMismatch>
+    {
+        self.last_mismatch.as_ref()
+    }
+
+    pub fn clear(&mut self)
+    {
+        *self = CallStack::default();
+    }
+
+    pub(crate) fn push(&mut self, frame: CallFrame)
+    {
+        //The stack grows down, frames at or below the new one can't be returned to any more
+        if self.frames.last().is_some_and(|x| x.sp <= frame.sp)
+        {
+            self.mismatch(frame.source, frame.sp);
+            self.frames.retain(|x| x.sp > frame.sp);
+        }
+        if self.frames.len() == MAX_FRAMES
+        {
+            self.frames.remove(0);
+        }
+        self.frames.push(frame);
+    }
+
+    ///sp is where the return address was popped from
+    pub(crate) fn pop(&mut self, pc: u16, sp: u16)
+    {
+        if self.frames.last().is_some_and(|x| x.sp == sp)
+        {
+            self.frames.pop();
+            return;
+        }
+        self.mismatch(pc, sp);
+        //Returning past frames unwinds them, a return from above the innermost frame leaves it in place
+        self.frames.retain(|x| x.sp > sp);
+    }
+
+    fn mismatch(&mut self, pc: u16, sp: u16)
+    {
+        self.mismatch_count += 1;
+        self.last_mismatch = Some(StackMismatch { pc, sp, expected: self.frames.last().copied() });
+    }
+}
@@ -0,0 +1,61 @@
+use super::*;
+
+fn frame(kind: FrameKind, source: u16, target: u16, sp: u16) -> CallFrame
+{
+    CallFrame { kind, source, target, sp }
+}
+
+#[test]
+fn test_push_pop()
+{
+    let mut stack = CallStack::new();
+    stack.push(frame(FrameKind::Call, 0x0150, 0x0200, 0xFFFC));
+    stack.push(frame(FrameKind::Interrupt, 0x0203, 0x0040, 0xFFFA));
+    assert_eq!(stack.backtrace().map(|x| x.target).collect::<Vec<u16>>(), [0x0040, 0x0200]);
+    stack.pop(0x0050, 0xFFFA);
+    stack.pop(0x0210, 0xFFFC);
+    assert!(stack.frames().is_empty());
+    assert_eq!((stack.mismatch_count(), stack.last_mismatch()), (0, None));
+}
+
+#[test]
+fn test_mismatches()
+{
+    let mut stack = CallStack::new();
+    let outer = frame(FrameKind::Call, 0x0150, 0x0200, 0xFFFC);
+    let inner = frame(FrameKind::Rst, 0x0200, 0x0038, 0xFFFA);
+    stack.push(outer);
+    stack.push(inner);
+
+    //A pushed address returned to from above the innermost frame leaves it in place
+    stack.pop(0x0040, 0xFFF8);
+    assert_eq!(stack.last_mismatch(), Some(&StackMismatch { pc: 0x0040, sp: 0xFFF8, expected: Some(inner) }));
+    assert_eq!(stack.frames(), [outer, inner]);
+
+    //Returning past the inner frame unwinds it along with the outer one it returned through
+    stack.pop(0x0041, 0xFFFC);
+    assert_eq!(stack.mismatch_count(), 2);
+    assert!(stack.frames().is_empty());
+
+    //SP reset by hand, then a call on top of the abandoned frame
+    stack.push(outer);
+    stack.push(frame(FrameKind::Call, 0x0300, 0x0400, 0xFFFC));
+    assert_eq!(stack.last_mismatch(), Some(&StackMismatch { pc: 0x0300, sp: 0xFFFC, expected: Some(outer) }));
+    assert_eq!(stack.frames().len(), 1);
+    assert_eq!(stack.frames()[0].source, 0x0300);
+
+    stack.clear();
+    assert_eq!((stack.mismatch_count(), stack.frames().len()), (0, 0));
+}
+
+#[test]
+fn test_frame_limit()
+{
+    let mut stack = CallStack::new();
+    for x in 0..MAX_FRAMES as u16 + 1
+    {
+        stack.push(frame(FrameKind::Call, x, x, 0xFFFE - 2 * x));
+    }
+    assert_eq!(stack.frames().len(), MAX_FRAMES);
+    assert_eq!(stack.frames()[0].source, 1);
+}
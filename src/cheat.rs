@@ -0,0 +1,162 @@
+#[cfg(test)]
+mod tests;
+
+use crate::ram::{CARTRIDGE_ROM, EXTERNAL_RAM, HIGH_RAM, WORK_RAM};
+
+//Game Genie compare bytes are stored scrambled
+const GAME_GENIE_COMPARE_XOR:u8 = 0xBA;
+
+//----GameShark Code Types----
+//0x01 writes to the current bank, 0x80-0x8F select a WRAM bank on the Color
+const GAMESHARK_WRITE:u8 = 0x01;
+const GAMESHARK_BANKED_WRITE:std::ops::RangeInclusive<u8> = 0x80..=0x8F;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum CheatCode
+{
+    ///Replaces a ROM byte as the CPU reads it, only when it matches the compare byte if there is one
+    GameGenie { address: u16, value: u8, compare: Option<u8> },
+    ///Written to memory once per frame at the start of VBlank
+    GameShark { code_type: u8, address: u16, value: u8 }
+}
+
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub enum CheatError
+{
+    ///Doesn't look like a Game Genie (ABC-DEF or ABC-DEF-GHI) or GameShark (8 hex digits) code
+    InvalidFormat(String),
+    ///Game Genie codes can only patch ROM, GameShark codes only cartridge RAM, WRAM and HRAM
+    InvalidAddress(u16),
+    ///GameShark type byte isn't one that's emulated
+    UnsupportedType(u8)
+}
+
+impl std::fmt::Display for CheatError
+{
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result
+    {
+        match self
+        {
+            CheatError::InvalidFormat(x) => write!(f, "Unrecognised cheat code \"{}\"", x),
+            CheatError::InvalidAddress(x) => write!(f, "Cheat address 0x{:04X} is outside the memory its code type can change", x),
+            CheatError::UnsupportedType(x) => write!(f, "Unsupported GameShark code type 0x{:02X}", x)
+        }
+    }
+}
+
+impl std::error::Error for CheatError {}
+
+impl CheatCode
+{
+    ///Decodes a code, the format is picked from its shape
+    pub fn parse(code: &str) -> Result<CheatCode, CheatError>
+    {
+        let invalid = || CheatError::InvalidFormat(code.to_string());
+        let trimmed = code.trim();
+        let digits = trimmed.chars()
+            .filter(|x| *x != '-')
+            .map(|x| x.to_digit(16).map(|y| y as u8))
+            .collect::<Option<Vec<u8>>>()
+            .ok_or_else(invalid)?;
+
+        let groups = trimmed.split('-').map(|x| x.len()).collect::<Vec<usize>>();
+        match groups.as_slice()
+        {
+            [6] | [3, 3] | [9] | [3, 3, 3] => CheatCode::game_genie(&digits),
+            [8] => CheatCode::gameshark(&digits),
+            _ => Err(invalid())
+        }
+    }
+
+    //Digits ABC-DEF-GHI: AB is the value, the address is FCDE with F inverted,
+    //and the compare byte is GI rotated right by 2 then XORed. H isn't used.
+    fn game_genie(digits: &[u8]) -> Result<CheatCode, CheatError>
+    {
+        let value = (digits[0] << 4) | digits[1];
+        let address = (((digits[5] ^ 0xF) as u16) << 12) | ((digits[2] as u16) << 8) | ((digits[3] as u16) << 4) | digits[4] as u16;
+        if !CARTRIDGE_ROM.contains(&address)
+        {
+            return Err(CheatError::InvalidAddress(address));
+        }
+        let compare = if digits.len() == 9
+        {
+            Some(((digits[6] << 4) | digits[8]).rotate_right(2) ^ GAME_GENIE_COMPARE_XOR)
+        }
+        else
+        {
+            None
+        };
+        Ok(CheatCode::GameGenie { address, value, compare })
+    }
+
+    //Digits TTVVLLHH: type, value, then the address low byte first
+    fn gameshark(digits: &[u8]) -> Result<CheatCode, CheatError>
+    {
+        let byte = |i: usize| (digits[i * 2] << 4) | digits[i * 2 + 1];
+        let code_type = byte(0);
+        if code_type != GAMESHARK_WRITE && !GAMESHARK_BANKED_WRITE.contains(&code_type)
+        {
+            return Err(CheatError::UnsupportedType(code_type));
+        }
+        let address = u16::from_le_bytes([byte(2), byte(3)]);
+        if ![EXTERNAL_RAM, WORK_RAM, HIGH_RAM].iter().any(|x| x.contains(&address))
+        {
+            return Err(CheatError::InvalidAddress(address));
+        }
+        Ok(CheatCode::GameShark { code_type, address, value: byte(1) })
+    }
+}
+
+///A code as the user entered it, along with whether it's switched on
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Cheat
+{
+    pub code: String,
+    pub decoded: CheatCode,
+    pub enabled: bool
+}
+
+impl Cheat
+{
+    pub fn new(code: &str) -> Result<Cheat, CheatError>
+    {
+        Ok(Cheat { code: code.trim().to_uppercase(), decoded: CheatCode::parse(code)?, enabled: true })
+    }
+}
+
+///ROM substitution checked on every cartridge read
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct RomPatch
+{
+    pub address: u16,
+    pub value: u8,
+    pub compare: Option<u8>
+}
+
+impl RomPatch
+{
+    pub fn apply(&self, address: u16, data: u8) -> Option<u8>
+    {
+        if address == self.address && self.compare.is_none_or(|x| x == data)
+        {
+            Some(self.value)
+        }
+        else
+        {
+            None
+        }
+    }
+}
+
+///Enabled Game Genie codes in the form Ram checks on reads
+pub fn rom_patches(cheats: &[Cheat]) -> Vec<RomPatch>
+{
+    cheats.iter()
+        .filter(|x| x.enabled)
+        .filter_map(|x| match x.decoded
+        {
+            CheatCode::GameGenie { address, value, compare } => Some(RomPatch { address, value, compare }),
+            _ => None
+        })
+        .collect()
+}
@@ -0,0 +1,47 @@
+use super::*;
+
+#[test]
+fn test_game_genie()
+{
+    assert_eq!(CheatCode::parse("3EA-F0B-2A2"), Ok(CheatCode::GameGenie { address: 0x4AF0, value: 0x3E, compare: Some(0x32) }));
+    assert_eq!(CheatCode::parse("3ea-f0b"), Ok(CheatCode::GameGenie { address: 0x4AF0, value: 0x3E, compare: None }));
+    assert_eq!(CheatCode::parse("00A17BC49"), CheatCode::parse("00A-17B-C49"));
+    //Inverted high nibble past 0x7 points outside ROM
+    assert_eq!(CheatCode::parse("3EA-F07"), Err(CheatError::InvalidAddress(0x8AF0)));
+}
+
+#[test]
+fn test_gameshark()
+{
+    assert_eq!(CheatCode::parse("01FF28D1"), Ok(CheatCode::GameShark { code_type: 0x01, address: 0xD128, value: 0xFF }));
+    assert_eq!(CheatCode::parse("810A00C0"), Ok(CheatCode::GameShark { code_type: 0x81, address: 0xC000, value: 0x0A }));
+    assert_eq!(CheatCode::parse("91FF28D1"), Err(CheatError::UnsupportedType(0x91)));
+    assert_eq!(CheatCode::parse("01FF00A0").map(|_| ()), Ok(()));
+    assert_eq!(CheatCode::parse("01FFFEFF").map(|_| ()), Ok(()));
+    //ROM, VRAM, echo RAM, OAM, I/O and IE are off limits
+    for x in ["01FF0040", "01FF0080", "01FF00E0", "01FF00FE", "01FF40FF", "01FFFFFF"]
+    {
+        assert!(matches!(CheatCode::parse(x), Err(CheatError::InvalidAddress(_))), "{}", x);
+    }
+}
+
+#[test]
+fn test_invalid_codes()
+{
+    for x in ["", "01FF28D", "XYZ-123", "3E-AF0B", "01FF-28D1"]
+    {
+        assert!(matches!(CheatCode::parse(x), Err(CheatError::InvalidFormat(_))), "{}", x);
+    }
+}
+
+#[test]
+fn test_rom_patches()
+{
+    let mut cheats = vec![Cheat::new("3EA-F0B-2A2").unwrap(), Cheat::new("01FF28D1").unwrap(), Cheat::new("12A-34B").unwrap()];
+    cheats[2].enabled = false;
+    let patches = rom_patches(&cheats);
+    assert_eq!(patches, vec![RomPatch { address: 0x4AF0, value: 0x3E, compare: Some(0x32) }]);
+    assert_eq!(patches[0].apply(0x4AF0, 0x32), Some(0x3E));
+    assert_eq!(patches[0].apply(0x4AF0, 0x33), None);
+    assert_eq!(patches[0].apply(0x4AF1, 0x32), None);
+}
@@ -1,6 +1,7 @@
 #[cfg(test)]
 mod tests;
-use crate::ram::{self, Ram};
+use std::io::Write;
+use crate::{bus::{Bus, Clocked}, callstack::{CallFrame, CallStack, FrameKind}, ram};
 
 //in an AF situation, A is msh, F is lsh, little endian
 
@@ -15,7 +16,7 @@ bitflags::bitflags!
     }
 }
 
-const ZERO_INSTRUCTION_TIME_TABLE:[u8;0x100] = //M-cycle timings
+pub(crate) const ZERO_INSTRUCTION_TIME_TABLE:[u8;0x100] = //M-cycle timings
     [1,3,2,2,1,1,2,1,5,2,2,2,1,1,2,1,
      1,3,2,2,1,1,2,1,3,2,2,2,1,1,2,1,
      2,3,2,2,1,1,2,1,2,2,2,2,1,1,2,1,
@@ -33,7 +34,7 @@ const ZERO_INSTRUCTION_TIME_TABLE:[u8;0x100] = //M-cycle timings
      3,3,2,0,0,4,2,4,4,1,4,0,0,0,2,4,
      3,3,2,1,0,4,2,4,3,2,4,1,0,0,2,4];
 
-const CB_INSTRUCTION_TIME_TABLE:[u8;0x100] = //M-Cycle timings
+pub(crate) const CB_INSTRUCTION_TIME_TABLE:[u8;0x100] = //M-Cycle timings
     [2,2,2,2,2,2,4,2,2,2,2,2,2,2,4,2,
      2,2,2,2,2,2,4,2,2,2,2,2,2,2,4,2,
      2,2,2,2,2,2,4,2,2,2,2,2,2,2,4,2,
@@ -51,6 +52,297 @@ const CB_INSTRUCTION_TIME_TABLE:[u8;0x100] = //M-Cycle timings
      2,2,2,2,2,2,4,2,2,2,2,2,2,2,4,2,
      2,2,2,2,2,2,4,2,2,2,2,2,2,2,4,2];
 
+//----Interrupt Dispatch----
+//Vectors are 8 bytes apart starting from VBlank, in the same order as the IF/IE bits
+const INTERRUPT_VECTOR_BASE:u16 = 0x0040;
+const INTERRUPT_MASK:u8 = 0b00011111;
+//Low nibble of P1, a line reads 0 while a selected button on it is held
+const JOYPAD_LINES:u8 = 0x0F;
+const INTERRUPT_DISPATCH_CYCLES:u8 = 5;
+//EI takes effect after the instruction that follows it
+const EI_DELAY:u8 = 2;
+
+//----Instruction Decoding----
+//Every opcode is decoded once into an Op with operand descriptors, so one handler covers
+//all the registers an instruction can take, (HL) included. The disassembler reads the same tables.
+pub(crate) const CB_PREFIX:u8 = 0xCB;
+
+///8-bit operand in bits 0-2 or 3-5 of an opcode, Hl is the byte at (HL)
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub(crate) enum R8 {B, C, D, E, H, L, Hl, A}
+
+///Register pair in bits 4-5, SP for loads and arithmetic, AF for the stack
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub(crate) enum R16 {Bc, De, Hl, Sp, Af}
+
+///Address of LD (r16), A and LD A, (r16), HL moves after the access
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub(crate) enum Indirect {Bc, De, HlIncrement, HlDecrement}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub(crate) enum Cond
+{
+    Always,
+    Flag(CpuFlags),
+    NotFlag(CpuFlags)
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub(crate) enum Alu {Add, Adc, Sub, Sbc, And, Xor, Or, Cp}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub(crate) enum Op
+{
+    Nop,
+    LdR16Imm(R16),
+    StoreA(Indirect),
+    LoadA(Indirect),
+    IncR16(R16),
+    DecR16(R16),
+    IncR8(R8),
+    DecR8(R8),
+    LdR8Imm(R8),
+    Rlca,
+    Rrca,
+    Rla,
+    Rra,
+    Daa,
+    Cpl,
+    Scf,
+    Ccf,
+    StoreSp,
+    AddHl(R16),
+    Stop,
+    Jr(Cond),
+    Halt,
+    Ld(R8, R8),
+    Alu(Alu, R8),
+    AluImm(Alu),
+    Ret(Cond),
+    Reti,
+    Pop(R16),
+    Push(R16),
+    Jp(Cond),
+    JpHl,
+    Call(Cond),
+    Rst(u8),
+    LdhStore,
+    LdhLoad,
+    LdhStoreC,
+    LdhLoadC,
+    StoreAbsolute,
+    LoadAbsolute,
+    AddSp,
+    LdHlSp,
+    LdSpHl,
+    Di,
+    Ei,
+    Prefix,
+    Illegal
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub(crate) enum Shift {Rlc, Rrc, Rl, Rr, Sla, Sra, Swap, Srl}
+
+///CB-prefixed opcode, the u8 is the bit number
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub(crate) enum CbOp
+{
+    Shift(Shift, R8),
+    Bit(u8, R8),
+    Res(u8, R8),
+    Set(u8, R8)
+}
+
+const R8_OPERANDS:[R8;8] = [R8::B, R8::C, R8::D, R8::E, R8::H, R8::L, R8::Hl, R8::A];
+const R16_OPERANDS:[R16;4] = [R16::Bc, R16::De, R16::Hl, R16::Sp];
+const R16_STACK_OPERANDS:[R16;4] = [R16::Bc, R16::De, R16::Hl, R16::Af];
+const INDIRECT_OPERANDS:[Indirect;4] = [Indirect::Bc, Indirect::De, Indirect::HlIncrement, Indirect::HlDecrement];
+const CONDITIONS:[Cond;4] =
+    [Cond::NotFlag(CpuFlags::FLAG_Z), Cond::Flag(CpuFlags::FLAG_Z), Cond::NotFlag(CpuFlags::FLAG_C), Cond::Flag(CpuFlags::FLAG_C)];
+const ALU_OPERATIONS:[Alu;8] = [Alu::Add, Alu::Adc, Alu::Sub, Alu::Sbc, Alu::And, Alu::Xor, Alu::Or, Alu::Cp];
+const SHIFTS:[Shift;8] = [Shift::Rlc, Shift::Rrc, Shift::Rl, Shift::Rr, Shift::Sla, Shift::Sra, Shift::Swap, Shift::Srl];
+
+//Opcode fields are xxyyyzzz, with yyy split into ppq
+const fn decode(opcode: u8) -> Op
+{
+    let x = opcode >> 6;
+    let y = ((opcode >> 3) & 7) as usize;
+    let z = (opcode & 7) as usize;
+    let p = y >> 1;
+    let q = y & 1;
+    match (x, z)
+    {
+        (0, 0) => match y
+        {
+            0 => Op::Nop,
+            1 => Op::StoreSp,
+            2 => Op::Stop,
+            3 => Op::Jr(Cond::Always),
+            _ => Op::Jr(CONDITIONS[y - 4])
+        },
+        (0, 1) => if q == 0 { Op::LdR16Imm(R16_OPERANDS[p]) } else { Op::AddHl(R16_OPERANDS[p]) },
+        (0, 2) => if q == 0 { Op::StoreA(INDIRECT_OPERANDS[p]) } else { Op::LoadA(INDIRECT_OPERANDS[p]) },
+        (0, 3) => if q == 0 { Op::IncR16(R16_OPERANDS[p]) } else { Op::DecR16(R16_OPERANDS[p]) },
+        (0, 4) => Op::IncR8(R8_OPERANDS[y]),
+        (0, 5) => Op::DecR8(R8_OPERANDS[y]),
+        (0, 6) => Op::LdR8Imm(R8_OPERANDS[y]),
+        (0, _) => [Op::Rlca, Op::Rrca, Op::Rla, Op::Rra, Op::Daa, Op::Cpl, Op::Scf, Op::Ccf][y],
+        (1, 6) if y == 6 => Op::Halt,
+        (1, _) => Op::Ld(R8_OPERANDS[y], R8_OPERANDS[z]),
+        (2, _) => Op::Alu(ALU_OPERATIONS[y], R8_OPERANDS[z]),
+        (_, 0) => match y
+        {
+            0..=3 => Op::Ret(CONDITIONS[y]),
+            4 => Op::LdhStore,
+            5 => Op::AddSp,
+            6 => Op::LdhLoad,
+            _ => Op::LdHlSp
+        },
+        (_, 1) => if q == 0 { Op::Pop(R16_STACK_OPERANDS[p]) } else { [Op::Ret(Cond::Always), Op::Reti, Op::JpHl, Op::LdSpHl][p] },
+        (_, 2) => match y
+        {
+            0..=3 => Op::Jp(CONDITIONS[y]),
+            4 => Op::LdhStoreC,
+            5 => Op::StoreAbsolute,
+            6 => Op::LdhLoadC,
+            _ => Op::LoadAbsolute
+        },
+        (_, 3) => match y
+        {
+            0 => Op::Jp(Cond::Always),
+            1 => Op::Prefix,
+            6 => Op::Di,
+            7 => Op::Ei,
+            _ => Op::Illegal
+        },
+        (_, 4) => if y < 4 { Op::Call(CONDITIONS[y]) } else { Op::Illegal },
+        (_, 5) => if q == 0 { Op::Push(R16_STACK_OPERANDS[p]) } else if p == 0 { Op::Call(Cond::Always) } else { Op::Illegal },
+        (_, 6) => Op::AluImm(ALU_OPERATIONS[y]),
+        _ => Op::Rst(y as u8 * 8)
+    }
+}
+
+//CB opcodes are xxyyyzzz, x picks the group, y the shift or bit and z the operand
+const fn decode_cb(opcode: u8) -> CbOp
+{
+    let y = (opcode >> 3) & 7;
+    let r = R8_OPERANDS[(opcode & 7) as usize];
+    match opcode >> 6
+    {
+        0 => CbOp::Shift(SHIFTS[y as usize], r),
+        1 => CbOp::Bit(y, r),
+        2 => CbOp::Res(y, r),
+        _ => CbOp::Set(y, r)
+    }
+}
+
+pub(crate) const OPS:[Op;0x100] =
+{
+    let mut table = [Op::Nop;0x100];
+    let mut i = 0;
+    while i < 0x100
+    {
+        table[i] = decode(i as u8);
+        i += 1;
+    }
+    table
+};
+
+pub(crate) const CB_OPS:[CbOp;0x100] =
+{
+    let mut table = [CbOp::Bit(0, R8::B);0x100];
+    let mut i = 0;
+    while i < 0x100
+    {
+        table[i] = decode_cb(i as u8);
+        i += 1;
+    }
+    table
+};
+
+//----Dispatch----
+//Every opcode gets its own copy of the executor with its Op as a constant, so the match on the
+//Op folds away and dispatch is a single indirect call through the table
+type Handler<B> = fn(&mut Cpu, &mut B, u16, u8) -> u8;
+type CbHandler<B> = fn(&mut Cpu, &mut B) -> u8;
+
+fn handler<B: Bus, const OPCODE: u8>(cpu: &mut Cpu, ram: &mut B, address: u16, pending: u8) -> u8
+{
+    let op = OPS[OPCODE as usize];
+    let sp = cpu.sp;
+    match op
+    {
+        Op::Illegal => cpu.lock_up(address, OPCODE),
+        _ => cpu.execute_op(ram, op, pending)
+    }
+    cpu.track_call_stack(op, address, sp);
+    ZERO_INSTRUCTION_TIME_TABLE[OPCODE as usize]
+}
+
+fn cb_handler<B: Bus, const OPCODE: u8>(cpu: &mut Cpu, ram: &mut B) -> u8
+{
+    cpu.execute_cb(ram, CB_OPS[OPCODE as usize]);
+    CB_INSTRUCTION_TIME_TABLE[OPCODE as usize]
+}
+
+macro_rules! handler_row
+{
+    ($handler:ident, $high:literal) =>
+    {
+        [
+            $handler::<B, {$high | 0x0}>, $handler::<B, {$high | 0x1}>, $handler::<B, {$high | 0x2}>, $handler::<B, {$high | 0x3}>,
+            $handler::<B, {$high | 0x4}>, $handler::<B, {$high | 0x5}>, $handler::<B, {$high | 0x6}>, $handler::<B, {$high | 0x7}>,
+            $handler::<B, {$high | 0x8}>, $handler::<B, {$high | 0x9}>, $handler::<B, {$high | 0xA}>, $handler::<B, {$high | 0xB}>,
+            $handler::<B, {$high | 0xC}>, $handler::<B, {$high | 0xD}>, $handler::<B, {$high | 0xE}>, $handler::<B, {$high | 0xF}>
+        ]
+    };
+}
+
+macro_rules! handler_table
+{
+    ($handler:ident) =>
+    {
+        flatten([
+            handler_row!($handler, 0x00), handler_row!($handler, 0x10), handler_row!($handler, 0x20), handler_row!($handler, 0x30),
+            handler_row!($handler, 0x40), handler_row!($handler, 0x50), handler_row!($handler, 0x60), handler_row!($handler, 0x70),
+            handler_row!($handler, 0x80), handler_row!($handler, 0x90), handler_row!($handler, 0xA0), handler_row!($handler, 0xB0),
+            handler_row!($handler, 0xC0), handler_row!($handler, 0xD0), handler_row!($handler, 0xE0), handler_row!($handler, 0xF0)
+        ])
+    };
+}
+
+const fn flatten<T: Copy>(rows: [[T;0x10];0x10]) -> [T;0x100]
+{
+    let mut table = [rows[0][0];0x100];
+    let mut i = 0;
+    while i < 0x100
+    {
+        table[i] = rows[i >> 4][i & 0xF];
+        i += 1;
+    }
+    table
+}
+
+struct Handlers<B>(std::marker::PhantomData<B>);
+
+impl<B: Bus> Handlers<B>
+{
+    const BASE:[Handler<B>;0x100] = handler_table!(handler);
+    const CB:[CbHandler<B>;0x100] = handler_table!(cb_handler);
+}
+
+///How the CPU's memory accesses line up with the rest of the board
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub enum CpuTiming
+{
+    ///Each instruction runs on its first M-cycle, then the CPU idles for its remaining length
+    #[default]
+    Instruction,
+    ///Every memory access gets its own M-cycle, with the board ticking between them
+    MCycle
+}
+
 #[derive(Clone, Copy)]
 pub struct ProgramCounter
 {
@@ -60,6 +352,71 @@ pub struct ProgramCounter
     pub current_instruction_cycles: u8
 }
 
+///Copy of the programmer visible CPU state, for debuggers and for setting up a test state
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct Registers
+{
+    pub a: u8,
+    pub f: CpuFlags,
+    pub b: u8,
+    pub c: u8,
+    pub d: u8,
+    pub e: u8,
+    pub h: u8,
+    pub l: u8,
+    pub sp: u16,
+    pub pc: u16,
+    pub ime: bool,
+    pub halted: bool,
+    pub stopped: bool
+}
+
+impl Registers
+{
+    pub fn af(&self) -> u16
+    {
+        u16::from_be_bytes([self.a, self.f.bits()])
+    }
+
+    pub fn bc(&self) -> u16
+    {
+        u16::from_be_bytes([self.b, self.c])
+    }
+
+    pub fn de(&self) -> u16
+    {
+        u16::from_be_bytes([self.d, self.e])
+    }
+
+    pub fn hl(&self) -> u16
+    {
+        u16::from_be_bytes([self.h, self.l])
+    }
+
+    ///The low nibble of F doesn't exist and is dropped
+    pub fn set_af(&mut self, x: u16)
+    {
+        let [a, f] = x.to_be_bytes();
+        self.a = a;
+        self.f = CpuFlags::from_bits_truncate(f);
+    }
+
+    pub fn set_bc(&mut self, x: u16)
+    {
+        [self.b, self.c] = x.to_be_bytes();
+    }
+
+    pub fn set_de(&mut self, x: u16)
+    {
+        [self.d, self.e] = x.to_be_bytes();
+    }
+
+    pub fn set_hl(&mut self, x: u16)
+    {
+        [self.h, self.l] = x.to_be_bytes();
+    }
+}
+
 pub struct Cpu
 {
     reg_a: u8,
@@ -73,8 +430,14 @@ pub struct Cpu
     sp: u16,
     pc: ProgramCounter,
     ime: bool,
+    ei_delay: u8,
     pub halted: bool,
-    pub stopped: bool
+    pub stopped: bool,
+    pub locked: bool,
+    lock_up_report: Option<(u16, u8)>,
+    trace: Option<Box<dyn Write>>,
+    trace_error: Option<std::io::Error>,
+    call_stack: CallStack
 }
 
 impl Cpu
@@ -83,24 +446,75 @@ impl Cpu
     {
         Cpu
         {
-            reg_a: 0x01,
+            reg_a: 0x00,
             reg_b: 0x00,
-            reg_c: 0x13,
+            reg_c: 0x00,
             reg_d: 0x00,
-            reg_e: 0xD8,
-            reg_f: CpuFlags::from_bits(0xB0).unwrap(),
-            reg_h: 0x01,
-            reg_l: 0x4D,
+            reg_e: 0x00,
+            reg_f: CpuFlags::empty(),
+            reg_h: 0x00,
+            reg_l: 0x00,
             sp: 0xFFFE,
             pc: ProgramCounter
             {
                 reg: 0x0000, should_increment: true, current_instruction_width: 1, current_instruction_cycles: 0
             },
             ime: false,
+            ei_delay: 0,
             halted: false,
-            stopped: false
+            stopped: false,
+            locked: false,
+            lock_up_report: None,
+            trace: None,
+            trace_error: None,
+            call_stack: CallStack::new()
         }
     }
+
+    ///Register values a boot ROM leaves behind, execution continues at the cartridge entry point
+    pub fn set_post_boot_registers(&mut self, af: u16, bc: u16, de: u16, hl: u16)
+    {
+        [self.reg_a, self.reg_b, self.reg_c, self.reg_d, self.reg_e, self.reg_h, self.reg_l] =
+            [(af >> 8) as u8, (bc >> 8) as u8, bc as u8, (de >> 8) as u8, de as u8, (hl >> 8) as u8, hl as u8];
+        self.reg_f = CpuFlags::from_bits_truncate(af as u8);
+        self.sp = 0xFFFE;
+        self.pc.reg = 0x0100;
+    }
+
+    pub fn registers(&self) -> Registers
+    {
+        Registers
+        {
+            a: self.reg_a,
+            f: self.reg_f,
+            b: self.reg_b,
+            c: self.reg_c,
+            d: self.reg_d,
+            e: self.reg_e,
+            h: self.reg_h,
+            l: self.reg_l,
+            sp: self.sp,
+            pc: self.pc.reg,
+            ime: self.ime,
+            halted: self.halted,
+            stopped: self.stopped
+        }
+    }
+
+    ///Overwrites the registers, setting IME directly cancels a pending EI
+    pub fn set_registers(&mut self, registers: Registers)
+    {
+        [self.reg_a, self.reg_b, self.reg_c, self.reg_d, self.reg_e, self.reg_h, self.reg_l] =
+            [registers.a, registers.b, registers.c, registers.d, registers.e, registers.h, registers.l];
+        self.reg_f = registers.f;
+        self.sp = registers.sp;
+        self.pc.reg = registers.pc;
+        self.ime = registers.ime;
+        self.ei_delay = 0;
+        self.halted = registers.halted;
+        self.stopped = registers.stopped;
+    }
+
     //Format [name]_[param1]_[param2]
     //r is a register
     //sp/pc are stack pointer and program counter
@@ -113,10 +527,21 @@ impl Cpu
         self.halted = true;
     }
 
-    fn stop(&mut self)
+    //With no button held STOP stops every clock until a joypad line goes low, with one held it's HALT
+    //unless an interrupt is pending. The byte after it is skipped when no interrupt is pending.
+    fn stop<B: Bus>(&mut self, ram: &mut B, pending: u8)
     {
-        self.halted = true;
-        self.stopped = true;
+        if pending == 0
+        {
+            self.pc.reg = self.pc.reg.wrapping_add(1);
+        }
+        let button_held = ram.peek(ram::P1) & JOYPAD_LINES != JOYPAD_LINES;
+        match button_held
+        {
+            true if pending != 0 => {},
+            true => {self.halted = true;},
+            false => {self.stopped = true;}
+        }
     }
 
     fn ld_r16_16(msh_reg: &mut u8, lsh_reg: &mut u8, msh_num: u8, lsh_num: u8)
@@ -125,22 +550,11 @@ impl Cpu
         *lsh_reg = lsh_num;
     }
 
-    fn ld_hl_sp_plus(sp: &mut u16, reg_h: &mut u8, reg_l: &mut u8, p1: i8)
+    fn ld_hl_sp_plus(sp: &mut u16, reg_h: &mut u8, reg_l: &mut u8, p1: i8, flags: &mut CpuFlags)
     {
-        let conv = p1.unsigned_abs() as u16;
-        let negative = p1 < 0;
-        if negative
-        {
-            let bytes = u16::to_le_bytes(*sp - conv);
-            *reg_h = bytes[1];
-            *reg_l = bytes[0];
-        }
-        else
-        {
-            let bytes = u16::to_le_bytes(*sp + conv);
-            *reg_h = bytes[1];
-            *reg_l = bytes[0];
-        }
+        let bytes = u16::to_le_bytes(Cpu::aux_sp_plus_i8(*sp, p1, flags));
+        *reg_h = bytes[1];
+        *reg_l = bytes[0];
     }
 
     fn ld_sp_16(sp: &mut u16, msh_num: u8, lsh_num: u8)
@@ -153,7 +567,7 @@ impl Cpu
         *p1 = p2;
     }
 
-    fn ld_16a_sp(sp: &mut u16, ram: &mut Ram, msh: u8, lsh: u8)
+    fn ld_16a_sp<B: Bus>(sp: &mut u16, ram: &mut B, msh: u8, lsh: u8)
     {
         let bytes = sp.to_le_bytes();
         ram.write_rp(msh, lsh, bytes[0]);
@@ -162,16 +576,6 @@ impl Cpu
         ram.write_rp(result.1, result.0, bytes[1]);
     }
 
-    fn ld_r8_r8(p1: &mut u8, p2: &mut u8)
-    {
-        *p1 = *p2;
-    }
-
-    fn ld_r8_r8_s(_p1: &mut u8)
-    {
-        // *p1 = *p1; Basically a NOP
-    }
-
     fn ld_sp_r16(sp: &mut u16, msh: &mut u8, lsh: &mut u8)
     {
         *sp = u16::from_le_bytes([*lsh, *msh]);
@@ -243,32 +647,6 @@ impl Cpu
         *sp = result.0;
     }
 
-    fn add_r8_r8(p1: &mut u8, p2: &mut u8, flags: &mut CpuFlags)
-    {
-        let half_carry_pre = ((*p1 ^ *p2) >> 4) & 1;
-        let result = p1.overflowing_add(*p2);
-        *p1 = result.0;
-        let half_carry_post = (result.0 >> 4) & 1;
-
-        flags.set(CpuFlags::FLAG_Z, result.0 == 0);
-        flags.set(CpuFlags::FLAG_H, half_carry_pre != half_carry_post);
-        flags.set(CpuFlags::FLAG_N, false);
-        flags.set(CpuFlags::FLAG_C, result.1);
-    }
-
-    fn add_r8_r8_s(p1: &mut u8, flags: &mut CpuFlags)
-    {
-        let half_carry_pre = 0; //((*p1 ^ *p1) >> 4) & 1;
-        let result = p1.overflowing_add(*p1);
-        *p1 = result.0;
-        let half_carry_post = (result.0 >> 4) & 1;
-
-        flags.set(CpuFlags::FLAG_Z, result.0 == 0);
-        flags.set(CpuFlags::FLAG_H, half_carry_pre != half_carry_post);
-        flags.set(CpuFlags::FLAG_N, false);
-        flags.set(CpuFlags::FLAG_C, result.1);
-    }
-
     fn add_r8_8(p1: &mut u8, p2: u8, flags: &mut CpuFlags)
     {
         let half_carry_pre = ((*p1 ^ p2) >> 4) & 1;
@@ -282,98 +660,34 @@ impl Cpu
         flags.set(CpuFlags::FLAG_C, result.1);
     }
 
-    fn add_r16_r16(p1_msh: &mut u8, p1_lsh: &mut u8, p2_msh: &mut u8, p2_lsh: &mut u8, flags: &mut CpuFlags)
+    ///HL + r16, Z is left alone
+    #[inline(always)]
+    fn add_r16_16(p1_msh: &mut u8, p1_lsh: &mut u8, p2: u16, flags: &mut CpuFlags)
     {
+        let [p2_lsh, p2_msh] = p2.to_le_bytes();
         let z = flags.contains(CpuFlags::FLAG_Z);
-        Cpu::add_r8_r8(p1_lsh, p2_lsh, flags);
-        Cpu::adc_r8_r8(p1_msh, p2_msh, flags);
+        Cpu::add_r8_8(p1_lsh, p2_lsh, flags);
+        Cpu::adc_r8_8(p1_msh, p2_msh, flags);
 
         flags.set(CpuFlags::FLAG_Z, z);
         flags.set(CpuFlags::FLAG_N, false);
     }
 
-    fn add_r16_r16_s(p1_msh: &mut u8, p1_lsh: &mut u8, flags: &mut CpuFlags)
-    {
-        let z = flags.contains(CpuFlags::FLAG_Z);
-        Cpu::add_r8_r8_s(p1_lsh, flags);
-        Cpu::adc_r8_r8_s(p1_msh, flags);
-
-        flags.set(CpuFlags::FLAG_Z, z);
-        flags.set(CpuFlags::FLAG_N, false);
-    }
-
-    fn add_r16_sp(p1_msh: &mut u8, p1_lsh: &mut u8, sp: &mut u16, flags: &mut CpuFlags)
-    {
-        let z = flags.contains(CpuFlags::FLAG_Z);
-        let reg = sp.to_le_bytes();
-
-        //ADD
-        let result = p1_lsh.overflowing_add(reg[0]);
-        *p1_lsh = result.0;
-        flags.set(CpuFlags::FLAG_C, result.1);
-
-        //ADC
-        let carry = flags.contains(CpuFlags::FLAG_C) as u8;
-        let half_carry_pre1 = ((*p1_msh ^ reg[1]) >> 4) & 1;
-        let result1 = p1_msh.overflowing_add(reg[1]);
-        let half_carry_post1 = (result1.0 >> 4) & 1;
-        let half_carry_pre2 = ((result1.0 ^ carry) >> 4) & 1;
-        let result2 = result1.0.overflowing_add(carry);
-        *p1_msh = result2.0;
-        let half_carry_post2 = (result2.0 >> 4) & 1;
-
-        flags.set(CpuFlags::FLAG_H, half_carry_pre1 != half_carry_post1 || half_carry_pre2 != half_carry_post2);
-        flags.set(CpuFlags::FLAG_N, false);
-        flags.set(CpuFlags::FLAG_C, result1.1 || result2.1);
-        flags.set(CpuFlags::FLAG_Z, z);
-    }
-
-    fn add_sp_i8(sp: &mut u16, p1: i8)
+    fn add_sp_i8(sp: &mut u16, p1: i8, flags: &mut CpuFlags)
     {
-        let conv = p1.unsigned_abs() as u16;
-        let negative = p1 < 0;
-        if negative
-        {
-            *sp -= conv;
-        }
-        else
-        {
-            *sp += conv;
-        }
+        *sp = Cpu::aux_sp_plus_i8(*sp, p1, flags);
     }
 
-    fn adc_r8_r8(p1: &mut u8, p2: &mut u8, flags: &mut CpuFlags)
+    //Shared by ADD SP,e8 and LD HL,SP+e8, H and C come from adding the offset to SP's low byte as unsigned
+    fn aux_sp_plus_i8(sp: u16, p1: i8, flags: &mut CpuFlags) -> u16
     {
-        let carry = flags.contains(CpuFlags::FLAG_C) as u8;
-        let half_carry_pre1 = ((*p1 ^ *p2) >> 4) & 1;
-        let result1 = p1.overflowing_add(*p2);
-        let half_carry_post1 = (result1.0 >> 4) & 1;
-        let half_carry_pre2 = ((result1.0 ^ carry) >> 4) & 1;
-        let result2 = result1.0.overflowing_add(carry);
-        *p1 = result2.0;
-        let half_carry_post2 = (result2.0 >> 4) & 1;
-
-        flags.set(CpuFlags::FLAG_Z, result2.0 == 0);
-        flags.set(CpuFlags::FLAG_H, half_carry_pre1 != half_carry_post1 || half_carry_pre2 != half_carry_post2);
-        flags.set(CpuFlags::FLAG_N, false);
-        flags.set(CpuFlags::FLAG_C, result1.1 || result2.1);
-    }
-
-    fn adc_r8_r8_s(p1: &mut u8, flags: &mut CpuFlags)
-    {
-        let carry = flags.contains(CpuFlags::FLAG_C) as u8;
-        let half_carry_pre1 = 0; //((*p1 ^ *p1) >> 4) & 1;
-        let result1 = p1.overflowing_add(*p1);
-        let half_carry_post1 = (result1.0 >> 4) & 1;
-        let half_carry_pre2 = ((result1.0 ^ carry) >> 4) & 1;
-        let result2 = result1.0.overflowing_add(carry);
-        *p1 = result2.0;
-        let half_carry_post2 = (result2.0 >> 4) & 1;
-
-        flags.set(CpuFlags::FLAG_Z, result2.0 == 0);
-        flags.set(CpuFlags::FLAG_H, half_carry_pre1 != half_carry_post1 || half_carry_pre2 != half_carry_post2);
+        let low = sp as u8;
+        let offset = p1 as u8;
+        flags.set(CpuFlags::FLAG_Z, false);
         flags.set(CpuFlags::FLAG_N, false);
-        flags.set(CpuFlags::FLAG_C, result1.1 || result2.1);
+        flags.set(CpuFlags::FLAG_H, (low & 0x0F) + (offset & 0x0F) > 0x0F);
+        flags.set(CpuFlags::FLAG_C, low.overflowing_add(offset).1);
+        sp.wrapping_add_signed(p1 as i16)
     }
 
     fn adc_r8_8(p1: &mut u8, p2: u8, flags: &mut CpuFlags)
@@ -394,32 +708,6 @@ impl Cpu
     }
 
     //TODO: Check subtraction half carry calculations
-    fn sub_r8_r8(p1: &mut u8, p2: &mut u8, flags: &mut CpuFlags)
-    {
-        let half_carry_pre = ((*p1 ^ *p2) >> 4) & 1;
-        let result = p1.overflowing_sub(*p2);
-        *p1 = result.0;
-        let half_carry_post = (result.0 >> 4) & 1;
-
-        flags.set(CpuFlags::FLAG_Z, result.0 == 0);
-        flags.set(CpuFlags::FLAG_H, half_carry_pre != half_carry_post);
-        flags.set(CpuFlags::FLAG_N, true);
-        flags.set(CpuFlags::FLAG_C, result.1);
-    }
-
-    fn sub_r8_r8_s(p1: &mut u8, flags: &mut CpuFlags)
-    {
-        let half_carry_pre = 0; //((*p1 ^ *p1) >> 4) & 1;
-        let result = p1.overflowing_sub(*p1);
-        *p1 = result.0;
-        let half_carry_post = (result.0 >> 4) & 1;
-
-        flags.set(CpuFlags::FLAG_Z, result.0 == 0);
-        flags.set(CpuFlags::FLAG_H, half_carry_pre != half_carry_post);
-        flags.set(CpuFlags::FLAG_N, true);
-        flags.set(CpuFlags::FLAG_C, result.1);
-    }
-
     fn sub_r8_8(p1: &mut u8, p2: u8, flags: &mut CpuFlags)
     {
         let half_carry_pre = ((*p1 ^ p2) >> 4) & 1;
@@ -433,40 +721,6 @@ impl Cpu
         flags.set(CpuFlags::FLAG_C, result.1);
     }
 
-    fn sbc_r8_r8(p1: &mut u8, p2: &mut u8, flags: &mut CpuFlags)
-    {
-        let carry = flags.contains(CpuFlags::FLAG_C) as u8;
-        let half_carry_pre1 = ((*p1 ^ *p2) >> 4) & 1;
-        let result1 = p1.overflowing_sub(*p2);
-        let half_carry_post1 = (result1.0 >> 4) & 1;
-        let half_carry_pre2 = ((result1.0 ^ carry) >> 4) & 1;
-        let result2 = result1.0.overflowing_sub(carry);
-        *p1 = result2.0;
-        let half_carry_post2 = (result2.0 >> 4) & 1;
-
-        flags.set(CpuFlags::FLAG_Z, result2.0 == 0);
-        flags.set(CpuFlags::FLAG_H, half_carry_pre1 != half_carry_post1 || half_carry_pre2 != half_carry_post2);
-        flags.set(CpuFlags::FLAG_N, true);
-        flags.set(CpuFlags::FLAG_C, result1.1 || result2.1);
-    }
-
-    fn sbc_r8_r8_s(p1: &mut u8, flags: &mut CpuFlags)
-    {
-        let carry = flags.contains(CpuFlags::FLAG_C) as u8;
-        let half_carry_pre1 = 0; //((*p1 ^ *p1) >> 4) & 1;
-        let result1 = p1.overflowing_sub(*p1);
-        let half_carry_post1 = (result1.0 >> 4) & 1;
-        let half_carry_pre2 = ((result1.0 ^ carry) >> 4) & 1;
-        let result2 = result1.0.overflowing_sub(carry);
-        *p1 = result2.0;
-        let half_carry_post2 = (result2.0 >> 4) & 1;
-
-        flags.set(CpuFlags::FLAG_Z, result2.0 == 0);
-        flags.set(CpuFlags::FLAG_H, half_carry_pre1 != half_carry_post1 || half_carry_pre2 != half_carry_post2);
-        flags.set(CpuFlags::FLAG_N, true);
-        flags.set(CpuFlags::FLAG_C, result1.1 || result2.1);
-    }
-
     fn sbc_r8_8(p1: &mut u8, p2: u8, flags: &mut CpuFlags)
     {
         let carry = flags.contains(CpuFlags::FLAG_C) as u8;
@@ -484,26 +738,6 @@ impl Cpu
         flags.set(CpuFlags::FLAG_C, result1.1 || result2.1);
     }
 
-    fn and_r8_r8(p1: &mut u8, p2: &mut u8, flags: &mut CpuFlags)
-    {
-        *p1 &= *p2;
-
-        flags.set(CpuFlags::FLAG_Z, *p1 == 0);
-        flags.set(CpuFlags::FLAG_H, true);
-        flags.set(CpuFlags::FLAG_N, false);
-        flags.set(CpuFlags::FLAG_C, false);
-    }
-
-    fn and_r8_r8_s(p1: &mut u8, flags: &mut CpuFlags)
-    {
-        *p1 &= *p1;
-
-        flags.set(CpuFlags::FLAG_Z, *p1 == 0);
-        flags.set(CpuFlags::FLAG_H, true);
-        flags.set(CpuFlags::FLAG_N, false);
-        flags.set(CpuFlags::FLAG_C, false);
-    }
-
     fn and_r8_8(p1: &mut u8, p2: u8, flags: &mut CpuFlags)
     {
         *p1 &= p2;
@@ -514,26 +748,6 @@ impl Cpu
         flags.set(CpuFlags::FLAG_C, false);
     }
 
-    fn xor_r8_r8(p1: &mut u8, p2: &mut u8, flags: &mut CpuFlags)
-    {
-        *p1 ^= *p2;
-
-        flags.set(CpuFlags::FLAG_Z, *p1 == 0);
-        flags.set(CpuFlags::FLAG_H, false);
-        flags.set(CpuFlags::FLAG_N, false);
-        flags.set(CpuFlags::FLAG_C, false);
-    }
-
-    fn xor_r8_r8_s(p1: &mut u8, flags: &mut CpuFlags)
-    {
-        *p1 ^= *p1;
-
-        flags.set(CpuFlags::FLAG_Z, *p1 == 0);
-        flags.set(CpuFlags::FLAG_H, false);
-        flags.set(CpuFlags::FLAG_N, false);
-        flags.set(CpuFlags::FLAG_C, false);
-    }
-
     fn xor_r8_8(p1: &mut u8, p2: u8, flags: &mut CpuFlags)
     {
         *p1 ^= p2;
@@ -544,26 +758,6 @@ impl Cpu
         flags.set(CpuFlags::FLAG_C, false);
     }
 
-    fn or_r8_r8(p1: &mut u8, p2: &mut u8, flags: &mut CpuFlags)
-    {
-        *p1 |= *p2;
-
-        flags.set(CpuFlags::FLAG_Z, *p1 == 0);
-        flags.set(CpuFlags::FLAG_H, false);
-        flags.set(CpuFlags::FLAG_N, false);
-        flags.set(CpuFlags::FLAG_C, false);
-    }
-
-    fn or_r8_r8_s(p1: &mut u8, flags: &mut CpuFlags)
-    {
-        *p1 |= *p1;
-
-        flags.set(CpuFlags::FLAG_Z, *p1 == 0);
-        flags.set(CpuFlags::FLAG_H, false);
-        flags.set(CpuFlags::FLAG_N, false);
-        flags.set(CpuFlags::FLAG_C, false);
-    }
-
     fn or_r8_8(p1: &mut u8, p2: u8, flags: &mut CpuFlags)
     {
         *p1 |= p2;
@@ -574,117 +768,47 @@ impl Cpu
         flags.set(CpuFlags::FLAG_C, false);
     }
 
-    fn cp_r8_r8(p1: &mut u8, p2: &mut u8, flags: &mut CpuFlags)
-    {
-        let half_carry_pre = ((*p1 ^ *p2) >> 4) & 1;
-        let result = p1.overflowing_sub(*p2);
-        *p1 = result.0;
-        let half_carry_post = (result.0 >> 4) & 1;
-
-        flags.set(CpuFlags::FLAG_Z, result.0 == 0);
-        flags.set(CpuFlags::FLAG_H, half_carry_pre != half_carry_post);
-        flags.set(CpuFlags::FLAG_N, true);
-        flags.set(CpuFlags::FLAG_C, result.1);
-    }
-
-    fn cp_r8_r8_s(p1: &mut u8, flags: &mut CpuFlags)
-    {
-        let half_carry_pre = 0; //((*p1 ^ *p1) >> 4) & 1;
-        let result = p1.overflowing_sub(*p1);
-        *p1 = result.0;
-        let half_carry_post = (result.0 >> 4) & 1;
-
-        flags.set(CpuFlags::FLAG_Z, result.0 == 0);
-        flags.set(CpuFlags::FLAG_H, half_carry_pre != half_carry_post);
-        flags.set(CpuFlags::FLAG_N, true);
-        flags.set(CpuFlags::FLAG_C, result.1);
-    }
-
+    ///SUB that only keeps the flags
     fn cp_r8_8(p1: &mut u8, p2: u8, flags: &mut CpuFlags)
     {
-        let half_carry_pre = ((*p1 ^ p2) >> 4) & 1;
-        let result = p1.overflowing_sub(p2);
-        *p1 = result.0;
-        let half_carry_post = (result.0 >> 4) & 1;
-
-        flags.set(CpuFlags::FLAG_Z, result.0 == 0);
-        flags.set(CpuFlags::FLAG_H, half_carry_pre != half_carry_post);
-        flags.set(CpuFlags::FLAG_N, true);
-        flags.set(CpuFlags::FLAG_C, result.1);
+        let mut result = *p1;
+        Cpu::sub_r8_8(&mut result, p2, flags);
     }
 
+    ///Corrects A to BCD after an addition or subtraction of two BCD numbers, N tells which one ran
     fn daa(reg_a: &mut u8, flags: &mut CpuFlags)
     {
-        let c_before = flags.contains(CpuFlags::FLAG_C);
-        let bits_47 = (*reg_a >> 4) & 0b00001111;
-        let h_before = flags.contains(CpuFlags::FLAG_H);
-        let bits_03 = *reg_a & 0b00001111;
-        if flags.contains(CpuFlags::FLAG_N) //Add preceded instruction
+        let mut adjust = 0;
+        let mut carry = flags.contains(CpuFlags::FLAG_C);
+        if flags.contains(CpuFlags::FLAG_N) //Subtract preceded instruction
         {
-            match (c_before, bits_47, h_before, bits_03)
+            if flags.contains(CpuFlags::FLAG_H)
+            {
+                adjust |= 0x06;
+            }
+            if carry
             {
-                (false, 0x0..=0x9, false, 0x0..=0x9) => {
-                    *reg_a = reg_a.wrapping_add(0x00);
-                    flags.set(CpuFlags::FLAG_C, false);
-                },
-                (false, 0x0..=0x8, false, 0xA..=0xF) => {
-                    *reg_a = reg_a.wrapping_add(0x06);
-                    flags.set(CpuFlags::FLAG_C, false);
-                },
-                (false, 0x0..=0x9, true, 0x0..=0x3) => {
-                    *reg_a = reg_a.wrapping_add(0x06);
-                    flags.set(CpuFlags::FLAG_C, false);
-                },
-                (false, 0xA..=0xF, false, 0x0..=0x9) => {
-                    *reg_a = reg_a.wrapping_add(0x60);
-                    flags.set(CpuFlags::FLAG_C, true);
-                },
-                (false, 0x9..=0xF, false, 0xA..=0xF) => {
-                    *reg_a = reg_a.wrapping_add(0x66);
-                    flags.set(CpuFlags::FLAG_C, true);
-                },
-                (false, 0xA..=0xF, true, 0x0..=0x3) => {
-                    *reg_a = reg_a.wrapping_add(0x66);
-                    flags.set(CpuFlags::FLAG_C, true);
-                },
-                (true, 0x0..=0x2, false, 0x0..=0x9) => {
-                    *reg_a = reg_a.wrapping_add(0x60);
-                    flags.set(CpuFlags::FLAG_C, true);
-                },
-                (true, 0x0..=0x2, false, 0xA..=0xF) => {
-                    *reg_a = reg_a.wrapping_add(0x66);
-                    flags.set(CpuFlags::FLAG_C, true);
-                },
-                (true, 0x0..=0x3, true, 0x0..=0x3) => {
-                    *reg_a = reg_a.wrapping_add(0x66);
-                    flags.set(CpuFlags::FLAG_C, true);
-                },
-                _ => panic!("Invalid BDC conversion")
+                adjust |= 0x60;
             }
+            *reg_a = reg_a.wrapping_sub(adjust);
         }
-        else //subtract preceded instruction
+        else //Add preceded instruction
         {
-            match (c_before, bits_47, h_before, bits_03)
+            if flags.contains(CpuFlags::FLAG_H) || *reg_a & 0x0F > 0x09
             {
-                (false, 0x0..=0x9, false, 0x0..=0x9) => {
-                    *reg_a = reg_a.wrapping_add(0x00);
-                    flags.set(CpuFlags::FLAG_C, false);
-                },
-                (false, 0x0..=0x8, true, 0x6..=0xF) => {
-                    *reg_a = reg_a.wrapping_add(0xFA);
-                    flags.set(CpuFlags::FLAG_C, false);
-                },
-                (true, 0x7..=0xF, false, 0x0..=0x9) => {
-                    *reg_a = reg_a.wrapping_add(0xA0);
-                    flags.set(CpuFlags::FLAG_C, true);
-                },
-                (true, 0x6..=0xF, true, 0x6..=0xF) => {
-                    *reg_a = reg_a.wrapping_add(0x9A);
-                    flags.set(CpuFlags::FLAG_C, true);
-                },
-                _ => panic!("Invalid BDC conversion")
+                adjust |= 0x06;
             }
+            if carry || *reg_a > 0x99
+            {
+                adjust |= 0x60;
+                carry = true;
+            }
+            *reg_a = reg_a.wrapping_add(adjust);
         }
+
+        flags.set(CpuFlags::FLAG_Z, *reg_a == 0);
+        flags.set(CpuFlags::FLAG_H, false);
+        flags.set(CpuFlags::FLAG_C, carry);
     }
 
     fn cpl(reg_a: &mut u8, flags: &mut CpuFlags)
@@ -711,66 +835,67 @@ impl Cpu
     fn jp_pc_16(pc: &mut ProgramCounter, msh: u8, lsh: u8)
     {
         pc.reg = u16::from_le_bytes([lsh, msh]);
-        pc.should_increment = false;
     }
 
-    fn jp_flag_pc_16(pc: &mut ProgramCounter, flag: CpuFlags, msh: u8, lsh: u8, flags: &mut CpuFlags)
+    ///Conditional jumps return whether they were taken, which costs an extra cycle
+    fn jp_flag_pc_16(pc: &mut ProgramCounter, flag: CpuFlags, msh: u8, lsh: u8, flags: &mut CpuFlags) -> bool
     {
-        if flags.contains(flag)
+        let taken = flags.contains(flag);
+        if taken
         {
             Cpu::jp_pc_16(pc, msh, lsh);
         }
+        taken
     }
 
-    fn jp_nflag_pc_16(pc: &mut ProgramCounter, flag: CpuFlags, msh: u8, lsh: u8, flags: &mut CpuFlags)
+    fn jp_nflag_pc_16(pc: &mut ProgramCounter, flag: CpuFlags, msh: u8, lsh: u8, flags: &mut CpuFlags) -> bool
     {
-        if !flags.contains(flag)
+        let taken = !flags.contains(flag);
+        if taken
         {
             Cpu::jp_pc_16(pc, msh, lsh);
         }
+        taken
     }
 
+    ///Relative to the address after the jump, PC has already moved past the offset
     fn jr_i8(pc: &mut ProgramCounter, p1: i8)
     {
-        let conv = p1.unsigned_abs() as u16;
-        let negative = p1 < 0;
-        if negative
-        {
-            pc.reg -= conv;
-        }
-        else
-        {
-            pc.reg += conv;
-        }
-        pc.should_increment = false;
+        pc.reg = pc.reg.wrapping_add_signed(p1 as i16);
     }
 
-    fn jr_flag_i8(pc: &mut ProgramCounter, flag: CpuFlags, p1: i8, flags: &mut CpuFlags)
+    fn jr_flag_i8(pc: &mut ProgramCounter, flag: CpuFlags, p1: i8, flags: &mut CpuFlags) -> bool
     {
-        if flags.contains(flag)
+        let taken = flags.contains(flag);
+        if taken
         {
             Cpu::jr_i8(pc, p1);
         }
+        taken
     }
 
-    fn jr_nflag_i8(pc: &mut ProgramCounter, flag: CpuFlags, p1: i8, flags: &mut CpuFlags)
+    fn jr_nflag_i8(pc: &mut ProgramCounter, flag: CpuFlags, p1: i8, flags: &mut CpuFlags) -> bool
     {
-        if !flags.contains(flag)
+        let taken = !flags.contains(flag);
+        if taken
         {
             Cpu::jr_i8(pc, p1);
         }
+        taken
     }
 
-    fn call_16(ram: &mut Ram, msh: u8, lsh: u8, pc: &mut ProgramCounter, sp: &mut u16)
+    fn call_16<B: Bus>(ram: &mut B, msh: u8, lsh: u8, pc: &mut ProgramCounter, sp: &mut u16)
     {
+        //Internal cycle before the pushes
+        ram.tick();
         let pc_bytes = pc.reg.to_le_bytes();
-        ram.write(*sp - 1, pc_bytes[1]);
-        ram.write(*sp - 2, pc_bytes[0]);
+        ram.write(sp.wrapping_sub(1), pc_bytes[1]);
+        ram.write(sp.wrapping_sub(2), pc_bytes[0]);
         pc.reg = u16::from_le_bytes([lsh, msh]);
-        *sp -= 2;
+        *sp = sp.wrapping_sub(2);
     }
 
-    fn call_flag_16(ram: &mut Ram, flag: CpuFlags, msh: u8, lsh: u8, pc: &mut ProgramCounter, sp: &mut u16, flags: &mut CpuFlags)
+    fn call_flag_16<B: Bus>(ram: &mut B, flag: CpuFlags, msh: u8, lsh: u8, pc: &mut ProgramCounter, sp: &mut u16, flags: &mut CpuFlags)
     {
         if flags.contains(flag)
         {
@@ -778,7 +903,7 @@ impl Cpu
         }
     }
 
-    fn call_nflag_16(ram: &mut Ram, flag: CpuFlags, msh: u8, lsh: u8, pc: &mut ProgramCounter, sp: &mut u16, flags: &mut CpuFlags)
+    fn call_nflag_16<B: Bus>(ram: &mut B, flag: CpuFlags, msh: u8, lsh: u8, pc: &mut ProgramCounter, sp: &mut u16, flags: &mut CpuFlags)
     {
         if !flags.contains(flag)
         {
@@ -786,40 +911,45 @@ impl Cpu
         }
     }
 
-    fn ret(ram: &mut Ram, pc: &mut ProgramCounter, sp: &mut u16)
+    fn ret<B: Bus>(ram: &mut B, pc: &mut ProgramCounter, sp: &mut u16)
     {
         let sp_lsh = ram.read(*sp);
-        let sp_msh = ram.read(*sp + 1);
+        let sp_msh = ram.read(sp.wrapping_add(1));
         pc.reg = u16::from_le_bytes([sp_lsh, sp_msh]);
-        *sp += 2;
+        *sp = sp.wrapping_add(2);
+        ram.tick();
     }
 
-    fn ret_flag(ram: &mut Ram, pc: &mut ProgramCounter, sp: &mut u16, flag: CpuFlags, flags: &mut CpuFlags)
+    fn ret_flag<B: Bus>(ram: &mut B, pc: &mut ProgramCounter, sp: &mut u16, flag: CpuFlags, flags: &mut CpuFlags)
     {
+        //Checking the condition takes a cycle of its own
+        ram.tick();
         if flags.contains(flag)
         {
             Cpu::ret(ram, pc, sp);
         }
     }
 
-    fn ret_nflag(ram: &mut Ram, pc: &mut ProgramCounter, sp: &mut u16, flag: CpuFlags, flags: &mut CpuFlags)
+    fn ret_nflag<B: Bus>(ram: &mut B, pc: &mut ProgramCounter, sp: &mut u16, flag: CpuFlags, flags: &mut CpuFlags)
     {
+        //Checking the condition takes a cycle of its own
+        ram.tick();
         if !flags.contains(flag)
         {
             Cpu::ret(ram, pc, sp);
         }
     }
 
-    fn rst(ram: &mut Ram, loc: u8, pc: &mut ProgramCounter, sp: &mut u16)
+    fn rst<B: Bus>(ram: &mut B, loc: u8, pc: &mut ProgramCounter, sp: &mut u16)
     {
+        ram.tick();
         let pc_bytes = pc.reg.to_le_bytes();
-        ram.write(*sp - 1, pc_bytes[1]);
-        ram.write(*sp - 2, pc_bytes[0]);
-        *sp -= 2;
+        ram.write(sp.wrapping_sub(1), pc_bytes[1]);
+        ram.write(sp.wrapping_sub(2), pc_bytes[0]);
+        *sp = sp.wrapping_sub(2);
         pc.reg = u16::from_le_bytes([loc, 0]);
     }
 
-
     //--------------------16 BIT OPCODES--------------------
 
     fn rlc_r8(p1: &mut u8, flags: &mut CpuFlags)
@@ -970,31 +1100,32 @@ impl Cpu
         *p2 |= 1u8 << p1;
     }
 
-    fn push_r16(ram: &mut Ram, sp: &mut u16, msh: &mut u8, lsh: &mut u8)
+    fn push_r16<B: Bus>(ram: &mut B, sp: &mut u16, msh: &mut u8, lsh: &mut u8)
     {
-        ram.write(*sp - 1, *msh);
-        ram.write(*sp - 2, *lsh);
-        *sp -= 2;
+        ram.tick();
+        ram.write(sp.wrapping_sub(1), *msh);
+        ram.write(sp.wrapping_sub(2), *lsh);
+        *sp = sp.wrapping_sub(2);
     }
 
-    fn push_pc(ram: &mut Ram, sp: &mut u16, pc: &mut ProgramCounter)
+    fn push_pc<B: Bus>(ram: &mut B, sp: &mut u16, pc: &mut ProgramCounter)
     {
         let bytes = pc.reg.to_le_bytes();
-        ram.write(*sp - 1, bytes[1]);
-        ram.write(*sp - 2, bytes[0]);
-        *sp -= 2;
+        ram.write(sp.wrapping_sub(1), bytes[1]);
+        ram.write(sp.wrapping_sub(2), bytes[0]);
+        *sp = sp.wrapping_sub(2);
     }
 
-    fn pop_r16(ram: &mut Ram, sp: &mut u16, msh: &mut u8, lsh: &mut u8)
+    fn pop_r16<B: Bus>(ram: &mut B, sp: &mut u16, msh: &mut u8, lsh: &mut u8)
     {
         *lsh = ram.read(*sp);
-        *msh = ram.read(*sp + 1);
-        *sp += 2;
+        *msh = ram.read(sp.wrapping_add(1));
+        *sp = sp.wrapping_add(2);
     }
 
     //----------INTERRUPT MANAGEMENT----------
 
-    fn reti(ram: &mut Ram, sp: &mut u16, pc: &mut ProgramCounter, ime: &mut bool)
+    fn reti<B: Bus>(ram: &mut B, sp: &mut u16, pc: &mut ProgramCounter, ime: &mut bool)
     {
         let l_bytes = ram.read(*sp);
         Cpu::inc_sp(sp);
@@ -1004,23 +1135,51 @@ impl Cpu
         *ime = true;
     }
 
-    fn ei(ime: &mut bool)
+    fn ei(ei_delay: &mut u8)
     {
-        *ime = true;
+        *ei_delay = EI_DELAY;
     }
 
-    fn di(ime: &mut bool)
+    fn di(ime: &mut bool, ei_delay: &mut u8)
     {
         *ime = false;
+        *ei_delay = 0;
+    }
+
+    ///Clears the highest priority pending interrupt in IF and returns its bit
+    fn acknowledge_interrupt<B: Bus>(ram: &mut B, pending: u8) -> u8
+    {
+        let bit = pending & pending.wrapping_neg();
+        let flags = ram.peek(ram::IF);
+        ram.poke(ram::IF, flags & !bit);
+        bit
+    }
+
+    ///Two wait cycles, the PC push, then the jump to the vector (spent by the caller)
+    fn dispatch_interrupt<B: Bus>(&mut self, ram: &mut B, bit: u8)
+    {
+        self.ime = false;
+        ram.tick();
+        ram.tick();
+        let source = self.pc.reg;
+        Cpu::push_pc(ram, &mut self.sp, &mut self.pc);
+        self.pc.reg = INTERRUPT_VECTOR_BASE + 8 * bit.trailing_zeros() as u16;
+        self.call_stack.push(CallFrame { kind: FrameKind::Interrupt, source, target: self.pc.reg, sp: self.sp });
+    }
+
+    fn pending_interrupts<B: Bus>(ram: &mut B) -> u8
+    {
+        ram.peek(ram::IF) & ram.peek(ram::IE) & INTERRUPT_MASK
     }
 
     //----------EXECUTION FUNCTIONS----------
 
+    ///Steps past the opcode just fetched, skipped once after the HALT bug
     fn aux_inc_pc(&mut self)
     {
         if self.pc.should_increment
         {
-            self.pc.reg += 1;
+            self.pc.reg = self.pc.reg.wrapping_add(1);
             self.pc.current_instruction_width = 0;
         }
         else
@@ -1029,783 +1188,513 @@ impl Cpu
         }
     }
 
-    fn aux_read_pc(&self,  ram: &mut Ram) -> u8
+    fn aux_read_pc<B: Bus>(&self,  ram: &mut B) -> u8
     {
         ram.read(self.pc.reg)
     }
 
-    fn aux_read_immediate_data(&mut self, ram: &mut Ram) -> u8
+    fn aux_read_immediate_data<B: Bus>(&mut self, ram: &mut B) -> u8
     {
-        self.pc.reg += 1;
+        let data = ram.read(self.pc.reg);
+        self.pc.reg = self.pc.reg.wrapping_add(1);
         self.pc.current_instruction_width += 1;
-        ram.read(self.pc.reg)
+        data
     }
 
-    pub fn execute(&mut self, ram: &mut Ram)
+    ///Called once per M-cycle. The whole instruction runs on its first cycle and
+    ///the CPU idles for the rest, see step for interleaving accesses with the board.
+    pub fn execute<B: Bus>(&mut self, ram: &mut B)
     {
         if self.pc.current_instruction_cycles > 1
         {
             self.pc.current_instruction_cycles -= 1;
             return;
         }
+        self.pc.current_instruction_cycles = self.step(ram);
+    }
 
-        //Fetch
-        let valid_interrupts = ram::InterruptFlag::from_bits(ram.read(ram::IF) & ram.read(ram::IE)).unwrap();
-        if self.ime
+    ///Runs one instruction, interrupt dispatch, or halted or locked cycle to completion and
+    ///returns the M-cycles it took. The bus is ticked once after every access, so
+    ///a bus that advances the rest of the board sees each access on its own cycle.
+    ///While stopped the whole board's clock is off, the cycle is returned without ticking.
+    pub fn step<B: Bus>(&mut self, bus: &mut B) -> u8
+    {
+        if self.locked
         {
-            if !valid_interrupts.is_empty()
-            {
-                self.ime = false;
-                Cpu::push_pc(ram, &mut self.sp, &mut self.pc);
-            }
-            if valid_interrupts.contains(ram::InterruptFlag::VB)
-            {
-                self.pc.reg = 0x0040;
-            }
-            else if valid_interrupts.contains(ram::InterruptFlag::LCDC)
-            {
-                self.pc.reg = 0x0048;
-            }
-            else if valid_interrupts.contains(ram::InterruptFlag::TIMA)
-            {
-                self.pc.reg = 0x0050;
-            }
-            else if valid_interrupts.contains(ram::InterruptFlag::SIO_TRANSFER_COMPLETE)
+            bus.tick();
+            return 1;
+        }
+        if self.stopped
+        {
+            //Only a joypad line going low starts the clock again, interrupts don't
+            if bus.peek(ram::P1) & JOYPAD_LINES == JOYPAD_LINES
             {
-                self.pc.reg = 0x0058;
+                return 1;
             }
-            else if valid_interrupts.contains(ram::InterruptFlag::P1X_NEG_EDGE)
+            self.stopped = false;
+        }
+        //IF and IE are checked inside the CPU rather than over the bus
+        let pending = Cpu::pending_interrupts(bus);
+        if pending != 0
+        {
+            //Any pending interrupt ends HALT, even with IME off
+            self.halted = false;
+        }
+        let interrupt = if self.ime && pending != 0 { Some(Cpu::acknowledge_interrupt(bus, pending)) } else { None };
+
+        if self.trace.is_some() && !self.halted && interrupt.is_none()
+        {
+            self.write_trace(bus);
+        }
+
+        let mut ram = Clocked::new(bus);
+        let cycles = match interrupt
+        {
+            _ if self.halted => 1,
+            Some(bit) =>
             {
-                self.pc.reg = 0x0060;
-            }
+                self.dispatch_interrupt(&mut ram, bit);
+                INTERRUPT_DISPATCH_CYCLES
+            },
+            None => self.execute_instruction(&mut ram, pending)
+        };
+        //Internal cycles that don't touch the bus are spent after the last access
+        while ram.cycles < cycles
+        {
+            ram.tick();
+        }
+        ram.cycles
+    }
+
+    ///Shadow of the calls, RSTs and interrupts the stack is currently inside of
+    pub fn call_stack(&self) -> &CallStack
+    {
+        &self.call_stack
+    }
+
+    ///Sends a gameboy-doctor style line to sink before every instruction, None turns tracing off.
+    ///Returns the sink that was set before.
+    pub fn set_trace(&mut self, sink: Option<Box<dyn Write>>) -> Option<Box<dyn Write>>
+    {
+        std::mem::replace(&mut self.trace, sink)
+    }
 
+    //State before the instruction at PC runs, PCMEM is read without ticking the bus.
+    //Kept out of line so the formatting doesn't weigh on step when tracing is off.
+    #[cold]
+    #[inline(never)]
+    fn write_trace<B: Bus>(&mut self, bus: &mut B)
+    {
+        let Some(sink) = self.trace.as_mut() else { return; };
+        let pc = self.pc.reg;
+        let pcmem = [0, 1, 2, 3].map(|x| bus.peek(pc.wrapping_add(x)));
+        let result = writeln!(sink, "A:{:02X} F:{:02X} B:{:02X} C:{:02X} D:{:02X} E:{:02X} H:{:02X} L:{:02X} SP:{:04X} PC:{:04X} PCMEM:{:02X},{:02X},{:02X},{:02X}",
+            self.reg_a, self.reg_f.bits(), self.reg_b, self.reg_c, self.reg_d, self.reg_e, self.reg_h, self.reg_l,
+            self.sp, pc, pcmem[0], pcmem[1], pcmem[2], pcmem[3]);
+        if let Err(x) = result
+        {
+            self.trace = None;
+            self.trace_error = Some(x);
         }
+    }
 
-        if self.halted
+    ///Error that stopped tracing, returned once
+    pub fn take_trace_error(&mut self) -> Option<std::io::Error>
+    {
+        self.trace_error.take()
+    }
+
+    ///Returns the instruction's length from the timing tables
+    fn execute_instruction<B: Bus>(&mut self, ram: &mut B, pending: u8) -> u8
+    {
+        //Fetch
+        let address = self.pc.reg;
+        let instruction = self.aux_read_pc(ram);
+        self.aux_inc_pc();
+
+        let cycles = if instruction == CB_PREFIX
+        {
+            let cb_instruction = self.aux_read_immediate_data(ram);
+            Handlers::<B>::CB[cb_instruction as usize](self, ram)
+        }
+        else
+        {
+            Handlers::<B>::BASE[instruction as usize](self, ram, address, pending)
+        };
+
+        if self.ei_delay > 0
         {
-            if !valid_interrupts.is_empty()
+            self.ei_delay -= 1;
+            if self.ei_delay == 0
             {
-                self.halted = false;
+                self.ime = true;
             }
-            else
+        }
+        cycles
+    }
+
+    //Conditional calls and returns that weren't taken leave SP where it was
+    #[inline(always)]
+    fn track_call_stack(&mut self, op: Op, address: u16, sp: u16)
+    {
+        let kind = match op
+        {
+            Op::Call(_) => FrameKind::Call,
+            Op::Rst(_) => FrameKind::Rst,
+            Op::Ret(_) | Op::Reti if self.sp == sp.wrapping_add(2) =>
             {
+                self.call_stack.pop(address, sp);
                 return;
-            }
+            },
+            _ => return
+        };
+        if self.sp == sp.wrapping_sub(2)
+        {
+            self.call_stack.push(CallFrame { kind, source: address, target: self.pc.reg, sp: self.sp });
         }
+    }
 
-        let instruction = self.aux_read_pc(ram);
+    #[inline(always)]
+    fn execute_op<B: Bus>(&mut self, ram: &mut B, op: Op, pending: u8)
+    {
+        match op
+        {
+            Op::Nop => {},
+            Op::LdR16Imm(r) => {
+                let lsh = self.aux_read_immediate_data(ram);
+                let msh = self.aux_read_immediate_data(ram);
+                match r
+                {
+                    R16::Sp => Cpu::ld_sp_16(&mut self.sp, msh, lsh),
+                    _ => {
+                        let (msh_reg, lsh_reg) = self.pair(r);
+                        Cpu::ld_r16_16(msh_reg, lsh_reg, msh, lsh);
+                    }
+                }
+            },
+            Op::StoreA(r) => {
+                let (msh, lsh) = self.indirect(r);
+                ram.write_rp(msh, lsh, self.reg_a);
+            },
+            Op::LoadA(r) => {
+                let (msh, lsh) = self.indirect(r);
+                Cpu::ld_r8_8(&mut self.reg_a, ram.read_rp(msh, lsh));
+            },
+            Op::IncR16(R16::Sp) => {Cpu::inc_sp(&mut self.sp);},
+            Op::IncR16(r) => {
+                let (msh, lsh) = self.pair(r);
+                Cpu::inc_r16(msh, lsh);
+            },
+            Op::DecR16(R16::Sp) => {Cpu::dec_sp(&mut self.sp);},
+            Op::DecR16(r) => {
+                let (msh, lsh) = self.pair(r);
+                Cpu::dec_r16(msh, lsh);
+            },
+            Op::IncR8(r) => {
+                let mut data = self.read_r8(ram, r);
+                Cpu::inc_r8(&mut data, &mut self.reg_f);
+                self.write_r8(ram, r, data);
+            },
+            Op::DecR8(r) => {
+                let mut data = self.read_r8(ram, r);
+                Cpu::dec_r8(&mut data, &mut self.reg_f);
+                self.write_r8(ram, r, data);
+            },
+            Op::LdR8Imm(r) => {
+                let num = self.aux_read_immediate_data(ram);
+                self.write_r8(ram, r, num);
+            },
+            Op::Rlca => {Cpu::rlca(&mut self.reg_a, &mut self.reg_f);},
+            Op::Rrca => {Cpu::rrca(&mut self.reg_a, &mut self.reg_f);},
+            Op::Rla => {Cpu::rla(&mut self.reg_a, &mut self.reg_f);},
+            Op::Rra => {Cpu::rra(&mut self.reg_a, &mut self.reg_f);},
+            Op::Daa => {Cpu::daa(&mut self.reg_a, &mut self.reg_f);},
+            Op::Cpl => {Cpu::cpl(&mut self.reg_a, &mut self.reg_f);},
+            Op::Scf => {Cpu::scf(&mut self.reg_f);},
+            Op::Ccf => {Cpu::ccf(&mut self.reg_f);},
+            Op::StoreSp => {
+                let lsh = self.aux_read_immediate_data(ram);
+                let msh = self.aux_read_immediate_data(ram);
+                Cpu::ld_16a_sp(&mut self.sp, ram, msh, lsh);
+            },
+            Op::AddHl(r) => {
+                let value = self.read_r16(r);
+                Cpu::add_r16_16(&mut self.reg_h, &mut self.reg_l, value, &mut self.reg_f);
+            },
+            Op::Stop => {self.stop(ram, pending);},
+            Op::Jr(cond) => {
+                let immediate = self.aux_read_immediate_data(ram) as i8;
+                let taken = match cond
+                {
+                    Cond::Always => {
+                        Cpu::jr_i8(&mut self.pc, immediate);
+                        true
+                    },
+                    Cond::Flag(flag) => Cpu::jr_flag_i8(&mut self.pc, flag, immediate, &mut self.reg_f),
+                    Cond::NotFlag(flag) => Cpu::jr_nflag_i8(&mut self.pc, flag, immediate, &mut self.reg_f)
+                };
+                if taken
+                {
+                    ram.tick();
+                }
+            },
+            Op::Halt => {
+                //HALT bug: with IME off and an interrupt already pending the CPU doesn't halt,
+                //and the next opcode is read twice
+                if !self.ime && pending != 0
+                {
+                    self.pc.should_increment = false;
+                }
+                else
+                {
+                    self.halt();
+                }
+            },
+            Op::Ld(dst, src) => {
+                let data = self.read_r8(ram, src);
+                self.write_r8(ram, dst, data);
+            },
+            Op::Alu(operation, r) => {
+                let data = self.read_r8(ram, r);
+                self.alu(operation, data);
+            },
+            Op::AluImm(operation) => {
+                let num = self.aux_read_immediate_data(ram);
+                self.alu(operation, num);
+            },
+            Op::Ret(Cond::Always) => {Cpu::ret(ram, &mut self.pc, &mut self.sp);},
+            Op::Ret(Cond::Flag(flag)) => {Cpu::ret_flag(ram, &mut self.pc, &mut self.sp, flag, &mut self.reg_f);},
+            Op::Ret(Cond::NotFlag(flag)) => {Cpu::ret_nflag(ram, &mut self.pc, &mut self.sp, flag, &mut self.reg_f);},
+            Op::Reti => {Cpu::reti(ram, &mut self.sp, &mut self.pc, &mut self.ime);},
+            Op::Pop(r) => {
+                let (mut msh, mut lsh) = (0, 0);
+                Cpu::pop_r16(ram, &mut self.sp, &mut msh, &mut lsh);
+                self.write_r16(r, u16::from_le_bytes([lsh, msh]));
+            },
+            Op::Push(r) => {
+                let [mut lsh, mut msh] = self.read_r16(r).to_le_bytes();
+                Cpu::push_r16(ram, &mut self.sp, &mut msh, &mut lsh);
+            },
+            Op::Jp(cond) => {
+                let lsh = self.aux_read_immediate_data(ram);
+                let msh = self.aux_read_immediate_data(ram);
+                let taken = match cond
+                {
+                    Cond::Always => {
+                        Cpu::jp_pc_16(&mut self.pc, msh, lsh);
+                        true
+                    },
+                    Cond::Flag(flag) => Cpu::jp_flag_pc_16(&mut self.pc, flag, msh, lsh, &mut self.reg_f),
+                    Cond::NotFlag(flag) => Cpu::jp_nflag_pc_16(&mut self.pc, flag, msh, lsh, &mut self.reg_f)
+                };
+                if taken
+                {
+                    ram.tick();
+                }
+            },
+            Op::JpHl => {Cpu::jp_pc_16(&mut self.pc, self.reg_h, self.reg_l);},
+            Op::Call(cond) => {
+                let lsh = self.aux_read_immediate_data(ram);
+                let msh = self.aux_read_immediate_data(ram);
+                match cond
+                {
+                    Cond::Always => Cpu::call_16(ram, msh, lsh, &mut self.pc, &mut self.sp),
+                    Cond::Flag(flag) => Cpu::call_flag_16(ram, flag, msh, lsh, &mut self.pc, &mut self.sp, &mut self.reg_f),
+                    Cond::NotFlag(flag) => Cpu::call_nflag_16(ram, flag, msh, lsh, &mut self.pc, &mut self.sp, &mut self.reg_f)
+                }
+            },
+            Op::Rst(loc) => {Cpu::rst(ram, loc, &mut self.pc, &mut self.sp);},
+            Op::LdhStore => {
+                let lsh = self.aux_read_immediate_data(ram);
+                ram.write_rp(0xFF, lsh, self.reg_a);
+            },
+            Op::LdhLoad => {
+                let lsh = self.aux_read_immediate_data(ram);
+                Cpu::ld_r8_8(&mut self.reg_a, ram.read_rp(0xFF, lsh));
+            },
+            Op::LdhStoreC => {ram.write_rp(0xFF, self.reg_c, self.reg_a);},
+            Op::LdhLoadC => {Cpu::ld_r8_8(&mut self.reg_a, ram.read_rp(0xFF, self.reg_c));},
+            Op::StoreAbsolute => {
+                let lsh = self.aux_read_immediate_data(ram);
+                let msh = self.aux_read_immediate_data(ram);
+                ram.write_rp(msh, lsh, self.reg_a);
+            },
+            Op::LoadAbsolute => {
+                let lsh = self.aux_read_immediate_data(ram);
+                let msh = self.aux_read_immediate_data(ram);
+                Cpu::ld_r8_8(&mut self.reg_a, ram.read_rp(msh, lsh));
+            },
+            Op::AddSp => {
+                let immediate = self.aux_read_immediate_data(ram) as i8;
+                Cpu::add_sp_i8(&mut self.sp, immediate, &mut self.reg_f);
+            },
+            Op::LdHlSp => {
+                let immediate = self.aux_read_immediate_data(ram) as i8;
+                Cpu::ld_hl_sp_plus(&mut self.sp, &mut self.reg_h, &mut self.reg_l, immediate, &mut self.reg_f);
+            },
+            Op::LdSpHl => {Cpu::ld_sp_r16(&mut self.sp, &mut self.reg_h, &mut self.reg_l);},
+            Op::Di => {Cpu::di(&mut self.ime, &mut self.ei_delay);},
+            Op::Ei => {Cpu::ei(&mut self.ei_delay);},
+            Op::Prefix | Op::Illegal => unreachable!("handled before execute_op")
+        }
+    }
 
-        #[cfg(feature = "cpu-debug")]
-        println!("Instruction: 0x{:02X?}, Program Counter: 0x{:02X?}", instruction, &self.pc.reg);
+    #[inline(always)]
+    fn execute_cb<B: Bus>(&mut self, ram: &mut B, op: CbOp)
+    {
+        let (CbOp::Shift(_, r) | CbOp::Bit(_, r) | CbOp::Res(_, r) | CbOp::Set(_, r)) = op;
+        let mut data = self.read_r8(ram, r);
+        match op
+        {
+            CbOp::Shift(shift, _) => match shift
+            {
+                Shift::Rlc => Cpu::rlc_r8(&mut data, &mut self.reg_f),
+                Shift::Rrc => Cpu::rrc_r8(&mut data, &mut self.reg_f),
+                Shift::Rl => Cpu::rl_r8(&mut data, &mut self.reg_f),
+                Shift::Rr => Cpu::rr_r8(&mut data, &mut self.reg_f),
+                Shift::Sla => Cpu::sla_r8(&mut data, &mut self.reg_f),
+                Shift::Sra => Cpu::sra_r8(&mut data, &mut self.reg_f),
+                Shift::Swap => Cpu::swap_r8(&mut data, &mut self.reg_f),
+                Shift::Srl => Cpu::srl_r8(&mut data, &mut self.reg_f)
+            },
+            CbOp::Bit(bit, _) => Cpu::bit_r8(bit, &mut data, &mut self.reg_f),
+            CbOp::Res(bit, _) => Cpu::res_r8(bit, &mut data),
+            CbOp::Set(bit, _) => Cpu::set_r8(bit, &mut data)
+        }
+        //BIT only reads its operand
+        if !matches!(op, CbOp::Bit(..))
+        {
+            self.write_r8(ram, r, data);
+        }
+    }
 
-        if self.pc.reg == 0xFA
+    #[inline(always)]
+    fn read_r8<B: Bus>(&self, ram: &mut B, r: R8) -> u8
+    {
+        match r
         {
-            println!("Copy check");
+            R8::B => self.reg_b,
+            R8::C => self.reg_c,
+            R8::D => self.reg_d,
+            R8::E => self.reg_e,
+            R8::H => self.reg_h,
+            R8::L => self.reg_l,
+            R8::Hl => ram.read_rp(self.reg_h, self.reg_l),
+            R8::A => self.reg_a
         }
+    }
 
-        if instruction != 0xCB
+    #[inline(always)]
+    fn write_r8<B: Bus>(&mut self, ram: &mut B, r: R8, data: u8)
+    {
+        match r
         {
-            match instruction
-            {
-                0x00 => {/* NOP */},
-                0x01 => {
-                    let lsh = self.aux_read_immediate_data(ram);
-                    let msh = self.aux_read_immediate_data(ram);
-                    Cpu::ld_r16_16(&mut self.reg_b, &mut self.reg_c, msh, lsh);
-                },
-                0x02 => {Cpu::ld_r8_r8(ram.get_rp_ref(self.reg_b, self.reg_c), &mut self.reg_a);},
-                0x03 => {Cpu::inc_r16(&mut self.reg_b, &mut self.reg_c);},
-                0x04 => {Cpu::inc_r8(&mut self.reg_b, &mut self.reg_f);},
-                0x05 => {Cpu::dec_r8(&mut self.reg_b, &mut self.reg_f);},
-                0x06 => {
-                    let num = self.aux_read_immediate_data(ram);
-                    Cpu::ld_r8_8(&mut self.reg_b, num);
-                },
-                0x07 => {Cpu::rlca(&mut self.reg_a, &mut self.reg_f);},
-                0x08 => {
-                    let lsh = self.aux_read_immediate_data(ram);
-                    let msh = self.aux_read_immediate_data(ram);
-                    Cpu::ld_16a_sp(&mut self.sp, ram, msh, lsh);
-                },
-                0x09 => {Cpu::add_r16_r16(&mut self.reg_h, &mut self.reg_l, &mut self.reg_b, &mut self.reg_c, &mut self.reg_f);},
-                0x0A => {Cpu::ld_r8_r8(&mut self.reg_a, ram.get_rp_ref(self.reg_b, self.reg_c));},
-                0x0B => {Cpu::dec_r16(&mut self.reg_b, &mut self.reg_c);},
-                0x0C => {Cpu::inc_r8(&mut self.reg_c, &mut self.reg_f);},
-                0x0D => {Cpu::dec_r8(&mut self.reg_c, &mut self.reg_f);},
-                0x0E => {
-                    let num = self.aux_read_immediate_data(ram);
-                    Cpu::ld_r8_8(&mut self.reg_c, num);
-                },
-                0x0F => {Cpu::rrca(&mut self.reg_a, &mut self.reg_f);},
-                0x10 => {self.stop();},
-                0x11 => {
-                    let lsh = self.aux_read_immediate_data(ram);
-                    let msh = self.aux_read_immediate_data(ram);
-                    Cpu::ld_r16_16(&mut self.reg_d, &mut self.reg_e, msh, lsh);
-                },
-                0x12 => {Cpu::ld_r8_r8(ram.get_rp_ref(self.reg_d, self.reg_e), &mut self.reg_a);},
-                0x13 => {Cpu::inc_r16(&mut self.reg_d, &mut self.reg_e);},
-                0x14 => {Cpu::inc_r8(&mut self.reg_d, &mut self.reg_f);},
-                0x15 => {Cpu::dec_r8(&mut self.reg_d, &mut self.reg_f);},
-                0x16 => {
-                    let num = self.aux_read_immediate_data(ram);
-                    Cpu::ld_r8_8(&mut self.reg_d, num);
-                },
-                0x17 => {Cpu::rla(&mut self.reg_a, &mut self.reg_f);},
-                0x18 => {
-                    let immediate = self.aux_read_immediate_data(ram) as i8;
-                    Cpu::jr_i8(&mut self.pc, immediate);
-                },
-                0x19 => {Cpu::add_r16_r16(&mut self.reg_h, &mut self.reg_l, &mut self.reg_d, &mut self.reg_e, &mut self.reg_f);},
-                0x1A => {Cpu::ld_r8_r8(&mut self.reg_a, ram.get_rp_ref(self.reg_d, self.reg_e));},
-                0x1B => {Cpu::dec_r16(&mut self.reg_d, &mut self.reg_e);},
-                0x1C => {Cpu::inc_r8(&mut self.reg_e, &mut self.reg_f);},
-                0x1D => {Cpu::dec_r8(&mut self.reg_e, &mut self.reg_f);},
-                0x1E => {
-                    let num = self.aux_read_immediate_data(ram);
-                    Cpu::ld_r8_8(&mut self.reg_e, num);
-                },
-                0x1F => {Cpu::rra(&mut self.reg_a, &mut self.reg_f);},
-                0x20 => {
-                    let immediate = self.aux_read_immediate_data(ram) as i8;
-                    Cpu::jr_nflag_i8(&mut self.pc, CpuFlags::FLAG_Z, immediate, &mut self.reg_f);
-                },
-                0x21 => {
-                    let lsh = self.aux_read_immediate_data(ram);
-                    let msh = self.aux_read_immediate_data(ram);
-                    Cpu::ld_r16_16(&mut self.reg_h, &mut self.reg_l, msh, lsh);
-                },
-                0x22 => {
-                    Cpu::ld_r8_r8(ram.get_rp_ref(self.reg_h, self.reg_l), &mut self.reg_a);
-                    Cpu::inc_r16(&mut self.reg_h, &mut self.reg_l);
-                },
-                0x23 => {Cpu::inc_r16(&mut self.reg_h, &mut self.reg_l);},
-                0x24 => {Cpu::inc_r8(&mut self.reg_h, &mut self.reg_f);},
-                0x25 => {Cpu::dec_r8(&mut self.reg_h, &mut self.reg_f);},
-                0x26 => {
-                    let num = self.aux_read_immediate_data(ram);
-                    Cpu::ld_r8_8(&mut self.reg_h, num);
-                },
-                0x27 => {Cpu::daa(&mut self.reg_a, &mut self.reg_f);},
-                0x28 => {
-                    let immediate = self.aux_read_immediate_data(ram) as i8;
-                    Cpu::jr_flag_i8(&mut self.pc, CpuFlags::FLAG_Z, immediate, &mut self.reg_f);
-                },
-                0x29 => {
-                    Cpu::add_r16_r16_s(&mut self.reg_h, &mut self.reg_l, &mut self.reg_f)},
-                0x2A => {
-                    Cpu::ld_r8_r8(&mut self.reg_a, ram.get_rp_ref(self.reg_h, self.reg_l));
-                    Cpu::inc_r16(&mut self.reg_h, &mut self.reg_l);
-                },
-                0x2B => {Cpu::dec_r16(&mut self.reg_h, &mut self.reg_l);},
-                0x2C => {Cpu::inc_r8(&mut self.reg_l, &mut self.reg_f);},
-                0x2D => {Cpu::dec_r8(&mut self.reg_l, &mut self.reg_f);},
-                0x2E => {
-                    let num = self.aux_read_immediate_data(ram);
-                    Cpu::ld_r8_8(&mut self.reg_l, num);
-                },
-                0x2F => {Cpu::cpl(&mut self.reg_a, &mut self.reg_f);},
-                0x30 => {
-                    let immediate = self.aux_read_immediate_data(ram) as i8;
-                    Cpu::jr_nflag_i8(&mut self.pc, CpuFlags::FLAG_C, immediate, &mut self.reg_f);
-                },
-                0x31 => {
-                    let lsh = self.aux_read_immediate_data(ram);
-                    let msh = self.aux_read_immediate_data(ram);
-                    Cpu::ld_sp_16( &mut self.sp, msh, lsh);
-                },
-                0x32 => {
-                    Cpu::ld_r8_r8(ram.get_rp_ref(self.reg_h, self.reg_l), &mut self.reg_a);
-                    Cpu::dec_r16(&mut self.reg_h, &mut self.reg_l);
-                },
-                0x33 => {Cpu::inc_sp(&mut self.sp);},
-                0x34 => {Cpu::inc_r8(ram.get_rp_ref(self.reg_h, self.reg_l), &mut self.reg_f);},
-                0x35 => {Cpu::dec_r8(ram.get_rp_ref(self.reg_h, self.reg_l), &mut self.reg_f);},
-                0x36 => {
-                    let num = self.aux_read_immediate_data(ram);
-                    Cpu::ld_r8_8(ram.get_rp_ref(self.reg_h, self.reg_l), num);
-                },
-                0x37 => {Cpu::scf(&mut self.reg_f);},
-                0x38 => {
-                    let immediate = self.aux_read_immediate_data(ram) as i8;
-                    Cpu::jr_flag_i8(&mut self.pc, CpuFlags::FLAG_C, immediate, &mut self.reg_f);
-                },
-                0x39 => {Cpu::add_r16_sp( &mut self.reg_h, &mut self.reg_l, &mut self.sp, &mut self.reg_f);},
-                0x3A => {
-                    Cpu::ld_r8_r8(&mut self.reg_a, ram.get_rp_ref(self.reg_h, self.reg_l));
-                    Cpu::dec_r16(&mut self.reg_h, &mut self.reg_l);
-                },
-                0x3B => {Cpu::dec_sp(&mut self.sp);},
-                0x3C => {Cpu::inc_r8(&mut self.reg_a, &mut self.reg_f);},
-                0x3D => {Cpu::dec_r8(&mut self.reg_a, &mut self.reg_f);},
-                0x3E => {
-                    let num = self.aux_read_immediate_data(ram);
-                    Cpu::ld_r8_8(&mut self.reg_a, num);
-                },
-                0x3F => {Cpu::ccf(&mut self.reg_f);},
-                0x40 => {Cpu::ld_r8_r8_s(&mut self.reg_b);},
-                0x41 => {Cpu::ld_r8_r8(&mut self.reg_b, &mut self.reg_c);},
-                0x42 => {Cpu::ld_r8_r8(&mut self.reg_b, &mut self.reg_d);},
-                0x43 => {Cpu::ld_r8_r8(&mut self.reg_b, &mut self.reg_e);},
-                0x44 => {Cpu::ld_r8_r8(&mut self.reg_b, &mut self.reg_h);},
-                0x45 => {Cpu::ld_r8_r8(&mut self.reg_b, &mut self.reg_l);},
-                0x46 => {Cpu::ld_r8_r8(&mut self.reg_b, ram.get_rp_ref(self.reg_h, self.reg_l));},
-                0x47 => {Cpu::ld_r8_r8(&mut self.reg_b, &mut self.reg_a);},
-                0x48 => {Cpu::ld_r8_r8(&mut self.reg_c, &mut self.reg_b);},
-                0x49 => {Cpu::ld_r8_r8_s(&mut self.reg_c);},
-                0x4A => {Cpu::ld_r8_r8(&mut self.reg_c, &mut self.reg_d);},
-                0x4B => {Cpu::ld_r8_r8(&mut self.reg_c, &mut self.reg_e);},
-                0x4C => {Cpu::ld_r8_r8(&mut self.reg_c, &mut self.reg_h);},
-                0x4D => {Cpu::ld_r8_r8(&mut self.reg_c, &mut self.reg_l);},
-                0x4E => {Cpu::ld_r8_r8(&mut self.reg_c, ram.get_rp_ref(self.reg_h, self.reg_l));},
-                0x4F => {Cpu::ld_r8_r8(&mut self.reg_c, &mut self.reg_a);},
-                0x50 => {Cpu::ld_r8_r8(&mut self.reg_d, &mut self.reg_b);},
-                0x51 => {Cpu::ld_r8_r8(&mut self.reg_d, &mut self.reg_c);},
-                0x52 => {Cpu::ld_r8_r8_s(&mut self.reg_d);},
-                0x53 => {Cpu::ld_r8_r8(&mut self.reg_d, &mut self.reg_e);},
-                0x54 => {Cpu::ld_r8_r8(&mut self.reg_d, &mut self.reg_h);},
-                0x55 => {Cpu::ld_r8_r8(&mut self.reg_d, &mut self.reg_l);},
-                0x56 => {Cpu::ld_r8_r8(&mut self.reg_d, ram.get_rp_ref(self.reg_h, self.reg_l));},
-                0x57 => {Cpu::ld_r8_r8(&mut self.reg_d, &mut self.reg_a);},
-                0x58 => {Cpu::ld_r8_r8(&mut self.reg_e, &mut self.reg_b);},
-                0x59 => {Cpu::ld_r8_r8(&mut self.reg_e, &mut self.reg_c);},
-                0x5A => {Cpu::ld_r8_r8(&mut self.reg_e, &mut self.reg_d);},
-                0x5B => {Cpu::ld_r8_r8_s(&mut self.reg_e);},
-                0x5C => {Cpu::ld_r8_r8(&mut self.reg_e, &mut self.reg_h);},
-                0x5D => {Cpu::ld_r8_r8(&mut self.reg_e, &mut self.reg_l);},
-                0x5E => {Cpu::ld_r8_r8(&mut self.reg_e, ram.get_rp_ref(self.reg_h, self.reg_l));},
-                0x5F => {Cpu::ld_r8_r8(&mut self.reg_e, &mut self.reg_a);},
-                0x60 => {Cpu::ld_r8_r8(&mut self.reg_h, &mut self.reg_b);},
-                0x61 => {Cpu::ld_r8_r8(&mut self.reg_h, &mut self.reg_c);},
-                0x62 => {Cpu::ld_r8_r8(&mut self.reg_h, &mut self.reg_d);},
-                0x63 => {Cpu::ld_r8_r8(&mut self.reg_h, &mut self.reg_e);},
-                0x64 => {Cpu::ld_r8_r8_s(&mut self.reg_h);},
-                0x65 => {Cpu::ld_r8_r8(&mut self.reg_h, &mut self.reg_l);},
-                0x66 => {
-                    let mut ram_read = ram.read_rp(self.reg_h, self.reg_l);
-                    Cpu::ld_r8_r8(&mut self.reg_h, &mut ram_read);
-                },
-                0x67 => {Cpu::ld_r8_r8(&mut self.reg_h, &mut self.reg_a);},
-                0x68 => {Cpu::ld_r8_r8(&mut self.reg_l, &mut self.reg_b);},
-                0x69 => {Cpu::ld_r8_r8(&mut self.reg_l, &mut self.reg_c);},
-                0x6A => {Cpu::ld_r8_r8(&mut self.reg_l, &mut self.reg_d);},
-                0x6B => {Cpu::ld_r8_r8(&mut self.reg_l, &mut self.reg_e);},
-                0x6C => {Cpu::ld_r8_r8(&mut self.reg_l, &mut self.reg_h);},
-                0x6D => {Cpu::ld_r8_r8_s(&mut self.reg_l);},
-                0x6E => {
-                    let mut ram_read = ram.read_rp(self.reg_h, self.reg_l);
-                    Cpu::ld_r8_r8(&mut self.reg_l, &mut ram_read);
-                },
-                0x6F => {Cpu::ld_r8_r8(&mut self.reg_l, &mut self.reg_a);},
-                0x70 => {Cpu::ld_r8_r8(ram.get_rp_ref(self.reg_h, self.reg_l), &mut self.reg_b);},
-                0x71 => {Cpu::ld_r8_r8(ram.get_rp_ref(self.reg_h, self.reg_l), &mut self.reg_c);},
-                0x72 => {Cpu::ld_r8_r8(ram.get_rp_ref(self.reg_h, self.reg_l), &mut self.reg_d);},
-                0x73 => {Cpu::ld_r8_r8(ram.get_rp_ref(self.reg_h, self.reg_l), &mut self.reg_e);},
-                0x74 => {
-                    let ram_read = ram.get_rp_ref(self.reg_h, self.reg_l);
-                    Cpu::ld_r8_r8(ram_read, &mut self.reg_h);
-                },
-                0x75 => {
-                    let ram_read = ram.get_rp_ref(self.reg_h, self.reg_l);
-                    Cpu::ld_r8_r8(ram_read, &mut self.reg_l);
-                },
-                0x76 => {self.halt();},
-                0x77 => {Cpu::ld_r8_r8(ram.get_rp_ref(self.reg_h, self.reg_l), &mut self.reg_a);},
-                0x78 => {Cpu::ld_r8_r8(&mut self.reg_a, &mut self.reg_b);},
-                0x79 => {Cpu::ld_r8_r8(&mut self.reg_a, &mut self.reg_c);},
-                0x7A => {Cpu::ld_r8_r8(&mut self.reg_a, &mut self.reg_d);},
-                0x7B => {Cpu::ld_r8_r8(&mut self.reg_a, &mut self.reg_e);},
-                0x7C => {Cpu::ld_r8_r8(&mut self.reg_a, &mut self.reg_h);},
-                0x7D => {Cpu::ld_r8_r8(&mut self.reg_a, &mut self.reg_l);},
-                0x7E => {Cpu::ld_r8_r8(&mut self.reg_a, ram.get_rp_ref(self.reg_h, self.reg_l));},
-                0x7F => {Cpu::ld_r8_r8_s(&mut self.reg_a);},
-                0x80 => {Cpu::add_r8_r8(&mut self.reg_a, &mut self.reg_b, &mut self.reg_f);},
-                0x81 => {Cpu::add_r8_r8(&mut self.reg_a, &mut self.reg_c, &mut self.reg_f);},
-                0x82 => {Cpu::add_r8_r8(&mut self.reg_a, &mut self.reg_d, &mut self.reg_f);},
-                0x83 => {Cpu::add_r8_r8(&mut self.reg_a, &mut self.reg_e, &mut self.reg_f);},
-                0x84 => {Cpu::add_r8_r8(&mut self.reg_a, &mut self.reg_h, &mut self.reg_f);},
-                0x85 => {Cpu::add_r8_r8(&mut self.reg_a, &mut self.reg_l, &mut self.reg_f);},
-                0x86 => {Cpu::add_r8_r8(&mut self.reg_a, ram.get_rp_ref(self.reg_h, self.reg_l), &mut self.reg_f);},
-                0x87 => {Cpu::add_r8_r8_s(&mut self.reg_a, &mut self.reg_f);},
-                0x88 => {Cpu::adc_r8_r8(&mut self.reg_a, &mut self.reg_b, &mut self.reg_f);},
-                0x89 => {Cpu::adc_r8_r8(&mut self.reg_a, &mut self.reg_c, &mut self.reg_f);},
-                0x8A => {Cpu::adc_r8_r8(&mut self.reg_a, &mut self.reg_d, &mut self.reg_f);},
-                0x8B => {Cpu::adc_r8_r8(&mut self.reg_a, &mut self.reg_e, &mut self.reg_f);},
-                0x8C => {Cpu::adc_r8_r8(&mut self.reg_a, &mut self.reg_h, &mut self.reg_f);},
-                0x8D => {Cpu::adc_r8_r8(&mut self.reg_a, &mut self.reg_l, &mut self.reg_f);},
-                0x8E => {Cpu::adc_r8_r8(&mut self.reg_a, ram.get_rp_ref(self.reg_h, self.reg_l), &mut self.reg_f);},
-                0x8F => {Cpu::adc_r8_r8_s(&mut self.reg_a, &mut self.reg_f);},
-                0x90 => {Cpu::sub_r8_r8(&mut self.reg_a, &mut self.reg_b, &mut self.reg_f);},
-                0x91 => {Cpu::sub_r8_r8(&mut self.reg_a, &mut self.reg_c, &mut self.reg_f);},
-                0x92 => {Cpu::sub_r8_r8(&mut self.reg_a, &mut self.reg_d, &mut self.reg_f);},
-                0x93 => {Cpu::sub_r8_r8(&mut self.reg_a, &mut self.reg_e, &mut self.reg_f);},
-                0x94 => {Cpu::sub_r8_r8(&mut self.reg_a, &mut self.reg_h, &mut self.reg_f);},
-                0x95 => {Cpu::sub_r8_r8(&mut self.reg_a, &mut self.reg_l, &mut self.reg_f);},
-                0x96 => {Cpu::sub_r8_r8(&mut self.reg_a, ram.get_rp_ref(self.reg_h, self.reg_l), &mut self.reg_f);},
-                0x97 => {Cpu::sub_r8_r8_s(&mut self.reg_a, &mut self.reg_f);},
-                0x98 => {Cpu::sbc_r8_r8(&mut self.reg_a, &mut self.reg_b, &mut self.reg_f);},
-                0x99 => {Cpu::sbc_r8_r8(&mut self.reg_a, &mut self.reg_c, &mut self.reg_f);},
-                0x9A => {Cpu::sbc_r8_r8(&mut self.reg_a, &mut self.reg_d, &mut self.reg_f);},
-                0x9B => {Cpu::sbc_r8_r8(&mut self.reg_a, &mut self.reg_e, &mut self.reg_f);},
-                0x9C => {Cpu::sbc_r8_r8(&mut self.reg_a, &mut self.reg_h, &mut self.reg_f);},
-                0x9D => {Cpu::sbc_r8_r8(&mut self.reg_a, &mut self.reg_l, &mut self.reg_f);},
-                0x9E => {Cpu::sbc_r8_r8(&mut self.reg_a, ram.get_rp_ref(self.reg_h, self.reg_l), &mut self.reg_f);},
-                0x9F => {Cpu::sbc_r8_r8_s(&mut self.reg_a, &mut self.reg_f);},
-                0xA0 => {Cpu::and_r8_r8(&mut self.reg_a, &mut self.reg_b, &mut self.reg_f);},
-                0xA1 => {Cpu::and_r8_r8(&mut self.reg_a, &mut self.reg_c, &mut self.reg_f);},
-                0xA2 => {Cpu::and_r8_r8(&mut self.reg_a, &mut self.reg_d, &mut self.reg_f);},
-                0xA3 => {Cpu::and_r8_r8(&mut self.reg_a, &mut self.reg_e, &mut self.reg_f);},
-                0xA4 => {Cpu::and_r8_r8(&mut self.reg_a, &mut self.reg_h, &mut self.reg_f);},
-                0xA5 => {Cpu::and_r8_r8_s(&mut self.reg_a, &mut self.reg_f);},
-                0xA6 => {Cpu::and_r8_r8(&mut self.reg_a, ram.get_rp_ref(self.reg_h, self.reg_l), &mut self.reg_f);},
-                0xA7 => {Cpu::and_r8_r8_s(&mut self.reg_a, &mut self.reg_f);},
-                0xA8 => {Cpu::xor_r8_r8(&mut self.reg_a, &mut self.reg_b, &mut self.reg_f);},
-                0xA9 => {Cpu::xor_r8_r8(&mut self.reg_a, &mut self.reg_c, &mut self.reg_f);},
-                0xAA => {Cpu::xor_r8_r8(&mut self.reg_a, &mut self.reg_d, &mut self.reg_f);},
-                0xAB => {Cpu::xor_r8_r8(&mut self.reg_a, &mut self.reg_e, &mut self.reg_f);},
-                0xAC => {Cpu::xor_r8_r8(&mut self.reg_a, &mut self.reg_h, &mut self.reg_f);},
-                0xAD => {Cpu::xor_r8_r8(&mut self.reg_a, &mut self.reg_l, &mut self.reg_f);},
-                0xAE => {Cpu::xor_r8_r8(&mut self.reg_a, ram.get_rp_ref(self.reg_h, self.reg_l), &mut self.reg_f);},
-                0xAF => {Cpu::xor_r8_r8_s(&mut self.reg_a, &mut self.reg_f);},
-                0xB0 => {Cpu::or_r8_r8(&mut self.reg_a, &mut self.reg_b, &mut self.reg_f);},
-                0xB1 => {Cpu::or_r8_r8(&mut self.reg_a, &mut self.reg_c, &mut self.reg_f);},
-                0xB2 => {Cpu::or_r8_r8(&mut self.reg_a, &mut self.reg_d, &mut self.reg_f);},
-                0xB3 => {Cpu::or_r8_r8(&mut self.reg_a, &mut self.reg_e, &mut self.reg_f);},
-                0xB4 => {Cpu::or_r8_r8(&mut self.reg_a, &mut self.reg_h, &mut self.reg_f);},
-                0xB5 => {Cpu::or_r8_r8(&mut self.reg_a, &mut self.reg_l, &mut self.reg_f);},
-                0xB6 => {Cpu::or_r8_r8(&mut self.reg_a, ram.get_rp_ref(self.reg_h, self.reg_l), &mut self.reg_f);},
-                0xB7 => {Cpu::or_r8_r8_s(&mut self.reg_a, &mut self.reg_f);},
-                0xB8 => {Cpu::cp_r8_r8(&mut self.reg_a, &mut self.reg_b, &mut self.reg_f);},
-                0xB9 => {Cpu::cp_r8_r8(&mut self.reg_a, &mut self.reg_c, &mut self.reg_f);},
-                0xBA => {Cpu::cp_r8_r8(&mut self.reg_a, &mut self.reg_d, &mut self.reg_f);},
-                0xBB => {Cpu::cp_r8_r8(&mut self.reg_a, &mut self.reg_e, &mut self.reg_f);},
-                0xBC => {Cpu::cp_r8_r8(&mut self.reg_a, &mut self.reg_h, &mut self.reg_f);},
-                0xBD => {Cpu::cp_r8_r8(&mut self.reg_a, &mut self.reg_l, &mut self.reg_f);},
-                0xBE => {Cpu::cp_r8_r8(&mut self.reg_a, ram.get_rp_ref(self.reg_h, self.reg_l), &mut self.reg_f);},
-                0xBF => {Cpu::cp_r8_r8_s(&mut self.reg_a, &mut self.reg_f);},
-                0xC0 => {Cpu::ret_nflag(ram, &mut self.pc, &mut self.sp, CpuFlags::FLAG_Z, &mut self.reg_f);},
-                0xC1 => {Cpu::pop_r16(ram, &mut self.sp, &mut self.reg_b, &mut self.reg_c);},
-                0xC2 => {
-                    let lsh = self.aux_read_immediate_data(ram);
-                    let msh = self.aux_read_immediate_data(ram);
-                    Cpu::jp_nflag_pc_16(&mut self.pc, CpuFlags::FLAG_Z, msh, lsh, &mut self.reg_f);
-                },
-                0xC3 => {
-                    let lsh = self.aux_read_immediate_data(ram);
-                    let msh = self.aux_read_immediate_data(ram);
-                    Cpu::jp_pc_16(&mut self.pc, msh, lsh);
-                },
-                0xC4 => {
-                    let lsh = self.aux_read_immediate_data(ram);
-                    let msh = self.aux_read_immediate_data(ram);
-                    Cpu::call_nflag_16(ram, CpuFlags::FLAG_Z, msh, lsh, &mut self.pc, &mut self.sp, &mut self.reg_f);
-                },
-                0xC5 => {Cpu::push_r16(ram, &mut self.sp, &mut self.reg_b, &mut self.reg_c);},
-                0xC6 => {
-                    let num = self.aux_read_immediate_data(ram);
-                    Cpu::add_r8_8(&mut self.reg_a, num, &mut self.reg_f);
-                },
-                0xC7 => {Cpu::rst(ram, 0x00, &mut self.pc, &mut self.sp);},
-                0xC8 => {Cpu::ret_flag(ram, &mut self.pc, &mut self.sp, CpuFlags::FLAG_Z, &mut self.reg_f);},
-                0xC9 => {Cpu::ret(ram, &mut self.pc, &mut self.sp);},
-                0xCA => {
-                    let lsh = self.aux_read_immediate_data(ram);
-                    let msh = self.aux_read_immediate_data(ram);
-                    Cpu::jp_flag_pc_16(&mut self.pc, CpuFlags::FLAG_Z, msh, lsh, &mut self.reg_f);
-                },
-                0xCB => {/*Prefix for the next instruction, handled earlier*/},
-                0xCC => {
-                    let lsh = self.aux_read_immediate_data(ram);
-                    let msh = self.aux_read_immediate_data(ram);
-                    Cpu::call_flag_16(ram, CpuFlags::FLAG_Z, msh, lsh, &mut self.pc, &mut self.sp, &mut self.reg_f);
-                },
-                0xCD => {let lsh = self.aux_read_immediate_data(ram);
-                    let msh = self.aux_read_immediate_data(ram);
-                    Cpu::call_16(ram, msh, lsh, &mut self.pc, &mut self.sp);},
-                0xCE => {
-                    let num = self.aux_read_immediate_data(ram);
-                    Cpu::adc_r8_8(&mut self.reg_a, num, &mut self.reg_f);
-                },
-                0xCF => {Cpu::rst(ram, 0x08, &mut self.pc, &mut self.sp);},
-                0xD0 => {Cpu::ret_nflag(ram, &mut self.pc, &mut self.sp, CpuFlags::FLAG_C, &mut self.reg_f);},
-                0xD1 => {Cpu::pop_r16(ram, &mut self.sp, &mut self.reg_d, &mut self.reg_e);},
-                0xD2 => {
-                    let lsh = self.aux_read_immediate_data(ram);
-                    let msh = self.aux_read_immediate_data(ram);
-                    Cpu::jp_nflag_pc_16(&mut self.pc, CpuFlags::FLAG_C, msh, lsh, &mut self.reg_f);
-                },
-                0xD3 => {self.invalid_instruction(0xD3);},
-                0xD4 => {
-                    let lsh = self.aux_read_immediate_data(ram);
-                    let msh = self.aux_read_immediate_data(ram);
-                    Cpu::call_nflag_16(ram, CpuFlags::FLAG_C, msh, lsh, &mut self.pc, &mut self.sp, &mut self.reg_f);
-                },
-                0xD5 => {Cpu::push_r16(ram, &mut self.sp, &mut self.reg_d, &mut self.reg_e);},
-                0xD6 => {
-                    let num = self.aux_read_immediate_data(ram);
-                    Cpu::sub_r8_8(&mut self.reg_a, num, &mut self.reg_f);
-                },
-                0xD7 => {Cpu::rst(ram, 0x10, &mut self.pc, &mut self.sp);},
-                0xD8 => {Cpu::ret_flag(ram, &mut self.pc, &mut self.sp, CpuFlags::FLAG_C, &mut self.reg_f);},
-                0xD9 => {Cpu::reti(ram, &mut self.sp, &mut self.pc, &mut self.ime);},
-                0xDA => {
-                    let lsh = self.aux_read_immediate_data(ram);
-                    let msh = self.aux_read_immediate_data(ram);
-                    Cpu::jp_flag_pc_16(&mut self.pc, CpuFlags::FLAG_C, msh, lsh, &mut self.reg_f);
-                },
-                0xDB => {self.invalid_instruction(0xDB);},
-                0xDC => {
-                    let lsh = self.aux_read_immediate_data(ram);
-                    let msh = self.aux_read_immediate_data(ram);
-                    Cpu::call_flag_16(ram, CpuFlags::FLAG_C, msh, lsh, &mut self.pc, &mut self.sp, &mut self.reg_f);
-                },
-                0xDD => {self.invalid_instruction(0xDD);},
-                0xDE => {
-                    let num = self.aux_read_immediate_data(ram);
-                    Cpu::sbc_r8_8(&mut self.reg_a, num, &mut self.reg_f);
-                },
-                0xDF => {Cpu::rst(ram, 0x18, &mut self.pc, &mut self.sp);},
-                0xE0 => {
-                    let lsh = self.aux_read_immediate_data(ram);
-                    Cpu::ld_r8_r8(ram.get_rp_ref(0xFF, lsh), &mut self.reg_a);
-                },
-                0xE1 => {Cpu::pop_r16(ram, &mut self.sp, &mut self.reg_h, &mut self.reg_l);},
-                0xE2 => {Cpu::ld_r8_r8(ram.get_rp_ref(0xFF, self.reg_c), &mut self.reg_a);},
-                0xE3 => {self.invalid_instruction(0xE3);},
-                0xE4 => {self.invalid_instruction(0xE4);},
-                0xE5 => {Cpu::push_r16(ram, &mut self.sp, &mut self.reg_h, &mut self.reg_l);},
-                0xE6 => {
-                    let num = self.aux_read_immediate_data(ram);
-                    Cpu::and_r8_8(&mut self.reg_a, num, &mut self.reg_f);
-                },
-                0xE7 => {Cpu::rst(ram, 0x20, &mut self.pc, &mut self.sp);},
-                0xE8 => {
-                    let immediate = self.aux_read_immediate_data(ram) as i8;
-                    Cpu::add_sp_i8(&mut self.sp, immediate);
-                },
-                0xE9 => {Cpu::jp_pc_16(&mut self.pc, self.reg_h, self.reg_l);},
-                0xEA => {
-                    let lsh = self.aux_read_immediate_data(ram);
-                    let msh = self.aux_read_immediate_data(ram);
-                    Cpu::ld_r8_r8(ram.get_rp_ref(msh, lsh), &mut self.reg_a);
-                },
-                0xEB => {self.invalid_instruction(0xEB);},
-                0xEC => {self.invalid_instruction(0xEC);},
-                0xED => {self.invalid_instruction(0xED);},
-                0xEE => {
-                    let num = self.aux_read_immediate_data(ram);
-                    Cpu::xor_r8_8(&mut self.reg_a, num, &mut self.reg_f);
-                },
-                0xEF => {Cpu::rst(ram, 0x28, &mut self.pc, &mut self.sp);},
-                0xF0 => {
-                    let lsh = self.aux_read_immediate_data(ram);
-                    Cpu::ld_r8_r8(&mut self.reg_a, ram.get_rp_ref(0xFF, lsh))
-                },
-                0xF1 => {Cpu::pop_r16(ram, &mut self.sp, &mut self.reg_a, &mut self.reg_f.bits);},
-                0xF2 => {Cpu::ld_r8_r8(&mut self.reg_a, ram.get_rp_ref(0xFF, self.reg_c));},
-                0xF3 => {Cpu::di(&mut self.ime);},
-                0xF4 => {self.invalid_instruction(0xF4);},
-                0xF5 => {Cpu::push_r16(ram, &mut self.sp, &mut self.reg_a, &mut self.reg_f.bits);},
-                0xF6 => {
-                    let num = self.aux_read_immediate_data(ram);
-                    Cpu::or_r8_8(&mut self.reg_a, num, &mut self.reg_f);
-                },
-                0xF7 => {Cpu::rst(ram, 0x30, &mut self.pc, &mut self.sp);},
-                0xF8 => {
-                    let immediate = self.aux_read_immediate_data(ram) as i8;
-                    Cpu::ld_hl_sp_plus(&mut self.sp, &mut self.reg_h, &mut self.reg_l, immediate);
-                },
-                0xF9 => {Cpu::ld_sp_r16(&mut self.sp, &mut self.reg_h, &mut self.reg_l);},
-                0xFA => {
-                    let lsh = self.aux_read_immediate_data(ram);
-                    let msh = self.aux_read_immediate_data(ram);
-                    Cpu::ld_r8_r8(&mut self.reg_a, ram.get_rp_ref(msh, lsh));
-                },
-                0xFB => {Cpu::ei(&mut self.ime);},
-                0xFC => {self.invalid_instruction(0xFC);},
-                0xFD => {self.invalid_instruction(0xFD);},
-                0xFE => {
-                    let num = self.aux_read_immediate_data(ram);
-                    Cpu::cp_r8_8(&mut self.reg_a, num, &mut self.reg_f);
-                },
-                0xFF => {Cpu::rst(ram, 0x38, &mut self.pc, &mut self.sp);}
-            }
+            R8::B => self.reg_b = data,
+            R8::C => self.reg_c = data,
+            R8::D => self.reg_d = data,
+            R8::E => self.reg_e = data,
+            R8::H => self.reg_h = data,
+            R8::L => self.reg_l = data,
+            R8::Hl => ram.write_rp(self.reg_h, self.reg_l, data),
+            R8::A => self.reg_a = data
+        }
+    }
 
-            self.pc.current_instruction_cycles += ZERO_INSTRUCTION_TIME_TABLE[instruction as usize];
+    ///Returns (msh, lsh), SP isn't split into halves
+    #[inline(always)]
+    fn pair(&mut self, r: R16) -> (&mut u8, &mut u8)
+    {
+        match r
+        {
+            R16::Bc => (&mut self.reg_b, &mut self.reg_c),
+            R16::De => (&mut self.reg_d, &mut self.reg_e),
+            R16::Hl => (&mut self.reg_h, &mut self.reg_l),
+            R16::Af => (&mut self.reg_a, &mut self.reg_f.bits),
+            R16::Sp => unreachable!("SP has no 8-bit halves")
         }
-        else
+    }
+
+    #[inline(always)]
+    fn read_r16(&self, r: R16) -> u16
+    {
+        match r
         {
-            let cb_instruction = self.aux_read_immediate_data(ram);
+            R16::Bc => u16::from_le_bytes([self.reg_c, self.reg_b]),
+            R16::De => u16::from_le_bytes([self.reg_e, self.reg_d]),
+            R16::Hl => u16::from_le_bytes([self.reg_l, self.reg_h]),
+            R16::Sp => self.sp,
+            R16::Af => u16::from_le_bytes([self.reg_f.bits(), self.reg_a])
+        }
+    }
 
-            match cb_instruction //CB Prefix
-            {
-                0x00 => {Cpu::rlc_r8(&mut self.reg_b, &mut self.reg_f);},
-                0x01 => {Cpu::rlc_r8(&mut self.reg_c, &mut self.reg_f);},
-                0x02 => {Cpu::rlc_r8(&mut self.reg_d, &mut self.reg_f);},
-                0x03 => {Cpu::rlc_r8(&mut self.reg_e, &mut self.reg_f);},
-                0x04 => {Cpu::rlc_r8(&mut self.reg_h, &mut self.reg_f);},
-                0x05 => {Cpu::rlc_r8(&mut self.reg_l, &mut self.reg_f);},
-                0x06 => {Cpu::rlc_r8(ram.get_rp_ref(self.reg_h, self.reg_l), &mut self.reg_f);},
-                0x07 => {Cpu::rlc_r8(&mut self.reg_a, &mut self.reg_f);},
-                0x08 => {Cpu::rrc_r8(&mut self.reg_b, &mut self.reg_f);},
-                0x09 => {Cpu::rrc_r8(&mut self.reg_c, &mut self.reg_f);},
-                0x0A => {Cpu::rrc_r8(&mut self.reg_d, &mut self.reg_f);},
-                0x0B => {Cpu::rrc_r8(&mut self.reg_e, &mut self.reg_f);},
-                0x0C => {Cpu::rrc_r8(&mut self.reg_h, &mut self.reg_f);},
-                0x0D => {Cpu::rrc_r8(&mut self.reg_l, &mut self.reg_f);},
-                0x0E => {Cpu::rrc_r8(ram.get_rp_ref(self.reg_h, self.reg_l), &mut self.reg_f);},
-                0x0F => {Cpu::rrc_r8(&mut self.reg_a, &mut self.reg_f);},
-                0x10 => {Cpu::rl_r8(&mut self.reg_b, &mut self.reg_f);},
-                0x11 => {Cpu::rl_r8(&mut self.reg_c, &mut self.reg_f);},
-                0x12 => {Cpu::rl_r8(&mut self.reg_d, &mut self.reg_f);},
-                0x13 => {Cpu::rl_r8(&mut self.reg_e, &mut self.reg_f);},
-                0x14 => {Cpu::rl_r8(&mut self.reg_h, &mut self.reg_f);},
-                0x15 => {Cpu::rl_r8(&mut self.reg_l, &mut self.reg_f);},
-                0x16 => {Cpu::rl_r8(ram.get_rp_ref(self.reg_h, self.reg_l), &mut self.reg_f);},
-                0x17 => {Cpu::rl_r8(&mut self.reg_a, &mut self.reg_f);},
-                0x18 => {Cpu::rr_r8(&mut self.reg_b, &mut self.reg_f);},
-                0x19 => {Cpu::rr_r8(&mut self.reg_c, &mut self.reg_f);},
-                0x1A => {Cpu::rr_r8(&mut self.reg_d, &mut self.reg_f);},
-                0x1B => {Cpu::rr_r8(&mut self.reg_e, &mut self.reg_f);},
-                0x1C => {Cpu::rr_r8(&mut self.reg_h, &mut self.reg_f);},
-                0x1D => {Cpu::rr_r8(&mut self.reg_l, &mut self.reg_f);},
-                0x1E => {Cpu::rr_r8(ram.get_rp_ref(self.reg_h, self.reg_l), &mut self.reg_f);},
-                0x1F => {Cpu::rr_r8(&mut self.reg_a, &mut self.reg_f);},
-                0x20 => {Cpu::sla_r8(&mut self.reg_b, &mut self.reg_f);},
-                0x21 => {Cpu::sla_r8(&mut self.reg_c, &mut self.reg_f);},
-                0x22 => {Cpu::sla_r8(&mut self.reg_d, &mut self.reg_f);},
-                0x23 => {Cpu::sla_r8(&mut self.reg_e, &mut self.reg_f);},
-                0x24 => {Cpu::sla_r8(&mut self.reg_h, &mut self.reg_f);},
-                0x25 => {Cpu::sla_r8(&mut self.reg_l, &mut self.reg_f);},
-                0x26 => {Cpu::sla_r8(ram.get_rp_ref(self.reg_h, self.reg_l), &mut self.reg_f);},
-                0x27 => {Cpu::sla_r8(&mut self.reg_a, &mut self.reg_f);},
-                0x28 => {Cpu::sra_r8(&mut self.reg_b, &mut self.reg_f);},
-                0x29 => {Cpu::sra_r8(&mut self.reg_c, &mut self.reg_f);},
-                0x2A => {Cpu::sra_r8(&mut self.reg_d, &mut self.reg_f);},
-                0x2B => {Cpu::sra_r8(&mut self.reg_e, &mut self.reg_f);},
-                0x2C => {Cpu::sra_r8(&mut self.reg_h, &mut self.reg_f);},
-                0x2D => {Cpu::sra_r8(&mut self.reg_l, &mut self.reg_f);},
-                0x2E => {Cpu::sra_r8(ram.get_rp_ref(self.reg_h, self.reg_l), &mut self.reg_f);},
-                0x2F => {Cpu::sra_r8(&mut self.reg_a, &mut self.reg_f);},
-                0x30 => {Cpu::swap_r8(&mut self.reg_b, &mut self.reg_f);},
-                0x31 => {Cpu::swap_r8(&mut self.reg_c, &mut self.reg_f);},
-                0x32 => {Cpu::swap_r8(&mut self.reg_d, &mut self.reg_f);},
-                0x33 => {Cpu::swap_r8(&mut self.reg_e, &mut self.reg_f);},
-                0x34 => {Cpu::swap_r8(&mut self.reg_h, &mut self.reg_f);},
-                0x35 => {Cpu::swap_r8(&mut self.reg_l, &mut self.reg_f);},
-                0x36 => {Cpu::swap_r8(ram.get_rp_ref(self.reg_h, self.reg_l), &mut self.reg_f);},
-                0x37 => {Cpu::swap_r8(&mut self.reg_a, &mut self.reg_f);},
-                0x38 => {Cpu::srl_r8(&mut self.reg_b, &mut self.reg_f);},
-                0x39 => {Cpu::srl_r8(&mut self.reg_c, &mut self.reg_f);},
-                0x3A => {Cpu::srl_r8(&mut self.reg_d, &mut self.reg_f);},
-                0x3B => {Cpu::srl_r8(&mut self.reg_e, &mut self.reg_f);},
-                0x3C => {Cpu::srl_r8(&mut self.reg_h, &mut self.reg_f);},
-                0x3D => {Cpu::srl_r8(&mut self.reg_l, &mut self.reg_f);},
-                0x3E => {Cpu::srl_r8(ram.get_rp_ref(self.reg_h, self.reg_l), &mut self.reg_f);},
-                0x3F => {Cpu::srl_r8(&mut self.reg_a, &mut self.reg_f);},
-                0x40 => {Cpu::bit_r8(0, &mut self.reg_b, &mut self.reg_f);},
-                0x41 => {Cpu::bit_r8(0, &mut self.reg_c, &mut self.reg_f);},
-                0x42 => {Cpu::bit_r8(0, &mut self.reg_d, &mut self.reg_f);},
-                0x43 => {Cpu::bit_r8(0, &mut self.reg_e, &mut self.reg_f);},
-                0x44 => {Cpu::bit_r8(0, &mut self.reg_h, &mut self.reg_f);},
-                0x45 => {Cpu::bit_r8(0, &mut self.reg_l, &mut self.reg_f);},
-                0x46 => {Cpu::bit_r8(0, ram.get_rp_ref(self.reg_h, self.reg_l), &mut self.reg_f);},
-                0x47 => {Cpu::bit_r8(0, &mut self.reg_a, &mut self.reg_f);},
-                0x48 => {Cpu::bit_r8(1, &mut self.reg_b, &mut self.reg_f);},
-                0x49 => {Cpu::bit_r8(1, &mut self.reg_c, &mut self.reg_f);},
-                0x4A => {Cpu::bit_r8(1, &mut self.reg_d, &mut self.reg_f);},
-                0x4B => {Cpu::bit_r8(1, &mut self.reg_e, &mut self.reg_f);},
-                0x4C => {Cpu::bit_r8(1, &mut self.reg_h, &mut self.reg_f);},
-                0x4D => {Cpu::bit_r8(1, &mut self.reg_l, &mut self.reg_f);},
-                0x4E => {Cpu::bit_r8(1, ram.get_rp_ref(self.reg_h, self.reg_l), &mut self.reg_f);},
-                0x4F => {Cpu::bit_r8(1, &mut self.reg_a, &mut self.reg_f);},
-                0x50 => {Cpu::bit_r8(2, &mut self.reg_b, &mut self.reg_f);},
-                0x51 => {Cpu::bit_r8(2, &mut self.reg_c, &mut self.reg_f);},
-                0x52 => {Cpu::bit_r8(2, &mut self.reg_d, &mut self.reg_f);},
-                0x53 => {Cpu::bit_r8(2, &mut self.reg_e, &mut self.reg_f);},
-                0x54 => {Cpu::bit_r8(2, &mut self.reg_h, &mut self.reg_f);},
-                0x55 => {Cpu::bit_r8(2, &mut self.reg_l, &mut self.reg_f);},
-                0x56 => {Cpu::bit_r8(2, ram.get_rp_ref(self.reg_h, self.reg_l), &mut self.reg_f);},
-                0x57 => {Cpu::bit_r8(2, &mut self.reg_a, &mut self.reg_f);},
-                0x58 => {Cpu::bit_r8(3, &mut self.reg_b, &mut self.reg_f);},
-                0x59 => {Cpu::bit_r8(3, &mut self.reg_c, &mut self.reg_f);},
-                0x5A => {Cpu::bit_r8(3, &mut self.reg_d, &mut self.reg_f);},
-                0x5B => {Cpu::bit_r8(3, &mut self.reg_e, &mut self.reg_f);},
-                0x5C => {Cpu::bit_r8(3, &mut self.reg_h, &mut self.reg_f);},
-                0x5D => {Cpu::bit_r8(3, &mut self.reg_l, &mut self.reg_f);},
-                0x5E => {Cpu::bit_r8(3, ram.get_rp_ref(self.reg_h, self.reg_l), &mut self.reg_f);},
-                0x5F => {Cpu::bit_r8(3, &mut self.reg_a, &mut self.reg_f);},
-                0x60 => {Cpu::bit_r8(4, &mut self.reg_b, &mut self.reg_f);},
-                0x61 => {Cpu::bit_r8(4, &mut self.reg_c, &mut self.reg_f);},
-                0x62 => {Cpu::bit_r8(4, &mut self.reg_d, &mut self.reg_f);},
-                0x63 => {Cpu::bit_r8(4, &mut self.reg_e, &mut self.reg_f);},
-                0x64 => {Cpu::bit_r8(4, &mut self.reg_h, &mut self.reg_f);},
-                0x65 => {Cpu::bit_r8(4, &mut self.reg_l, &mut self.reg_f);},
-                0x66 => {Cpu::bit_r8(4, ram.get_rp_ref(self.reg_h, self.reg_l), &mut self.reg_f);},
-                0x67 => {Cpu::bit_r8(4, &mut self.reg_a, &mut self.reg_f);},
-                0x68 => {Cpu::bit_r8(5, &mut self.reg_b, &mut self.reg_f);},
-                0x69 => {Cpu::bit_r8(5, &mut self.reg_c, &mut self.reg_f);},
-                0x6A => {Cpu::bit_r8(5, &mut self.reg_d, &mut self.reg_f);},
-                0x6B => {Cpu::bit_r8(5, &mut self.reg_e, &mut self.reg_f);},
-                0x6C => {Cpu::bit_r8(5, &mut self.reg_h, &mut self.reg_f);},
-                0x6D => {Cpu::bit_r8(5, &mut self.reg_l, &mut self.reg_f);},
-                0x6E => {Cpu::bit_r8(5, ram.get_rp_ref(self.reg_h, self.reg_l), &mut self.reg_f);},
-                0x6F => {Cpu::bit_r8(5, &mut self.reg_a, &mut self.reg_f);},
-                0x70 => {Cpu::bit_r8(6, &mut self.reg_b, &mut self.reg_f);},
-                0x71 => {Cpu::bit_r8(6, &mut self.reg_c, &mut self.reg_f);},
-                0x72 => {Cpu::bit_r8(6, &mut self.reg_d, &mut self.reg_f);},
-                0x73 => {Cpu::bit_r8(6, &mut self.reg_e, &mut self.reg_f);},
-                0x74 => {Cpu::bit_r8(6, &mut self.reg_h, &mut self.reg_f);},
-                0x75 => {Cpu::bit_r8(6, &mut self.reg_l, &mut self.reg_f);},
-                0x76 => {Cpu::bit_r8(6, ram.get_rp_ref(self.reg_h, self.reg_l), &mut self.reg_f);},
-                0x77 => {Cpu::bit_r8(6, &mut self.reg_a, &mut self.reg_f);},
-                0x78 => {Cpu::bit_r8(7, &mut self.reg_b, &mut self.reg_f);},
-                0x79 => {Cpu::bit_r8(7, &mut self.reg_c, &mut self.reg_f);},
-                0x7A => {Cpu::bit_r8(7, &mut self.reg_d, &mut self.reg_f);},
-                0x7B => {Cpu::bit_r8(7, &mut self.reg_e, &mut self.reg_f);},
-                0x7C => {Cpu::bit_r8(7, &mut self.reg_h, &mut self.reg_f);},
-                0x7D => {Cpu::bit_r8(7, &mut self.reg_l, &mut self.reg_f);},
-                0x7E => {Cpu::bit_r8(7, ram.get_rp_ref(self.reg_h, self.reg_l), &mut self.reg_f);},
-                0x7F => {Cpu::bit_r8(7, &mut self.reg_a, &mut self.reg_f);},
-                0x80 => {Cpu::res_r8(0, &mut self.reg_b);},
-                0x81 => {Cpu::res_r8(0, &mut self.reg_c);},
-                0x82 => {Cpu::res_r8(0, &mut self.reg_d);},
-                0x83 => {Cpu::res_r8(0, &mut self.reg_e);},
-                0x84 => {Cpu::res_r8(0, &mut self.reg_h);},
-                0x85 => {Cpu::res_r8(0, &mut self.reg_l);},
-                0x86 => {Cpu::res_r8(0, ram.get_rp_ref(self.reg_h, self.reg_l));},
-                0x87 => {Cpu::res_r8(0, &mut self.reg_a);},
-                0x88 => {Cpu::res_r8(1, &mut self.reg_b);},
-                0x89 => {Cpu::res_r8(1, &mut self.reg_c);},
-                0x8A => {Cpu::res_r8(1, &mut self.reg_d);},
-                0x8B => {Cpu::res_r8(1, &mut self.reg_e);},
-                0x8C => {Cpu::res_r8(1, &mut self.reg_h);},
-                0x8D => {Cpu::res_r8(1, &mut self.reg_l);},
-                0x8E => {Cpu::res_r8(1, ram.get_rp_ref(self.reg_h, self.reg_l));},
-                0x8F => {Cpu::res_r8(1, &mut self.reg_a);},
-                0x90 => {Cpu::res_r8(2, &mut self.reg_b);},
-                0x91 => {Cpu::res_r8(2, &mut self.reg_c);},
-                0x92 => {Cpu::res_r8(2, &mut self.reg_d);},
-                0x93 => {Cpu::res_r8(2, &mut self.reg_e);},
-                0x94 => {Cpu::res_r8(2, &mut self.reg_h);},
-                0x95 => {Cpu::res_r8(2, &mut self.reg_l);},
-                0x96 => {Cpu::res_r8(2, ram.get_rp_ref(self.reg_h, self.reg_l));},
-                0x97 => {Cpu::res_r8(2, &mut self.reg_a);},
-                0x98 => {Cpu::res_r8(3, &mut self.reg_b);},
-                0x99 => {Cpu::res_r8(3, &mut self.reg_c);},
-                0x9A => {Cpu::res_r8(3, &mut self.reg_d);},
-                0x9B => {Cpu::res_r8(3, &mut self.reg_e);},
-                0x9C => {Cpu::res_r8(3, &mut self.reg_h);},
-                0x9D => {Cpu::res_r8(3, &mut self.reg_l);},
-                0x9E => {Cpu::res_r8(3, ram.get_rp_ref(self.reg_h, self.reg_l));},
-                0x9F => {Cpu::res_r8(3, &mut self.reg_a);},
-                0xA0 => {Cpu::res_r8(4, &mut self.reg_b);},
-                0xA1 => {Cpu::res_r8(4, &mut self.reg_c);},
-                0xA2 => {Cpu::res_r8(4, &mut self.reg_d);},
-                0xA3 => {Cpu::res_r8(4, &mut self.reg_e);},
-                0xA4 => {Cpu::res_r8(4, &mut self.reg_h);},
-                0xA5 => {Cpu::res_r8(4, &mut self.reg_l);},
-                0xA6 => {Cpu::res_r8(4, ram.get_rp_ref(self.reg_h, self.reg_l));},
-                0xA7 => {Cpu::res_r8(4, &mut self.reg_a);},
-                0xA8 => {Cpu::res_r8(5, &mut self.reg_b);},
-                0xA9 => {Cpu::res_r8(5, &mut self.reg_c);},
-                0xAA => {Cpu::res_r8(5, &mut self.reg_d);},
-                0xAB => {Cpu::res_r8(5, &mut self.reg_e);},
-                0xAC => {Cpu::res_r8(5, &mut self.reg_h);},
-                0xAD => {Cpu::res_r8(5, &mut self.reg_l);},
-                0xAE => {Cpu::res_r8(5, ram.get_rp_ref(self.reg_h, self.reg_l));},
-                0xAF => {Cpu::res_r8(5, &mut self.reg_a);},
-                0xB0 => {Cpu::res_r8(6, &mut self.reg_b);},
-                0xB1 => {Cpu::res_r8(6, &mut self.reg_c);},
-                0xB2 => {Cpu::res_r8(6, &mut self.reg_d);},
-                0xB3 => {Cpu::res_r8(6, &mut self.reg_e);},
-                0xB4 => {Cpu::res_r8(6, &mut self.reg_h);},
-                0xB5 => {Cpu::res_r8(6, &mut self.reg_l);},
-                0xB6 => {Cpu::res_r8(6, ram.get_rp_ref(self.reg_h, self.reg_l));},
-                0xB7 => {Cpu::res_r8(6, &mut self.reg_a);},
-                0xB8 => {Cpu::res_r8(7, &mut self.reg_b);},
-                0xB9 => {Cpu::res_r8(7, &mut self.reg_c);},
-                0xBA => {Cpu::res_r8(7, &mut self.reg_d);},
-                0xBB => {Cpu::res_r8(7, &mut self.reg_e);},
-                0xBC => {Cpu::res_r8(7, &mut self.reg_h);},
-                0xBD => {Cpu::res_r8(7, &mut self.reg_l);},
-                0xBE => {Cpu::res_r8(7, ram.get_rp_ref(self.reg_h, self.reg_l));},
-                0xBF => {Cpu::res_r8(7, &mut self.reg_a);},
-                0xC0 => {Cpu::set_r8(0, &mut self.reg_b);},
-                0xC1 => {Cpu::set_r8(0, &mut self.reg_c);},
-                0xC2 => {Cpu::set_r8(0, &mut self.reg_d);},
-                0xC3 => {Cpu::set_r8(0, &mut self.reg_e);},
-                0xC4 => {Cpu::set_r8(0, &mut self.reg_h);},
-                0xC5 => {Cpu::set_r8(0, &mut self.reg_l);},
-                0xC6 => {Cpu::set_r8(0, ram.get_rp_ref(self.reg_h, self.reg_l));},
-                0xC7 => {Cpu::set_r8(0, &mut self.reg_a);},
-                0xC8 => {Cpu::set_r8(1, &mut self.reg_b);},
-                0xC9 => {Cpu::set_r8(1, &mut self.reg_c);},
-                0xCA => {Cpu::set_r8(1, &mut self.reg_d);},
-                0xCB => {Cpu::set_r8(1, &mut self.reg_e);},
-                0xCC => {Cpu::set_r8(1, &mut self.reg_h);},
-                0xCD => {Cpu::set_r8(1, &mut self.reg_l);},
-                0xCE => {Cpu::set_r8(1, ram.get_rp_ref(self.reg_h, self.reg_l));},
-                0xCF => {Cpu::set_r8(1, &mut self.reg_a);},
-                0xD0 => {Cpu::set_r8(2, &mut self.reg_b);},
-                0xD1 => {Cpu::set_r8(2, &mut self.reg_c);},
-                0xD2 => {Cpu::set_r8(2, &mut self.reg_d);},
-                0xD3 => {Cpu::set_r8(2, &mut self.reg_e);},
-                0xD4 => {Cpu::set_r8(2, &mut self.reg_h);},
-                0xD5 => {Cpu::set_r8(2, &mut self.reg_l);},
-                0xD6 => {Cpu::set_r8(2, ram.get_rp_ref(self.reg_h, self.reg_l));},
-                0xD7 => {Cpu::set_r8(2, &mut self.reg_a);},
-                0xD8 => {Cpu::set_r8(3, &mut self.reg_b);},
-                0xD9 => {Cpu::set_r8(3, &mut self.reg_c);},
-                0xDA => {Cpu::set_r8(3, &mut self.reg_d);},
-                0xDB => {Cpu::set_r8(3, &mut self.reg_e);},
-                0xDC => {Cpu::set_r8(3, &mut self.reg_h);},
-                0xDD => {Cpu::set_r8(3, &mut self.reg_l);},
-                0xDE => {Cpu::set_r8(3, ram.get_rp_ref(self.reg_h, self.reg_l));},
-                0xDF => {Cpu::set_r8(3, &mut self.reg_a);},
-                0xE0 => {Cpu::set_r8(4, &mut self.reg_b);},
-                0xE1 => {Cpu::set_r8(4, &mut self.reg_c);},
-                0xE2 => {Cpu::set_r8(4, &mut self.reg_d);},
-                0xE3 => {Cpu::set_r8(4, &mut self.reg_e);},
-                0xE4 => {Cpu::set_r8(4, &mut self.reg_h);},
-                0xE5 => {Cpu::set_r8(4, &mut self.reg_l);},
-                0xE6 => {Cpu::set_r8(4, ram.get_rp_ref(self.reg_h, self.reg_l));},
-                0xE7 => {Cpu::set_r8(4, &mut self.reg_a);},
-                0xE8 => {Cpu::set_r8(5, &mut self.reg_b);},
-                0xE9 => {Cpu::set_r8(5, &mut self.reg_c);},
-                0xEA => {Cpu::set_r8(5, &mut self.reg_d);},
-                0xEB => {Cpu::set_r8(5, &mut self.reg_e);},
-                0xEC => {Cpu::set_r8(5, &mut self.reg_h);},
-                0xED => {Cpu::set_r8(5, &mut self.reg_l);},
-                0xEE => {Cpu::set_r8(5, ram.get_rp_ref(self.reg_h, self.reg_l));},
-                0xEF => {Cpu::set_r8(5, &mut self.reg_a);},
-                0xF0 => {Cpu::set_r8(6, &mut self.reg_b);},
-                0xF1 => {Cpu::set_r8(6, &mut self.reg_c);},
-                0xF2 => {Cpu::set_r8(6, &mut self.reg_d);},
-                0xF3 => {Cpu::set_r8(6, &mut self.reg_e);},
-                0xF4 => {Cpu::set_r8(6, &mut self.reg_h);},
-                0xF5 => {Cpu::set_r8(6, &mut self.reg_l);},
-                0xF6 => {Cpu::set_r8(6, ram.get_rp_ref(self.reg_h, self.reg_l));},
-                0xF7 => {Cpu::set_r8(6, &mut self.reg_a);},
-                0xF8 => {Cpu::set_r8(7, &mut self.reg_b);},
-                0xF9 => {Cpu::set_r8(7, &mut self.reg_c);},
-                0xFA => {Cpu::set_r8(7, &mut self.reg_d);},
-                0xFB => {Cpu::set_r8(7, &mut self.reg_e);},
-                0xFC => {Cpu::set_r8(7, &mut self.reg_h);},
-                0xFD => {Cpu::set_r8(7, &mut self.reg_l);},
-                0xFE => {Cpu::set_r8(7, ram.get_rp_ref(self.reg_h, self.reg_l));},
-                0xFF => {Cpu::set_r8(7, &mut self.reg_a);}
+    ///The low nibble of F always reads back as 0
+    #[inline(always)]
+    fn write_r16(&mut self, r: R16, value: u16)
+    {
+        let [lsh, msh] = value.to_le_bytes();
+        match r
+        {
+            R16::Sp => self.sp = value,
+            R16::Af => {
+                self.reg_a = msh;
+                self.reg_f = CpuFlags::from_bits_truncate(lsh);
+            },
+            _ => {
+                let (msh_reg, lsh_reg) = self.pair(r);
+                Cpu::ld_r16_16(msh_reg, lsh_reg, msh, lsh);
             }
+        }
+    }
+
+    ///Returns the address as (msh, lsh) and moves HL past it for the HL+/HL- forms
+    #[inline(always)]
+    fn indirect(&mut self, r: Indirect) -> (u8, u8)
+    {
+        match r
+        {
+            Indirect::Bc => (self.reg_b, self.reg_c),
+            Indirect::De => (self.reg_d, self.reg_e),
+            Indirect::HlIncrement => {
+                let address = (self.reg_h, self.reg_l);
+                Cpu::inc_r16(&mut self.reg_h, &mut self.reg_l);
+                address
+            },
+            Indirect::HlDecrement => {
+                let address = (self.reg_h, self.reg_l);
+                Cpu::dec_r16(&mut self.reg_h, &mut self.reg_l);
+                address
+            }
+        }
+    }
 
-            self.pc.current_instruction_cycles += CB_INSTRUCTION_TIME_TABLE[cb_instruction as usize];
+    #[inline(always)]
+    fn alu(&mut self, operation: Alu, data: u8)
+    {
+        let (a, flags) = (&mut self.reg_a, &mut self.reg_f);
+        match operation
+        {
+            Alu::Add => Cpu::add_r8_8(a, data, flags),
+            Alu::Adc => Cpu::adc_r8_8(a, data, flags),
+            Alu::Sub => Cpu::sub_r8_8(a, data, flags),
+            Alu::Sbc => Cpu::sbc_r8_8(a, data, flags),
+            Alu::And => Cpu::and_r8_8(a, data, flags),
+            Alu::Xor => Cpu::xor_r8_8(a, data, flags),
+            Alu::Or => Cpu::or_r8_8(a, data, flags),
+            Alu::Cp => Cpu::cp_r8_8(a, data, flags)
         }
+    }
 
-        self.aux_inc_pc();
+    ///Illegal opcodes hang the CPU for good, interrupts included
+    fn lock_up(&mut self, address: u16, opcode: u8)
+    {
+        self.locked = true;
+        self.lock_up_report = Some((address, opcode));
     }
 
-    fn invalid_instruction(&self, opcode: u8)
+    ///Address and opcode of the illegal instruction that locked the CPU, returned once
+    pub fn take_lock_up(&mut self) -> Option<(u16, u8)>
     {
-        panic!("Tried to execute invalid instruction {:X?}", opcode);
+        self.lock_up_report.take()
     }
 
     // regs: [u8;8],
@@ -4,6 +4,10 @@ mod alu_tests;
 mod bitwise_tests;
 mod ld_tests;
 mod jump_branch_tests;
+mod interrupt_tests;
+mod timing_tests;
+mod program_tests;
+mod single_step_tests;
 
 // #[test]
 // fn benchmark_test()
@@ -1,9 +1,8 @@
-use crate::cpu::*;
+use crate::{bus::FlatMemory, cpu::*};
 
 #[test]
 fn test_aux_inc_16()
 {
-    let mut proc = Cpu::new();
     let result = Cpu::aux_inc_16(0x12, 0x34);
     assert_eq!(result, (0x35, 0x12));
     let result2 = Cpu::aux_inc_16(0x00, 0xFF);
@@ -12,6 +11,69 @@ fn test_aux_inc_16()
     assert_eq!(result3, (0x00, 0x00));
 }
 
+#[test]
+fn test_add_sp_i8()
+{
+    //(SP, offset, result, H, C), the flags come from the low byte added as unsigned
+    let cases:[(u16, i8, u16, bool, bool); 6] =
+    [
+        (0x1000, 0x01, 0x1001, false, false),
+        (0x000F, 0x01, 0x0010, true, false),
+        (0x00FF, 0x01, 0x0100, true, true),
+        (0x1000, -1, 0x0FFF, false, false),
+        (0x1001, -1, 0x1000, true, true),
+        (0xFFFF, 0x01, 0x0000, true, true)
+    ];
+    for (sp, offset, result, half_carry, carry) in cases
+    {
+        let mut proc = Cpu::new();
+        proc.sp = sp;
+        proc.reg_f = CpuFlags::FLAG_Z | CpuFlags::FLAG_N;
+        Cpu::add_sp_i8(&mut proc.sp, offset, &mut proc.reg_f);
+        assert_eq!(proc.sp, result, "{:04X} {}", sp, offset);
+        assert_eq!(proc.reg_f.contains(CpuFlags::FLAG_H), half_carry, "{:04X} {}", sp, offset);
+        assert_eq!(proc.reg_f.contains(CpuFlags::FLAG_C), carry, "{:04X} {}", sp, offset);
+        assert!(!proc.reg_f.intersects(CpuFlags::FLAG_Z | CpuFlags::FLAG_N));
+    }
+}
+
+#[test]
+fn test_ld_hl_sp_plus_flags()
+{
+    let mut proc = Cpu::new();
+    proc.sp = 0x0000;
+    proc.reg_f = CpuFlags::FLAG_Z | CpuFlags::FLAG_N | CpuFlags::FLAG_H | CpuFlags::FLAG_C;
+    Cpu::ld_hl_sp_plus(&mut proc.sp, &mut proc.reg_h, &mut proc.reg_l, -128, &mut proc.reg_f);
+    assert_eq!((proc.reg_h, proc.reg_l), (0xFF, 0x80));
+    assert_eq!(proc.sp, 0x0000);
+    assert!(proc.reg_f.is_empty());
+    proc.sp = 0xFFF8;
+    Cpu::ld_hl_sp_plus(&mut proc.sp, &mut proc.reg_h, &mut proc.reg_l, 0x08, &mut proc.reg_f);
+    assert_eq!((proc.reg_h, proc.reg_l), (0x00, 0x00));
+    assert_eq!(proc.reg_f, CpuFlags::FLAG_H | CpuFlags::FLAG_C);
+}
+
+#[test]
+fn test_stack_wraps()
+{
+    let mut proc = Cpu::new();
+    let mut mem = FlatMemory::new();
+    proc.sp = 0x0001;
+    (proc.reg_b, proc.reg_c) = (0x12, 0x34);
+    Cpu::push_r16(&mut mem, &mut proc.sp, &mut proc.reg_b, &mut proc.reg_c);
+    assert_eq!(proc.sp, 0xFFFF);
+    assert_eq!((mem.read(0x0000), mem.read(0xFFFF)), (0x12, 0x34));
+    Cpu::pop_r16(&mut mem, &mut proc.sp, &mut proc.reg_d, &mut proc.reg_e);
+    assert_eq!(proc.sp, 0x0001);
+    assert_eq!((proc.reg_d, proc.reg_e), (0x12, 0x34));
+
+    proc.sp = 0x0000;
+    proc.pc.reg = 0x4321;
+    Cpu::rst(&mut mem, 0x38, &mut proc.pc, &mut proc.sp);
+    assert_eq!(proc.sp, 0xFFFE);
+    assert_eq!((mem.read(0xFFFF), mem.read(0xFFFE)), (0x43, 0x21));
+}
+
 #[test]
 fn test_inc_r16()
 {
@@ -112,30 +174,30 @@ fn test_dec_sp()
 }
 
 #[test]
-fn test_add_r8_r8()
+fn test_alu_add()
 {
     let mut proc = Cpu::new();
     //Test add
     proc.reg_a = 0b00000111;
     proc.reg_b = 1;
-    Cpu::add_r8_r8(&mut proc.reg_a, &mut proc.reg_b, &mut proc.reg_f);
+    proc.alu(Alu::Add, proc.reg_b);
     assert_eq!(proc.reg_a, 8);
     //Test flag H (ZNHC0000)
     proc.reg_a = 1;
     proc.reg_b = 0b00001111;
-    Cpu::add_r8_r8(&mut proc.reg_a, &mut proc.reg_b, &mut proc.reg_f);
+    proc.alu(Alu::Add, proc.reg_b);
     assert_eq!(proc.reg_a, 16);
     assert_eq!(proc.reg_f, CpuFlags::FLAG_H);
     //Test flag Z
     proc.reg_a = 0;
     proc.reg_b = 0;
-    Cpu::add_r8_r8(&mut proc.reg_a, &mut proc.reg_b, &mut proc.reg_f);
+    proc.alu(Alu::Add, proc.reg_b);
     assert_eq!(proc.reg_a, 0);
     assert_eq!(proc.reg_f, CpuFlags::FLAG_Z);
     //Test flag C
     proc.reg_a = 0b11111111;
     proc.reg_b = 2;
-    Cpu::add_r8_r8(&mut proc.reg_a, &mut proc.reg_b, &mut proc.reg_f);
+    proc.alu(Alu::Add, proc.reg_b);
     assert_eq!(proc.reg_a, 1);
     assert_eq!(proc.reg_f, CpuFlags::FLAG_H | CpuFlags::FLAG_C); //Half carry also occurs
 }
@@ -166,52 +228,61 @@ fn test_add_r8_8()
 }
 
 #[test]
-fn test_add_r16_r16()
+fn test_add_r16_16()
 {
     let mut proc = Cpu::new();
     //Test add
     proc.reg_h = 0;
     proc.reg_l = 0b11111111;
-    proc.reg_b = 0;
-    proc.reg_c = 0b00000001;
     proc.reg_f = CpuFlags::empty();
-    Cpu::add_r16_r16(&mut proc.reg_h, &mut proc.reg_l, &mut proc.reg_b, &mut proc.reg_c, &mut proc.reg_f);
+    Cpu::add_r16_16(&mut proc.reg_h, &mut proc.reg_l, 0x0001, &mut proc.reg_f);
     assert_eq!(proc.reg_h, 1);
     assert_eq!(proc.reg_l, 0);
     assert!(proc.reg_f.is_empty());
     //Test Carry and Zero (Zero should be unchanged)
     proc.reg_h = 0b11111111;
     proc.reg_l = 0b11111111;
-    proc.reg_b = 0;
-    proc.reg_c = 0b00000001;
     proc.reg_f = CpuFlags::empty();
-    Cpu::add_r16_r16(&mut proc.reg_h, &mut proc.reg_l, &mut proc.reg_b, &mut proc.reg_c, &mut proc.reg_f);
+    Cpu::add_r16_16(&mut proc.reg_h, &mut proc.reg_l, 0x0001, &mut proc.reg_f);
     assert_eq!(proc.reg_h, 0);
     assert_eq!(proc.reg_l, 0);
     assert_eq!(proc.reg_f, CpuFlags::FLAG_H | CpuFlags::FLAG_C);
     //Test Half Carry
     proc.reg_h = 0b00001111;
     proc.reg_l = 0b11111111;
-    proc.reg_b = 0;
-    proc.reg_c = 0b00000001;
     proc.reg_f = CpuFlags::empty();
-    Cpu::add_r16_r16(&mut proc.reg_h, &mut proc.reg_l, &mut proc.reg_b, &mut proc.reg_c, &mut proc.reg_f);
+    Cpu::add_r16_16(&mut proc.reg_h, &mut proc.reg_l, 0x0001, &mut proc.reg_f);
     assert_eq!(proc.reg_h, 0b00010000);
     assert_eq!(proc.reg_l, 0);
     assert_eq!(proc.reg_f, CpuFlags::FLAG_H);
 }
 
 #[test]
-fn test_and_r8_r8()
+fn test_alu_operands()
+{
+    let mut proc = Cpu::new();
+    let mut mem = FlatMemory::new();
+    proc.reg_a = 0b00001100;
+    proc.reg_l = 0b00001010;
+    proc.execute_op(&mut mem, Op::Alu(Alu::And, R8::L), 0);
+    assert_eq!(proc.reg_a, 0b00001000);
+    (proc.reg_h, proc.reg_l) = (0xC0, 0x00);
+    mem.write(0xC000, 3);
+    proc.execute_op(&mut mem, Op::Alu(Alu::Add, R8::Hl), 0);
+    assert_eq!(proc.reg_a, 0b00001011);
+}
+
+#[test]
+fn test_alu_and()
 {
     let mut proc = Cpu::new();
     proc.reg_a = 0b10101011;
     proc.reg_b = 0b01010101;
-    Cpu::and_r8_r8(&mut proc.reg_a, &mut proc.reg_b, &mut proc.reg_f);
+    proc.alu(Alu::And, proc.reg_b);
     assert_eq!(proc.reg_a, 1);
     assert_eq!(proc.reg_f, CpuFlags::FLAG_H);
     proc.reg_b = 0b01010100;
-    Cpu::and_r8_r8(&mut proc.reg_a, &mut proc.reg_b, &mut proc.reg_f);
+    proc.alu(Alu::And, proc.reg_b);
     assert_eq!(proc.reg_f, CpuFlags::FLAG_Z | CpuFlags::FLAG_H);
 }
 
@@ -228,16 +299,16 @@ fn test_and_r8_8()
 }
 
 #[test]
-fn test_xor_r8_r8()
+fn test_alu_xor()
 {
     let mut proc = Cpu::new();
     proc.reg_a = 0b10101011;
     proc.reg_b = 0b01010101;
-    Cpu::xor_r8_r8(&mut proc.reg_a, &mut proc.reg_b, &mut proc.reg_f);
+    proc.alu(Alu::Xor, proc.reg_b);
     assert_eq!(proc.reg_a, 0b11111110);
     assert!(proc.reg_f.is_empty());
     proc.reg_b = 0b11111110;
-    Cpu::xor_r8_r8(&mut proc.reg_a, &mut proc.reg_b, &mut proc.reg_f);
+    proc.alu(Alu::Xor, proc.reg_b);
     assert_eq!(proc.reg_f, CpuFlags::FLAG_Z);
 }
 
@@ -254,17 +325,17 @@ fn test_xor_r8_8()
 }
 
 #[test]
-fn test_or_r8_r8()
+fn test_alu_or()
 {
     let mut proc = Cpu::new();
     proc.reg_a = 0b10101011;
     proc.reg_b = 0b01010101;
-    Cpu::or_r8_r8(&mut proc.reg_a, &mut proc.reg_b, &mut proc.reg_f);
+    proc.alu(Alu::Or, proc.reg_b);
     assert_eq!(proc.reg_a, 0b11111111);
     assert!(proc.reg_f.is_empty());
     proc.reg_a = 0b00000000;
     proc.reg_b = 0b00000000;
-    Cpu::or_r8_r8(&mut proc.reg_a, &mut proc.reg_b, &mut proc.reg_f);
+    proc.alu(Alu::Or, proc.reg_b);
     assert_eq!(proc.reg_f, CpuFlags::FLAG_Z);
 }
 
@@ -281,6 +352,19 @@ fn test_or_r8_8()
     assert_eq!(proc.reg_f, CpuFlags::FLAG_Z);
 }
 
+#[test]
+fn test_cp_r8_8()
+{
+    let mut proc = Cpu::new();
+    proc.reg_a = 0x42;
+    Cpu::cp_r8_8(&mut proc.reg_a, 0x42, &mut proc.reg_f);
+    assert_eq!(proc.reg_a, 0x42);
+    assert_eq!(proc.reg_f, CpuFlags::FLAG_Z | CpuFlags::FLAG_N);
+    Cpu::cp_r8_8(&mut proc.reg_a, 0x43, &mut proc.reg_f);
+    assert_eq!(proc.reg_a, 0x42);
+    assert_eq!(proc.reg_f, CpuFlags::FLAG_N | CpuFlags::FLAG_H | CpuFlags::FLAG_C);
+}
+
 #[test]
 fn test_cpl()
 {
@@ -309,3 +393,29 @@ fn test_scf()
     Cpu::scf(&mut proc.reg_f);
     assert!(proc.reg_f.contains(CpuFlags::FLAG_C));
 }
+
+#[test]
+fn test_daa()
+{
+    //Every BCD addition and subtraction comes out as BCD, with C as the decimal carry
+    let bcd = |x: u8| ((x / 10) << 4) | (x % 10);
+    let mut proc = Cpu::new();
+    for x in 0..100
+    {
+        for y in 0..100
+        {
+            proc.reg_a = bcd(x);
+            Cpu::add_r8_8(&mut proc.reg_a, bcd(y), &mut proc.reg_f);
+            Cpu::daa(&mut proc.reg_a, &mut proc.reg_f);
+            assert_eq!(proc.reg_a, bcd((x + y) % 100), "{} + {}", x, y);
+            assert_eq!(proc.reg_f.contains(CpuFlags::FLAG_C), x + y >= 100, "{} + {}", x, y);
+            assert_eq!(proc.reg_f.contains(CpuFlags::FLAG_Z), (x + y) % 100 == 0);
+
+            proc.reg_a = bcd(x);
+            Cpu::sub_r8_8(&mut proc.reg_a, bcd(y), &mut proc.reg_f);
+            Cpu::daa(&mut proc.reg_a, &mut proc.reg_f);
+            assert_eq!(proc.reg_a, bcd((100 + x - y) % 100), "{} - {}", x, y);
+            assert_eq!(proc.reg_f.contains(CpuFlags::FLAG_C), x < y, "{} - {}", x, y);
+        }
+    }
+}
@@ -1,4 +1,4 @@
-use crate::cpu::*;
+use crate::{bus::{Bus, FlatMemory}, cpu::*, ram};
 
 #[test]
 fn test_halt()
@@ -11,9 +11,83 @@ fn test_halt()
 #[test]
 fn test_stop()
 {
+    //STOP then INC A, with nothing held STOP skips a byte and stops until a joypad line goes low
+    let mut mem = FlatMemory::new();
+    mem.load(0x0000, &[0x10, 0x3C, 0x3C]);
+    mem.write(ram::P1, 0xFF);
     let mut cpu = Cpu::new();
-    cpu.stop();
-    assert!(cpu.halted && cpu.stopped);
+    assert_eq!(cpu.step(&mut mem), 1);
+    assert!(cpu.stopped && !cpu.halted);
+    assert_eq!(cpu.pc.reg, 0x0002);
+    //Interrupts don't wake it
+    mem.write(ram::IF, 0x01);
+    mem.write(ram::IE, 0x01);
+    assert_eq!(cpu.step(&mut mem), 1);
+    assert_eq!((cpu.pc.reg, cpu.reg_a), (0x0002, 0x00));
+    mem.write(ram::P1, 0xFE);
+    cpu.step(&mut mem);
+    assert!(!cpu.stopped);
+    assert_eq!((cpu.pc.reg, cpu.reg_a), (0x0003, 0x01));
+}
+
+#[test]
+fn test_stop_encodings()
+{
+    //(P1, pending interrupt) -> (PC after, halted, stopped)
+    let cases =
+    [
+        ((0xFF, false), (0x0002, false, true)),
+        ((0xFF, true), (0x0001, false, true)),
+        ((0xEE, false), (0x0002, true, false)),
+        ((0xEE, true), (0x0001, false, false))
+    ];
+    for ((p1, pending), expected) in cases
+    {
+        let mut mem = FlatMemory::new();
+        mem.load(0x0000, &[0x10, 0x00]);
+        mem.write(ram::P1, p1);
+        mem.write(ram::IE, pending as u8);
+        mem.write(ram::IF, pending as u8);
+        let mut cpu = Cpu::new();
+        cpu.step(&mut mem);
+        assert_eq!((cpu.pc.reg, cpu.halted, cpu.stopped), expected, "P1 {:02X}, pending {}", p1, pending);
+    }
+}
+
+#[test]
+fn test_decode()
+{
+    let illegal:Vec<usize> = (0..0x100).filter(|x| OPS[*x] == Op::Illegal).collect();
+    assert_eq!(illegal, [0xD3, 0xDB, 0xDD, 0xE3, 0xE4, 0xEB, 0xEC, 0xED, 0xF4, 0xFC, 0xFD]);
+    assert_eq!(OPS[0x22], Op::StoreA(Indirect::HlIncrement));
+    assert_eq!(OPS[0x39], Op::AddHl(R16::Sp));
+    assert_eq!(OPS[0x76], Op::Halt);
+    assert_eq!(OPS[0x7E], Op::Ld(R8::A, R8::Hl));
+    assert_eq!(OPS[0x9E], Op::Alu(Alu::Sbc, R8::Hl));
+    assert_eq!(OPS[0xC0], Op::Ret(Cond::NotFlag(CpuFlags::FLAG_Z)));
+    assert_eq!(OPS[0xD9], Op::Reti);
+    assert_eq!(OPS[0xDA], Op::Jp(Cond::Flag(CpuFlags::FLAG_C)));
+    assert_eq!(OPS[0xF1], Op::Pop(R16::Af));
+    assert_eq!(OPS[0xFF], Op::Rst(0x38));
+    assert_eq!(CB_OPS[0x06], CbOp::Shift(Shift::Rlc, R8::Hl));
+    assert_eq!(CB_OPS[0x37], CbOp::Shift(Shift::Swap, R8::A));
+    assert_eq!(CB_OPS[0x5E], CbOp::Bit(3, R8::Hl));
+    assert_eq!(CB_OPS[0x90], CbOp::Res(2, R8::B));
+    assert_eq!(CB_OPS[0xFF], CbOp::Set(7, R8::A));
+}
+
+#[test]
+fn test_set_post_boot_registers()
+{
+    let mut cpu = Cpu::new();
+    cpu.sp = 0;
+    cpu.set_post_boot_registers(0x01B0, 0x0013, 0x00D8, 0x014D);
+    assert_eq!((cpu.reg_a, cpu.reg_f.bits()), (0x01, 0xB0));
+    assert_eq!((cpu.reg_b, cpu.reg_c, cpu.reg_d, cpu.reg_e, cpu.reg_h, cpu.reg_l), (0x00, 0x13, 0x00, 0xD8, 0x01, 0x4D));
+    assert_eq!((cpu.sp, cpu.pc.reg), (0xFFFE, 0x0100));
+    //Low nibble of F doesn't exist
+    cpu.set_post_boot_registers(0x11FF, 0, 0, 0);
+    assert_eq!(cpu.reg_f.bits(), 0xF0);
 }
 
 #[test]
@@ -35,7 +109,7 @@ fn test_aux_inc_pc()
 fn test_aux_read_pc()
 {
     let mut cpu = Cpu::new();
-    let mut ram = Ram::new();
+    let mut ram = FlatMemory::new();
     ram.write(0x5050, 0x12);
     cpu.pc.reg = 0x5050;
     let result = cpu.aux_read_pc(&mut ram);
@@ -46,11 +120,14 @@ fn test_aux_read_pc()
 fn aux_read_immediate_data()
 {
     let mut cpu = Cpu::new();
-    let mut ram = Ram::new();
+    let mut ram = FlatMemory::new();
     ram.write(0x5050, 0x01);
     ram.write(0x5051, 0x02);
     cpu.pc.reg = 0x5050;
     let result = cpu.aux_read_immediate_data(&mut ram);
+    assert_eq!(result, 0x01);
+    assert_eq!(cpu.pc.reg, 0x5051);
+    let result = cpu.aux_read_immediate_data(&mut ram);
     assert_eq!(result, 0x02);
 }
 
@@ -58,14 +135,14 @@ fn aux_read_immediate_data()
 fn test_reti()
 {
     let mut proc = Cpu::new();
-    let mut mem = Ram::new();
+    let mut mem = FlatMemory::new();
     mem.write(0xFFFD, 0x69); //LSH
     mem.write(0xFFFE, 0x42); //MSH
     proc.sp = 0xFFFD;
     Cpu::reti(&mut mem, &mut proc.sp, &mut proc.pc, &mut proc.ime);
     assert_eq!(proc.sp, 0xFFFF);
     assert_eq!(proc.pc.reg, 0x4269);
-    assert_eq!(proc.ime, true);
+    assert!(proc.ime);
 }
 
 #[test]
@@ -73,8 +150,10 @@ fn test_ei()
 {
     let mut proc = Cpu::new();
     proc.ime = false;
-    Cpu::ei(&mut proc.ime);
-    assert_eq!(proc.ime, true);
+    Cpu::ei(&mut proc.ei_delay);
+    //Takes effect after the next instruction
+    assert!(!proc.ime);
+    assert_eq!(proc.ei_delay, EI_DELAY);
 }
 
 #[test]
@@ -82,6 +161,79 @@ fn test_di()
 {
     let mut proc = Cpu::new();
     proc.ime = true;
-    Cpu::di(&mut proc.ime);
-    assert_eq!(proc.ime, false);
-}
\ No newline at end of file
+    proc.ei_delay = EI_DELAY;
+    Cpu::di(&mut proc.ime, &mut proc.ei_delay);
+    assert!(!proc.ime);
+    assert_eq!(proc.ei_delay, 0);
+}
+#[test]
+fn test_illegal_opcode_locks_up()
+{
+    for opcode in [0xD3, 0xDB, 0xDD, 0xE3, 0xE4, 0xEB, 0xEC, 0xED, 0xF4, 0xFC, 0xFD]
+    {
+        let mut cpu = Cpu::new();
+        let mut mem = FlatMemory::new();
+        mem.load(0x0200, &[opcode, 0x00]);
+        cpu.pc.reg = 0x0200;
+        cpu.step(&mut mem);
+        assert!(cpu.locked);
+        assert_eq!(cpu.take_lock_up(), Some((0x0200, opcode)));
+        assert_eq!(cpu.take_lock_up(), None);
+
+        //Not even an interrupt gets it going again
+        cpu.ime = true;
+        mem.write(ram::IE, 0x01);
+        mem.write(ram::IF, 0x01);
+        assert_eq!(cpu.step(&mut mem), 1);
+        assert_eq!(cpu.pc.reg, 0x0201);
+        assert_eq!(cpu.sp, 0xFFFE);
+    }
+}
+
+#[test]
+fn test_trace()
+{
+    let trace = crate::tests::SharedBuffer::default();
+    let mut cpu = Cpu::new();
+    let mut memory = FlatMemory::new();
+    memory.load(0x0100, &[0x3E, 0x12, 0x76]);
+    cpu.pc.reg = 0x0100;
+    cpu.set_trace(Some(Box::new(trace.clone())));
+    for _ in 0..3
+    {
+        cpu.step(&mut memory);
+    }
+    //Nothing is logged while halted
+    assert_eq!(trace.lines(), vec![
+        "A:00 F:00 B:00 C:00 D:00 E:00 H:00 L:00 SP:FFFE PC:0100 PCMEM:3E,12,76,00",
+        "A:12 F:00 B:00 C:00 D:00 E:00 H:00 L:00 SP:FFFE PC:0102 PCMEM:76,00,00,00"
+    ]);
+}
+
+#[test]
+fn test_registers()
+{
+    let mut cpu = Cpu::new();
+    cpu.set_post_boot_registers(0x01B0, 0x0013, 0x00D8, 0x014D);
+    let mut registers = cpu.registers();
+    assert_eq!((registers.af(), registers.bc(), registers.de(), registers.hl()), (0x01B0, 0x0013, 0x00D8, 0x014D));
+    assert_eq!((registers.sp, registers.pc), (0xFFFE, 0x0100));
+    assert!(registers.f.contains(CpuFlags::FLAG_Z | CpuFlags::FLAG_H | CpuFlags::FLAG_C));
+
+    registers.set_af(0x12FF);
+    registers.set_bc(0x3456);
+    registers.set_de(0x789A);
+    registers.set_hl(0xBCDE);
+    registers.sp = 0xD000;
+    registers.pc = 0xC000;
+    registers.halted = true;
+    assert_eq!((registers.a, registers.f.bits()), (0x12, 0xF0));
+    assert_eq!([registers.b, registers.c, registers.d, registers.e, registers.h, registers.l], [0x34, 0x56, 0x78, 0x9A, 0xBC, 0xDE]);
+
+    //A pending EI is dropped when IME is set directly
+    cpu.ei_delay = 2;
+    cpu.set_registers(registers);
+    assert_eq!(cpu.registers(), registers);
+    assert_eq!((cpu.reg_h, cpu.sp, cpu.pc.reg, cpu.ei_delay), (0xBC, 0xD000, 0xC000, 0));
+    assert!(cpu.halted && !cpu.ime);
+}
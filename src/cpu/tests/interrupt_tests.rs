@@ -0,0 +1,191 @@
+use crate::{cpu::*, ram::Ram};
+
+///Runs one instruction (or interrupt dispatch) to completion, returns the M-cycles it took
+fn step(cpu: &mut Cpu, ram: &mut Ram) -> u8
+{
+    cpu.execute(ram);
+    let mut cycles = 1;
+    while cpu.pc.current_instruction_cycles > 1
+    {
+        cpu.execute(ram);
+        cycles += 1;
+    }
+    cycles
+}
+
+fn setup(code: &[u8]) -> (Cpu, Ram)
+{
+    let mut cpu = Cpu::new();
+    let mut ram = Ram::new();
+    ram.write(ram::SC_BOOT_ROM_DISABLE, 1);
+    for (i, x) in code.iter().enumerate()
+    {
+        ram.write(0xC000 + i as u16, *x);
+    }
+    cpu.pc.reg = 0xC000;
+    cpu.sp = 0xD000;
+    (cpu, ram)
+}
+
+#[test]
+fn test_dispatch()
+{
+    let (mut cpu, mut ram) = setup(&[0x00]);
+    cpu.ime = true;
+    ram.write(ram::IE, 0x05);
+    ram.write(ram::IF, 0x05);
+    assert_eq!(step(&mut cpu, &mut ram), INTERRUPT_DISPATCH_CYCLES);
+    assert_eq!(cpu.pc.reg, 0x0040);
+    assert!(!cpu.ime);
+    //Only the serviced bit is acknowledged, the unused top bits read as 1
+    assert_eq!(ram.read(ram::IF), 0xE4);
+    assert_eq!(cpu.sp, 0xCFFE);
+    assert_eq!((ram.read(0xCFFF), ram.read(0xCFFE)), (0xC0, 0x00));
+
+    //Timer is next in priority
+    cpu.ime = true;
+    step(&mut cpu, &mut ram);
+    assert_eq!(cpu.pc.reg, 0x0050);
+    assert_eq!(ram.read(ram::IF), 0xE0);
+}
+
+#[test]
+fn test_priority()
+{
+    for (bits, vector) in [(0x1F, 0x40), (0x1E, 0x48), (0x1C, 0x50), (0x18, 0x58), (0x10, 0x60)]
+    {
+        let (mut cpu, mut ram) = setup(&[0x00]);
+        cpu.ime = true;
+        ram.write(ram::IE, 0xFF);
+        ram.write(ram::IF, bits);
+        step(&mut cpu, &mut ram);
+        assert_eq!(cpu.pc.reg, vector);
+    }
+    //Not enabled in IE
+    let (mut cpu, mut ram) = setup(&[0x00]);
+    cpu.ime = true;
+    ram.write(ram::IE, 0x02);
+    ram.write(ram::IF, 0x01);
+    step(&mut cpu, &mut ram);
+    assert_eq!(cpu.pc.reg, 0xC001);
+}
+
+#[test]
+fn test_reti_returns_to_interrupted_instruction()
+{
+    let (mut cpu, mut ram) = setup(&[0x00, 0x00]);
+    ram.write(0x0040, 0xD9);
+    cpu.ime = true;
+    ram.write(ram::IE, 0x01);
+    ram.write(ram::IF, 0x01);
+    step(&mut cpu, &mut ram);
+    assert_eq!(cpu.pc.reg, 0x0040);
+    step(&mut cpu, &mut ram);
+    assert_eq!(cpu.pc.reg, 0xC000);
+    assert!(cpu.ime);
+    //Acknowledged, so the handler isn't entered again
+    step(&mut cpu, &mut ram);
+    assert_eq!(cpu.pc.reg, 0xC001);
+}
+
+#[test]
+fn test_interrupt_call_frame()
+{
+    use crate::callstack::{CallFrame, FrameKind};
+
+    let (mut cpu, mut ram) = setup(&[0x00, 0x00]);
+    ram.write(0x0050, 0xD9);
+    cpu.ime = true;
+    ram.write(ram::IE, 0x04);
+    ram.write(ram::IF, 0x04);
+    step(&mut cpu, &mut ram);
+    assert_eq!(cpu.call_stack().frames(), [CallFrame { kind: FrameKind::Interrupt, source: 0xC000, target: 0x0050, sp: 0xCFFE }]);
+    step(&mut cpu, &mut ram);
+    assert!(cpu.call_stack().frames().is_empty());
+    assert_eq!(cpu.call_stack().mismatch_count(), 0);
+}
+
+#[test]
+fn test_ei_delay()
+{
+    //EI, NOP, NOP
+    let (mut cpu, mut ram) = setup(&[0xFB, 0x00, 0x00]);
+    ram.write(ram::IE, 0x01);
+    ram.write(ram::IF, 0x01);
+    step(&mut cpu, &mut ram);
+    assert!(!cpu.ime);
+    step(&mut cpu, &mut ram);
+    assert_eq!(cpu.pc.reg, 0xC002);
+    assert!(cpu.ime);
+    step(&mut cpu, &mut ram);
+    assert_eq!(cpu.pc.reg, 0x0040);
+    assert_eq!((ram.read(0xCFFF), ram.read(0xCFFE)), (0xC0, 0x02));
+
+    //EI, DI never enables
+    let (mut cpu, mut ram) = setup(&[0xFB, 0xF3, 0x00]);
+    ram.write(ram::IE, 0x01);
+    ram.write(ram::IF, 0x01);
+    for _ in 0..3
+    {
+        step(&mut cpu, &mut ram);
+    }
+    assert!(!cpu.ime);
+    assert_eq!(cpu.pc.reg, 0xC003);
+}
+
+#[test]
+fn test_halt_wakes_without_ime()
+{
+    let (mut cpu, mut ram) = setup(&[0x76, 0x00, 0x00]);
+    ram.write(ram::IE, 0x04);
+    step(&mut cpu, &mut ram);
+    assert!(cpu.halted);
+    step(&mut cpu, &mut ram);
+    assert!(cpu.halted);
+    assert_eq!(cpu.pc.reg, 0xC001);
+
+    //Wakes up and carries on without dispatching
+    ram.write(ram::IF, 0x04);
+    step(&mut cpu, &mut ram);
+    assert!(!cpu.halted);
+    assert_eq!(cpu.pc.reg, 0xC002);
+    assert_eq!(ram.read(ram::IF), 0xE4);
+}
+
+#[test]
+fn test_halt_bug()
+{
+    //HALT, LD A,0x14 is read as LD A,0x3E then INC D
+    let (mut cpu, mut ram) = setup(&[0x76, 0x3E, 0x14]);
+    ram.write(ram::IE, 0x01);
+    ram.write(ram::IF, 0x01);
+    step(&mut cpu, &mut ram);
+    assert!(!cpu.halted);
+    step(&mut cpu, &mut ram);
+    assert_eq!(cpu.reg_a, 0x3E);
+    assert_eq!(cpu.pc.reg, 0xC002);
+    step(&mut cpu, &mut ram);
+    assert_eq!(cpu.reg_d, 0x01);
+    assert_eq!(cpu.pc.reg, 0xC003);
+}
+
+#[test]
+fn test_return_addresses()
+{
+    //JR +2 lands past the offset byte, CALL pushes the address after itself
+    let (mut cpu, mut ram) = setup(&[0x18, 0x02, 0x00, 0x00, 0xCD, 0x00, 0xD1, 0x00]);
+    ram.write(0xD100, 0xC9);
+    step(&mut cpu, &mut ram);
+    assert_eq!(cpu.pc.reg, 0xC004);
+    step(&mut cpu, &mut ram);
+    assert_eq!(cpu.pc.reg, 0xD100);
+    assert_eq!((ram.read(0xCFFF), ram.read(0xCFFE)), (0xC0, 0x07));
+    step(&mut cpu, &mut ram);
+    assert_eq!(cpu.pc.reg, 0xC007);
+
+    //RST pushes the address after the opcode
+    let (mut cpu, mut ram) = setup(&[0xEF]);
+    step(&mut cpu, &mut ram);
+    assert_eq!(cpu.pc.reg, 0x0028);
+    assert_eq!((ram.read(0xCFFF), ram.read(0xCFFE)), (0xC0, 0x01));
+}
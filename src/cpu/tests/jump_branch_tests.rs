@@ -1,4 +1,4 @@
-use crate::cpu::*;
+use crate::{bus::FlatMemory, cpu::*};
 
 #[test]
 fn test_jp_pc_16()
@@ -6,7 +6,7 @@ fn test_jp_pc_16()
     let mut proc = Cpu::new();
     Cpu::jp_pc_16(&mut proc.pc, 0x69, 0x42);
     assert_eq!(proc.pc.reg, 0x6942);
-    assert!(!proc.pc.should_increment);
+    assert!(proc.pc.should_increment);
 }
 
 #[test]
@@ -20,7 +20,7 @@ fn test_jp_flag_pc_16()
     proc.reg_f = CpuFlags::FLAG_Z;
     Cpu::jp_flag_pc_16(&mut proc.pc, CpuFlags::FLAG_Z,0x69, 0x42, &mut proc.reg_f);
     assert_eq!(proc.pc.reg, 0x6942);
-    assert!(!proc.pc.should_increment);
+    assert!(proc.pc.should_increment);
 }
 
 #[test]
@@ -34,7 +34,7 @@ fn test_jp_nflag_pc_16()
     proc.reg_f = CpuFlags::empty();
     Cpu::jp_nflag_pc_16(&mut proc.pc, CpuFlags::FLAG_Z,0x69, 0x42, &mut proc.reg_f);
     assert_eq!(proc.pc.reg, 0x6942);
-    assert!(!proc.pc.should_increment);
+    assert!(proc.pc.should_increment);
 }
 
 #[test]
@@ -43,10 +43,10 @@ fn test_jr_i8()
     let mut proc = Cpu::new();
     Cpu::jr_i8(&mut proc.pc, 0x69);
     assert_eq!(proc.pc.reg, 0x69);
-    assert!(!proc.pc.should_increment);
+    assert!(proc.pc.should_increment);
     Cpu::jr_i8(&mut proc.pc, -0x69);
     assert_eq!(proc.pc.reg, 0);
-    assert!(!proc.pc.should_increment);
+    assert!(proc.pc.should_increment);
 }
 
 #[test]
@@ -63,10 +63,10 @@ fn test_jr_flag_i8()
     proc.reg_f = CpuFlags::FLAG_Z;
     Cpu::jr_flag_i8(&mut proc.pc, CpuFlags::FLAG_Z, 0x69, &mut proc.reg_f);
     assert_eq!(proc.pc.reg, 0x69);
-    assert!(!proc.pc.should_increment);
+    assert!(proc.pc.should_increment);
     Cpu::jr_flag_i8(&mut proc.pc, CpuFlags::FLAG_Z, -0x69, &mut proc.reg_f);
     assert_eq!(proc.pc.reg, 0);
-    assert!(!proc.pc.should_increment);
+    assert!(proc.pc.should_increment);
 }
 
 #[test]
@@ -83,17 +83,17 @@ fn test_jr_nflag_i8()
     proc.reg_f = CpuFlags::empty();
     Cpu::jr_nflag_i8(&mut proc.pc, CpuFlags::FLAG_Z, 0x69, &mut proc.reg_f);
     assert_eq!(proc.pc.reg, 0x69);
-    assert!(!proc.pc.should_increment);
+    assert!(proc.pc.should_increment);
     Cpu::jr_nflag_i8(&mut proc.pc, CpuFlags::FLAG_Z, -0x69, &mut proc.reg_f);
     assert_eq!(proc.pc.reg, 0);
-    assert!(!proc.pc.should_increment);
+    assert!(proc.pc.should_increment);
 }
 
 #[test]
 fn test_call_16()
 {
     let mut proc = Cpu::new();
-    let mut mem = Ram::new();
+    let mut mem = FlatMemory::new();
     proc.sp = 0x6969;
     proc.pc.reg = 0x1234;
     Cpu::call_16(&mut mem, 0x56, 0x78, &mut proc.pc, &mut proc.sp);
@@ -107,7 +107,7 @@ fn test_call_16()
 fn test_call_flag_16()
 {
     let mut proc = Cpu::new();
-    let mut mem = Ram::new();
+    let mut mem = FlatMemory::new();
     proc.sp = 0x6969;
     proc.pc.reg = 0x1234;
     proc.reg_f = CpuFlags::empty();
@@ -128,7 +128,7 @@ fn test_call_flag_16()
 fn test_call_nflag_16()
 {
     let mut proc = Cpu::new();
-    let mut mem = Ram::new();
+    let mut mem = FlatMemory::new();
     proc.sp = 0x6969;
     proc.pc.reg = 0x1234;
     proc.reg_f = CpuFlags::FLAG_Z;
@@ -149,7 +149,7 @@ fn test_call_nflag_16()
 fn test_ret()
 {
     let mut proc = Cpu::new();
-    let mut mem = Ram::new();
+    let mut mem = FlatMemory::new();
     mem.write(0x6968, 0x12);
     mem.write(0x6967, 0x34);
     proc.sp = 0x6967;
@@ -162,7 +162,7 @@ fn test_ret()
 fn test_ret_flag()
 {
     let mut proc = Cpu::new();
-    let mut mem = Ram::new();
+    let mut mem = FlatMemory::new();
     mem.write(0x6968, 0x12);
     mem.write(0x6967, 0x34);
     proc.sp = 0x6967;
@@ -180,7 +180,7 @@ fn test_ret_flag()
 fn test_ret_nflag()
 {
     let mut proc = Cpu::new();
-    let mut mem = Ram::new();
+    let mut mem = FlatMemory::new();
     mem.write(0x6968, 0x12);
     mem.write(0x6967, 0x34);
     proc.sp = 0x6967;
@@ -192,4 +192,33 @@ fn test_ret_nflag()
     Cpu::ret_nflag(&mut mem, &mut proc.pc, &mut proc.sp, CpuFlags::FLAG_Z, &mut proc.reg_f);
     assert_eq!(proc.pc.reg, 0x1234);
     assert_eq!(proc.sp, 0x6969);
-}
\ No newline at end of file
+}
+#[test]
+fn test_call_stack_tracking()
+{
+    use crate::callstack::{CallFrame, FrameKind};
+
+    //CALL 0x0010, then CALL Z (not taken), RST 0x18, RET, RET, then a PUSH HL/RET jump
+    let mut mem = FlatMemory::new();
+    mem.load(0x0000, &[0xCD, 0x10, 0x00, 0xE5, 0xC9]);
+    mem.load(0x0010, &[0xCC, 0x20, 0x00, 0xDF, 0xC9]);
+    mem.load(0x0018, &[0xC9]);
+    let mut cpu = Cpu::new();
+    cpu.step(&mut mem);
+    cpu.step(&mut mem);
+    cpu.step(&mut mem);
+    let frames = [CallFrame { kind: FrameKind::Call, source: 0x0000, target: 0x0010, sp: 0xFFFC },
+        CallFrame { kind: FrameKind::Rst, source: 0x0013, target: 0x0018, sp: 0xFFFA }];
+    assert_eq!(cpu.call_stack().frames(), frames);
+    cpu.step(&mut mem);
+    assert_eq!(cpu.call_stack().frames(), &frames[..1]);
+    cpu.step(&mut mem);
+    assert!(cpu.call_stack().frames().is_empty());
+    assert_eq!((cpu.pc.reg, cpu.call_stack().mismatch_count()), (0x0003, 0));
+
+    //Returning to an address that was pushed by hand is reported
+    cpu.step(&mut mem);
+    cpu.step(&mut mem);
+    assert_eq!(cpu.call_stack().mismatch_count(), 1);
+    assert_eq!(cpu.call_stack().last_mismatch().unwrap().pc, 0x0004);
+}
@@ -1,4 +1,4 @@
-use crate::cpu::*;
+use crate::{bus::FlatMemory, cpu::*};
 
 #[test]
 fn test_ld_r16_16()
@@ -15,13 +15,13 @@ fn test_ld_hl_sp_plus()
 {
     let mut proc = Cpu::new();
     proc.sp = 0x11FF;
-    Cpu::ld_hl_sp_plus(&mut proc.sp, &mut proc.reg_h, &mut proc.reg_l, 1);
+    Cpu::ld_hl_sp_plus(&mut proc.sp, &mut proc.reg_h, &mut proc.reg_l, 1, &mut proc.reg_f);
     assert_eq!(proc.reg_h, 0x12);
     assert_eq!(proc.reg_l, 0x00);
-    Cpu::ld_hl_sp_plus(&mut proc.sp, &mut proc.reg_h, &mut proc.reg_l,-1);
+    Cpu::ld_hl_sp_plus(&mut proc.sp, &mut proc.reg_h, &mut proc.reg_l, -1, &mut proc.reg_f);
     assert_eq!(proc.reg_h, 0x11);
     assert_eq!(proc.reg_l, 0xFE);
-    //CHECK CARRY FLAGS
+    assert_eq!(proc.sp, 0x11FF);
 }
 
 #[test]
@@ -46,7 +46,7 @@ fn test_ld_r8_8()
 fn test_ld_16a_sp()
 {
     let mut proc = Cpu::new();
-    let mut mem = Ram::new();
+    let mut mem = FlatMemory::new();
     proc.sp = 0x1234;
     Cpu::ld_16a_sp(&mut proc.sp, &mut mem, 0x69, 0x42);
     assert_eq!(mem.read(0x6942), 0x34);
@@ -57,19 +57,29 @@ fn test_ld_16a_sp()
 fn test_ld_r8_r8()
 {
     let mut proc = Cpu::new();
+    let mut mem = FlatMemory::new();
     proc.reg_a = 0;
     proc.reg_b = 0x21;
-    Cpu::ld_r8_r8(&mut proc.reg_a, &mut proc.reg_b);
+    proc.execute_op(&mut mem, Op::Ld(R8::A, R8::B), 0);
+    assert_eq!(proc.reg_a, 0x21);
+    proc.execute_op(&mut mem, Op::Ld(R8::A, R8::A), 0);
     assert_eq!(proc.reg_a, 0x21);
 }
 
 #[test]
-fn test_ld_r8_r8_s()
+fn test_ld_r8_hl()
 {
     let mut proc = Cpu::new();
-    proc.reg_a = 0x21;
-    Cpu::ld_r8_r8_s(&mut proc.reg_a);
-    assert_eq!(proc.reg_a, 0x21);
+    let mut mem = FlatMemory::new();
+    (proc.reg_h, proc.reg_l) = (0xC0, 0x10);
+    mem.write(0xC010, 0x42);
+    proc.execute_op(&mut mem, Op::Ld(R8::D, R8::Hl), 0);
+    assert_eq!(proc.reg_d, 0x42);
+    proc.execute_op(&mut mem, Op::Ld(R8::Hl, R8::L), 0);
+    assert_eq!(mem.read(0xC010), 0x10);
+    //H is read before the store overwrites it
+    proc.execute_op(&mut mem, Op::Ld(R8::H, R8::Hl), 0);
+    assert_eq!(proc.reg_h, 0x10);
 }
 
 #[test]
@@ -87,7 +97,7 @@ fn test_ld_sp_r16()
 fn test_push_r16()
 {
     let mut proc = Cpu::new();
-    let mut mem = Ram::new();
+    let mut mem = FlatMemory::new();
     proc.sp = 0xFFFF;
     (proc.reg_a, proc.reg_b) = (0x42, 0x69); //MSH, LSH
     Cpu::push_r16(&mut mem, &mut proc.sp, &mut proc.reg_a, &mut proc.reg_b);
@@ -100,7 +110,7 @@ fn test_push_r16()
 fn test_push_pc()
 {
     let mut proc = Cpu::new();
-    let mut mem = Ram::new();
+    let mut mem = FlatMemory::new();
     proc.sp = 0xFFFF;
     proc.pc.reg = 0x4269;
     Cpu::push_pc(&mut mem, &mut proc.sp, &mut proc.pc);
@@ -113,11 +123,25 @@ fn test_push_pc()
 fn test_pop_r16()
 {
     let mut proc = Cpu::new();
-    let mut mem = Ram::new();
+    let mut mem = FlatMemory::new();
     mem.write(0xFFFD, 0x69); //LSH
     mem.write(0xFFFE, 0x42); //MSH
     proc.sp = 0xFFFD;
     Cpu::pop_r16(&mut mem, &mut proc.sp, &mut proc.reg_a, &mut proc.reg_b);
     assert_eq!(proc.sp, 0xFFFF);
     assert_eq!((proc.reg_a, proc.reg_b), (0x42, 0x69));
-}
\ No newline at end of file
+}
+
+#[test]
+fn test_pop_af()
+{
+    let mut proc = Cpu::new();
+    let mut mem = FlatMemory::new();
+    mem.write(0xFFFD, 0xFF); //F
+    mem.write(0xFFFE, 0x42); //A
+    proc.sp = 0xFFFD;
+    proc.execute_op(&mut mem, Op::Pop(R16::Af), 0);
+    //The low nibble of F doesn't exist
+    assert_eq!((proc.reg_a, proc.reg_f.bits()), (0x42, 0xF0));
+    assert_eq!(proc.sp, 0xFFFF);
+}
@@ -0,0 +1,87 @@
+use crate::{asm, bus::FlatMemory, cpu::*};
+
+//Programs start at 0x0100 with the stack at the top of memory and run until HALT
+fn run(source: &str) -> (Cpu, FlatMemory)
+{
+    let mut cpu = Cpu::new();
+    let mut memory = FlatMemory::new();
+    memory.load(0x0100, &asm::assemble(source, 0x0100).unwrap());
+    cpu.pc.reg = 0x0100;
+    cpu.sp = 0xFFFE;
+    for _ in 0..10_000
+    {
+        if cpu.halted
+        {
+            return (cpu, memory);
+        }
+        cpu.step(&mut memory);
+    }
+    panic!("program didn't halt");
+}
+
+#[test]
+fn test_fibonacci()
+{
+    let (cpu, memory) = run("
+    ld hl, $C000
+    ld b, 0
+    ld c, 1
+    ld d, 10
+.next:
+    ld [hl], b
+    inc hl
+    ld a, b
+    add a, c
+    ld b, c
+    ld c, a
+    dec d
+    jr nz, .next
+    halt
+");
+    assert_eq!(memory.mem[0xC000..0xC00A], [0, 1, 1, 2, 3, 5, 8, 13, 21, 34]);
+    assert_eq!((cpu.reg_h, cpu.reg_l), (0xC0, 0x0A));
+    assert!(cpu.reg_f.contains(CpuFlags::FLAG_Z));
+}
+
+#[test]
+fn test_call_and_stack()
+{
+    let (cpu, memory) = run("
+    ld bc, $1234
+    push bc
+    call swap_bc
+    pop de
+    halt
+swap_bc:
+    ld a, b
+    ld b, c
+    ld c, a
+    ret
+");
+    assert_eq!((cpu.reg_b, cpu.reg_c), (0x34, 0x12));
+    assert_eq!((cpu.reg_d, cpu.reg_e), (0x12, 0x34));
+    assert_eq!(cpu.sp, 0xFFFE);
+    //Return address left behind below the pushed BC
+    assert_eq!(memory.mem[0xFFFA..0xFFFC], [0x07, 0x01]);
+}
+
+#[test]
+fn test_bit_counting()
+{
+    //Counts the set bits of $B5 by shifting them out through carry
+    let (cpu, _) = run("
+    ld a, $B5
+    ld b, 0
+    ld c, 8
+.next:
+    rla
+    jr nc, .clear
+    inc b
+.clear:
+    dec c
+    jr nz, .next
+    halt
+");
+    assert_eq!(cpu.reg_b, 5);
+    assert_eq!(cpu.reg_a, 0x5A);
+}
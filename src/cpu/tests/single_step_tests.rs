@@ -0,0 +1,184 @@
+//Runs the community SM83 single-step test vectors, one JSON file per opcode holding an array of
+//{name, initial, final, cycles} cases. The vectors aren't checked in, point GBI_SM83_TESTS at the
+//directory holding them (default sm83/v1 in the crate root) and run cargo test -- --ignored
+use std::{fs, panic::{self, AssertUnwindSafe}, path::{Path, PathBuf}};
+use serde_json::Value;
+use crate::{bus::{Bus, FlatMemory}, cpu::*};
+
+const TESTS_DIRECTORY_VARIABLE:&str = "GBI_SM83_TESTS";
+const DEFAULT_TESTS_DIRECTORY:&str = "sm83/v1";
+//Failures listed per file, the rest are only counted
+const REPORTED_FAILURES:usize = 3;
+
+fn field(state: &Value, name: &str) -> Result<u64, String>
+{
+    state[name].as_u64().ok_or_else(|| format!("missing {}", name))
+}
+
+fn ram(state: &Value) -> Result<Vec<(u16, u8)>, String>
+{
+    let entries = state["ram"].as_array().ok_or("missing ram")?;
+    entries.iter().map(|x| match (x[0].as_u64(), x[1].as_u64())
+    {
+        (Some(address), Some(data)) => Ok((address as u16, data as u8)),
+        _ => Err(format!("bad ram entry {}", x))
+    }).collect()
+}
+
+fn registers(state: &Value) -> Result<Registers, String>
+{
+    Ok(Registers
+    {
+        a: field(state, "a")? as u8,
+        f: CpuFlags::from_bits_truncate(field(state, "f")? as u8),
+        b: field(state, "b")? as u8,
+        c: field(state, "c")? as u8,
+        d: field(state, "d")? as u8,
+        e: field(state, "e")? as u8,
+        h: field(state, "h")? as u8,
+        l: field(state, "l")? as u8,
+        sp: field(state, "sp")? as u16,
+        pc: field(state, "pc")? as u16,
+        ime: field(state, "ime")? != 0,
+        halted: false,
+        stopped: false
+    })
+}
+
+///Runs one vector and describes every mismatch
+fn run_case(case: &Value) -> Result<(), String>
+{
+    let initial = &case["initial"];
+    let expected = &case["final"];
+    let cycles = case["cycles"].as_array().ok_or("missing cycles")?.len();
+
+    let mut memory = FlatMemory::new();
+    //IE is given on its own by some versions, the RAM list wins if it has the address too
+    if let Some(ie) = initial["ie"].as_u64()
+    {
+        memory.write(0xFFFF, ie as u8);
+    }
+    for (address, data) in ram(initial)?
+    {
+        memory.write(address, data);
+    }
+    let mut cpu = Cpu::new();
+    cpu.set_registers(registers(initial)?);
+
+    let used = panic::catch_unwind(AssertUnwindSafe(|| cpu.step(&mut memory)))
+        .map_err(|x| format!("panicked: {}", x.downcast_ref::<&str>().copied()
+            .or(x.downcast_ref::<String>().map(|x| x.as_str())).unwrap_or("?")))?;
+
+    let mut errors = Vec::new();
+    let mut result = cpu.registers();
+    let want = registers(expected)?;
+    //Halting isn't part of the vectors' state
+    (result.halted, result.stopped) = (false, false);
+    if result != want
+    {
+        errors.push(format!("registers {:X?}, expected {:X?}", result, want));
+    }
+    for (address, data) in ram(expected)?
+    {
+        let actual = memory.read(address);
+        if actual != data
+        {
+            errors.push(format!("[{:04X}] = {:02X}, expected {:02X}", address, actual, data));
+        }
+    }
+    if used as usize != cycles
+    {
+        errors.push(format!("{} cycles, expected {}", used, cycles));
+    }
+    if errors.is_empty() { Ok(()) } else { Err(errors.join(", ")) }
+}
+
+///Returns (cases run, failure descriptions)
+fn run_file(path: &Path) -> Result<(usize, Vec<String>), String>
+{
+    let text = fs::read_to_string(path).map_err(|x| x.to_string())?;
+    let json:Value = serde_json::from_str(&text).map_err(|x| x.to_string())?;
+    let cases = json.as_array().ok_or("expected an array of tests")?;
+    let failures = cases.iter()
+        .filter_map(|x| run_case(x).err().map(|error| format!("{}: {}", x["name"].as_str().unwrap_or("?"), error)))
+        .collect();
+    Ok((cases.len(), failures))
+}
+
+fn tests_directory() -> PathBuf
+{
+    std::env::var_os(TESTS_DIRECTORY_VARIABLE).map(PathBuf::from)
+        .unwrap_or_else(|| Path::new(env!("CARGO_MANIFEST_DIR")).join(DEFAULT_TESTS_DIRECTORY))
+}
+
+#[test]
+#[ignore = "needs the SM83 JSON test vectors on disk, see GBI_SM83_TESTS"]
+fn test_single_step_vectors()
+{
+    let directory = tests_directory();
+    let mut paths:Vec<PathBuf> = fs::read_dir(&directory)
+        .unwrap_or_else(|x| panic!("can't read {}: {}", directory.display(), x))
+        .filter_map(|x| x.ok().map(|x| x.path()))
+        .filter(|x| x.extension().is_some_and(|x| x == "json"))
+        .collect();
+    paths.sort();
+    assert!(!paths.is_empty(), "no .json files in {}", directory.display());
+
+    let mut total = 0;
+    let mut report = Vec::new();
+    for path in &paths
+    {
+        let name = path.file_stem().unwrap().to_string_lossy();
+        match run_file(path)
+        {
+            Ok((count, failures)) =>
+            {
+                total += count;
+                if !failures.is_empty()
+                {
+                    report.push(format!("{}: {}/{} failed", name, failures.len(), count));
+                    report.extend(failures.iter().take(REPORTED_FAILURES).map(|x| format!("    {}", x)));
+                }
+            },
+            Err(x) => report.push(format!("{}: {}", name, x))
+        }
+    }
+    assert!(report.is_empty(), "{} files, {} cases\n{}", paths.len(), total, report.join("\n"));
+}
+
+#[test]
+fn test_single_step_harness()
+{
+    //ADD A, B then LD (HL), A in the vectors' format
+    let json = r#"[
+        {
+            "name": "80 0000",
+            "initial": {"pc": 49152, "sp": 0, "a": 15, "b": 1, "c": 0, "d": 0, "e": 0, "f": 0, "h": 0, "l": 0, "ime": 0, "ie": 0,
+                "ram": [[49152, 128]]},
+            "final": {"pc": 49153, "sp": 0, "a": 16, "b": 1, "c": 0, "d": 0, "e": 0, "f": 32, "h": 0, "l": 0, "ime": 0, "ie": 0,
+                "ram": [[49152, 128]]},
+            "cycles": [[49152, 128, "r-m"]]
+        },
+        {
+            "name": "77 0000",
+            "initial": {"pc": 256, "sp": 0, "a": 66, "b": 0, "c": 0, "d": 0, "e": 0, "f": 0, "h": 192, "l": 16, "ime": 0,
+                "ram": [[256, 119], [49168, 0]]},
+            "final": {"pc": 257, "sp": 0, "a": 66, "b": 0, "c": 0, "d": 0, "e": 0, "f": 0, "h": 192, "l": 16, "ime": 0,
+                "ram": [[256, 119], [49168, 66]]},
+            "cycles": [[256, 119, "r-m"], [49168, 66, "-wm"]]
+        }
+    ]"#;
+    let cases:Value = serde_json::from_str(json).unwrap();
+    for case in cases.as_array().unwrap()
+    {
+        assert_eq!(run_case(case), Ok(()));
+    }
+
+    //Each kind of mismatch is reported
+    let mut case = cases[1].clone();
+    case["final"]["a"] = 0.into();
+    case["final"]["ram"][1][1] = 0.into();
+    case["cycles"] = Value::Array(vec![Value::Null; 3]);
+    let error = run_case(&case).unwrap_err();
+    assert!(error.contains("registers") && error.contains("[C010] = 42, expected 00") && error.contains("2 cycles, expected 3"), "{}", error);
+}
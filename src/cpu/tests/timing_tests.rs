@@ -0,0 +1,116 @@
+use crate::{bus::{AccessKind, TracingBus}, cpu::*, ram::Ram};
+
+fn setup(code: &[u8]) -> (Cpu, TracingBus<Ram>)
+{
+    let mut cpu = Cpu::new();
+    let mut ram = Ram::new();
+    ram.write(ram::SC_BOOT_ROM_DISABLE, 1);
+    for (i, x) in code.iter().enumerate()
+    {
+        ram.write(0xC000 + i as u16, *x);
+    }
+    cpu.pc.reg = 0xC000;
+    cpu.sp = 0xD000;
+    (cpu, TracingBus::new(ram))
+}
+
+///(cycle, address, data if written) for each access
+fn accesses(bus: &TracingBus<Ram>) -> Vec<(u64, u16, Option<u8>)>
+{
+    bus.accesses().iter()
+        .map(|x| (x.cycle, x.address, (x.kind == AccessKind::Write).then_some(x.data)))
+        .collect()
+}
+
+#[test]
+fn test_accesses_on_own_cycles()
+{
+    //INC (HL): fetch, read, write
+    let (mut cpu, mut bus) = setup(&[0x34]);
+    cpu.reg_h = 0xC1;
+    bus.inner().write(0xC100, 0x41);
+    assert_eq!(cpu.step(&mut bus), 3);
+    assert_eq!(accesses(&bus), vec![(0, 0xC000, None), (1, 0xC100, None), (2, 0xC100, Some(0x42))]);
+
+    //SET 0,(HL) goes through the bus too
+    let (mut cpu, mut bus) = setup(&[0xCB, 0xC6]);
+    cpu.reg_h = 0xC1;
+    assert_eq!(cpu.step(&mut bus), 4);
+    assert_eq!(accesses(&bus)[2..], [(2, 0xC100, None), (3, 0xC100, Some(0x01))]);
+
+    //PUSH BC has its internal cycle before the writes
+    let (mut cpu, mut bus) = setup(&[0xC5]);
+    cpu.reg_b = 0x12;
+    cpu.reg_c = 0x34;
+    assert_eq!(cpu.step(&mut bus), 4);
+    assert_eq!(accesses(&bus)[1..], [(2, 0xCFFF, Some(0x12)), (3, 0xCFFE, Some(0x34))]);
+
+    //LD (a16),SP: fetch, two immediates, two writes
+    let (mut cpu, mut bus) = setup(&[0x08, 0x00, 0xC1]);
+    assert_eq!(cpu.step(&mut bus), 5);
+    assert_eq!(accesses(&bus).iter().map(|x| x.0).collect::<Vec<u64>>(), vec![0, 1, 2, 3, 4]);
+}
+
+#[test]
+fn test_branch_cycles()
+{
+    //(code, Z flag set, M-cycles)
+    let cases:[(&[u8], bool, u8); 12] =
+    [
+        (&[0x20, 0x05], false, 3),
+        (&[0x20, 0x05], true, 2),
+        (&[0xC2, 0x00, 0xC1], false, 4),
+        (&[0xC2, 0x00, 0xC1], true, 3),
+        (&[0xC4, 0x00, 0xC1], false, 6),
+        (&[0xC4, 0x00, 0xC1], true, 3),
+        (&[0xC0], false, 5),
+        (&[0xC0], true, 2),
+        (&[0xC9], false, 4),
+        (&[0xCD, 0x00, 0xC1], false, 6),
+        (&[0xC7], false, 4),
+        (&[0xE9], false, 1)
+    ];
+    for (code, zero, cycles) in cases
+    {
+        let (mut cpu, mut bus) = setup(code);
+        cpu.reg_f.set(CpuFlags::FLAG_Z, zero);
+        assert_eq!(cpu.step(&mut bus), cycles, "{:02X?}", code);
+    }
+}
+
+#[test]
+fn test_dispatch_cycles()
+{
+    let (mut cpu, mut bus) = setup(&[0x00]);
+    cpu.ime = true;
+    bus.inner().write(ram::IE, 0x01);
+    bus.inner().write(ram::IF, 0x01);
+    assert_eq!(cpu.step(&mut bus), INTERRUPT_DISPATCH_CYCLES);
+    //Checking and acknowledging IF happens inside the CPU, only the PC push reaches the bus
+    assert_eq!(accesses(&bus), vec![(2, 0xCFFF, Some(0xC0)), (3, 0xCFFE, Some(0x00))]);
+    assert_eq!(bus.inner().read(ram::IF) & 0x1F, 0x00);
+}
+
+#[test]
+fn test_trace_stays_off_the_bus()
+{
+    let (mut cpu, mut bus) = setup(&[0x00]);
+    cpu.set_trace(Some(Box::new(std::io::sink())));
+    assert_eq!(cpu.step(&mut bus), 1);
+    assert_eq!(accesses(&bus), vec![(0, 0xC000, None)]);
+}
+
+#[test]
+fn test_execute_idles_for_instruction_length()
+{
+    //Instruction timing runs the whole CALL up front, then counts down
+    let (mut cpu, mut bus) = setup(&[0xCD, 0x00, 0xC1]);
+    cpu.execute(bus.inner());
+    assert_eq!(cpu.pc.reg, 0xC100);
+    for _ in 0..5
+    {
+        assert!(cpu.pc.current_instruction_cycles > 1);
+        cpu.execute(bus.inner());
+    }
+    assert_eq!(cpu.pc.current_instruction_cycles, 1);
+}
@@ -0,0 +1,333 @@
+#[cfg(test)]
+mod tests;
+
+use crate::cpu::{Alu, CbOp, Cond, CpuFlags, Indirect, Op, R16, R8, CB_INSTRUCTION_TIME_TABLE, CB_OPS, CB_PREFIX, OPS, ZERO_INSTRUCTION_TIME_TABLE};
+
+//Extra M-cycles a conditional branch takes when the condition holds
+const JR_TAKEN_CYCLES:u8 = 1;
+const JP_TAKEN_CYCLES:u8 = 1;
+const CALL_TAKEN_CYCLES:u8 = 3;
+const RET_TAKEN_CYCLES:u8 = 3;
+
+///Assembly dialect used when formatting
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub enum Syntax
+{
+    ///ld a, [hl+] / ldh [$FF44], a / jp $0150
+    #[default]
+    Rgbds,
+    ///ldi a,(hl) / ld (FF00+44),a / jp 0150
+    NoCashGmb
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Register8 {B, C, D, E, H, L, A}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Register16 {BC, DE, HL, SP, AF}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Condition {NZ, Z, NC, C}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Operand
+{
+    Register8(Register8),
+    Register16(Register16),
+    Condition(Condition),
+    ///Memory pointed to by a register pair, (hl) included
+    Indirect(Register16),
+    ///(hl) then increment HL
+    IndirectIncrement,
+    ///(hl) then decrement HL
+    IndirectDecrement,
+    Immediate8(u8),
+    Immediate16(u16),
+    ///Memory at an immediate address
+    Address(u16),
+    ///Memory at 0xFF00 plus an immediate byte
+    HighAddress(u8),
+    ///Memory at 0xFF00 plus C
+    HighC,
+    ///Signed immediate added to SP
+    SignedImmediate(i8),
+    ///SP plus a signed immediate, only in LD HL,SP+e
+    StackOffset(i8),
+    ///Resolved destination of JR, JP, CALL and RST
+    Target(u16),
+    ///Bit number for BIT, RES and SET
+    Bit(u8)
+}
+
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Instruction
+{
+    pub address: u16,
+    ///Lowercase RGBDS mnemonic, db for opcodes that lock up the CPU
+    pub mnemonic: &'static str,
+    pub operands: Vec<Operand>,
+    pub length: u8,
+    ///M-cycles, the not-taken case for conditional branches
+    pub cycles: u8,
+    ///M-cycles when a conditional branch is taken
+    pub taken_cycles: Option<u8>
+}
+
+const ALU:[&str; 8] = ["add", "adc", "sub", "sbc", "and", "xor", "or", "cp"];
+const SHIFTS:[&str; 8] = ["rlc", "rrc", "rl", "rr", "sla", "sra", "swap", "srl"];
+
+impl Instruction
+{
+    ///Where a jump, call or RST goes, None for JP HL and everything else
+    pub fn target(&self) -> Option<u16>
+    {
+        self.operands.iter().find_map(|x| match x
+        {
+            Operand::Target(address) => Some(*address),
+            _ => None
+        })
+    }
+
+    pub fn format(&self, syntax: Syntax) -> String
+    {
+        let mnemonic = match syntax
+        {
+            Syntax::Rgbds => self.mnemonic,
+            Syntax::NoCashGmb => match (self.mnemonic, self.operands.as_slice())
+            {
+                ("ld", [Operand::IndirectIncrement, _] | [_, Operand::IndirectIncrement]) => "ldi",
+                ("ld", [Operand::IndirectDecrement, _] | [_, Operand::IndirectDecrement]) => "ldd",
+                ("ldh", _) => "ld",
+                (x, _) => x
+            }
+        };
+        let operands = self.operands.iter().map(|x| format_operand(x, syntax)).collect::<Vec<String>>();
+        match (syntax, operands.is_empty())
+        {
+            (_, true) => mnemonic.to_string(),
+            (Syntax::Rgbds, false) => format!("{} {}", mnemonic, operands.join(", ")),
+            (Syntax::NoCashGmb, false) => format!("{:<4} {}", mnemonic, operands.join(","))
+        }
+    }
+}
+
+impl std::fmt::Display for Instruction
+{
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result
+    {
+        write!(f, "{}", self.format(Syntax::Rgbds))
+    }
+}
+
+fn format_operand(operand: &Operand, syntax: Syntax) -> String
+{
+    let rgbds = syntax == Syntax::Rgbds;
+    let indirect = |x: String| if rgbds { format!("[{}]", x) } else { format!("({})", x) };
+    let signed = |x: i8| match (rgbds, x < 0)
+    {
+        (true, _) => format!("{:+}", x),
+        (false, true) => format!("-{:02X}", x.unsigned_abs()),
+        (false, false) => format!("+{:02X}", x)
+    };
+    match *operand
+    {
+        Operand::Register8(x) => format!("{:?}", x).to_lowercase(),
+        Operand::Register16(x) => format!("{:?}", x).to_lowercase(),
+        Operand::Condition(x) => format!("{:?}", x).to_lowercase(),
+        Operand::Indirect(x) => indirect(format!("{:?}", x).to_lowercase()),
+        Operand::IndirectIncrement => if rgbds { "[hl+]".to_string() } else { "(hl)".to_string() },
+        Operand::IndirectDecrement => if rgbds { "[hl-]".to_string() } else { "(hl)".to_string() },
+        Operand::Immediate8(x) => if rgbds { format!("${:02X}", x) } else { format!("{:02X}", x) },
+        Operand::Immediate16(x) | Operand::Target(x) => if rgbds { format!("${:04X}", x) } else { format!("{:04X}", x) },
+        Operand::Address(x) => indirect(if rgbds { format!("${:04X}", x) } else { format!("{:04X}", x) }),
+        Operand::HighAddress(x) => if rgbds { format!("[$FF{:02X}]", x) } else { format!("(FF00+{:02X})", x) },
+        Operand::HighC => if rgbds { "[c]".to_string() } else { "(FF00+c)".to_string() },
+        Operand::SignedImmediate(x) => signed(x).trim_start_matches('+').to_string(),
+        Operand::StackOffset(x) => format!("sp{}", signed(x)),
+        Operand::Bit(x) => x.to_string()
+    }
+}
+
+///Decodes the instruction at the start of bytes, None if it runs past the end
+pub fn decode(bytes: &[u8], address: u16) -> Option<Instruction>
+{
+    let opcode = *bytes.first()?;
+    let (mnemonic, operands, length) = if opcode == CB_PREFIX
+    {
+        let (mnemonic, operands) = decode_cb(*bytes.get(1)?);
+        (mnemonic, operands, 2)
+    }
+    else
+    {
+        decode_base(opcode, bytes, address)?
+    };
+    let cycles = if opcode == CB_PREFIX
+    {
+        CB_INSTRUCTION_TIME_TABLE[bytes[1] as usize]
+    }
+    else
+    {
+        ZERO_INSTRUCTION_TIME_TABLE[opcode as usize]
+    };
+    let taken = match (mnemonic, operands.first())
+    {
+        ("jr", Some(Operand::Condition(_))) => Some(cycles + JR_TAKEN_CYCLES),
+        ("jp", Some(Operand::Condition(_))) => Some(cycles + JP_TAKEN_CYCLES),
+        ("call", Some(Operand::Condition(_))) => Some(cycles + CALL_TAKEN_CYCLES),
+        ("ret", Some(Operand::Condition(_))) => Some(cycles + RET_TAKEN_CYCLES),
+        _ => None
+    };
+    Some(Instruction { address, mnemonic, operands, length, cycles, taken_cycles: taken })
+}
+
+///Linear sweep from the start of bytes, stops at the first instruction that doesn't fit
+pub fn disassemble(bytes: &[u8], start: u16) -> Vec<Instruction>
+{
+    let mut instructions = Vec::new();
+    let mut offset = 0;
+    while let Some(x) = decode(&bytes[offset..], start.wrapping_add(offset as u16))
+    {
+        offset += x.length as usize;
+        instructions.push(x);
+    }
+    instructions
+}
+
+//Operands come from the same decoded Ops the CPU executes
+fn decode_base(opcode: u8, bytes: &[u8], address: u16) -> Option<(&'static str, Vec<Operand>, u8)>
+{
+    use Operand::{Address, HighAddress, HighC, Immediate8, Immediate16, SignedImmediate, StackOffset, Target};
+    let a = Operand::Register8(Register8::A);
+    let hl = Operand::Register16(Register16::HL);
+    let sp = Operand::Register16(Register16::SP);
+    //Immediates are only read for the instructions that have them
+    let n = || bytes.get(1).copied();
+    let nn = || Some(u16::from_le_bytes([*bytes.get(1)?, *bytes.get(2)?]));
+    let relative = |e: u8| address.wrapping_add(2).wrapping_add_signed(e as i8 as i16);
+
+    let decoded = match OPS[opcode as usize]
+    {
+        Op::Nop => ("nop", vec![], 1),
+        Op::LdR16Imm(r) => ("ld", vec![pair(r), Immediate16(nn()?)], 3),
+        Op::StoreA(r) => ("ld", vec![indirect(r), a], 1),
+        Op::LoadA(r) => ("ld", vec![a, indirect(r)], 1),
+        Op::IncR16(r) => ("inc", vec![pair(r)], 1),
+        Op::DecR16(r) => ("dec", vec![pair(r)], 1),
+        Op::IncR8(r) => ("inc", vec![register(r)], 1),
+        Op::DecR8(r) => ("dec", vec![register(r)], 1),
+        Op::LdR8Imm(r) => ("ld", vec![register(r), Immediate8(n()?)], 2),
+        Op::Rlca => ("rlca", vec![], 1),
+        Op::Rrca => ("rrca", vec![], 1),
+        Op::Rla => ("rla", vec![], 1),
+        Op::Rra => ("rra", vec![], 1),
+        Op::Daa => ("daa", vec![], 1),
+        Op::Cpl => ("cpl", vec![], 1),
+        Op::Scf => ("scf", vec![], 1),
+        Op::Ccf => ("ccf", vec![], 1),
+        Op::StoreSp => ("ld", vec![Address(nn()?), sp], 3),
+        Op::AddHl(r) => ("add", vec![hl, pair(r)], 1),
+        //Second byte is skipped over, assemblers emit 0x00
+        Op::Stop => n().map(|_| ("stop", vec![], 2))?,
+        Op::Jr(cond) => ("jr", branch(cond, Some(Target(relative(n()?)))), 2),
+        Op::Halt => ("halt", vec![], 1),
+        Op::Ld(dst, src) => ("ld", vec![register(dst), register(src)], 1),
+        Op::Alu(operation, r) => alu(operation, register(r), 1),
+        Op::AluImm(operation) => alu(operation, Immediate8(n()?), 2),
+        Op::Ret(cond) => ("ret", branch(cond, None), 1),
+        Op::Reti => ("reti", vec![], 1),
+        Op::Pop(r) => ("pop", vec![pair(r)], 1),
+        Op::Push(r) => ("push", vec![pair(r)], 1),
+        Op::Jp(cond) => ("jp", branch(cond, Some(Target(nn()?))), 3),
+        Op::JpHl => ("jp", vec![hl], 1),
+        Op::Call(cond) => ("call", branch(cond, Some(Target(nn()?))), 3),
+        Op::Rst(vector) => ("rst", vec![Target(vector as u16)], 1),
+        Op::LdhStore => ("ldh", vec![HighAddress(n()?), a], 2),
+        Op::LdhLoad => ("ldh", vec![a, HighAddress(n()?)], 2),
+        Op::LdhStoreC => ("ldh", vec![HighC, a], 1),
+        Op::LdhLoadC => ("ldh", vec![a, HighC], 1),
+        Op::StoreAbsolute => ("ld", vec![Address(nn()?), a], 3),
+        Op::LoadAbsolute => ("ld", vec![a, Address(nn()?)], 3),
+        Op::AddSp => ("add", vec![sp, SignedImmediate(n()? as i8)], 2),
+        Op::LdHlSp => ("ld", vec![hl, StackOffset(n()? as i8)], 2),
+        Op::LdSpHl => ("ld", vec![sp, hl], 1),
+        Op::Di => ("di", vec![], 1),
+        Op::Ei => ("ei", vec![], 1),
+        Op::Prefix | Op::Illegal => ("db", vec![Immediate8(opcode)], 1)
+    };
+    Some(decoded)
+}
+
+fn register(r: R8) -> Operand
+{
+    match r
+    {
+        R8::B => Operand::Register8(Register8::B),
+        R8::C => Operand::Register8(Register8::C),
+        R8::D => Operand::Register8(Register8::D),
+        R8::E => Operand::Register8(Register8::E),
+        R8::H => Operand::Register8(Register8::H),
+        R8::L => Operand::Register8(Register8::L),
+        R8::Hl => Operand::Indirect(Register16::HL),
+        R8::A => Operand::Register8(Register8::A)
+    }
+}
+
+fn pair(r: R16) -> Operand
+{
+    Operand::Register16(match r
+    {
+        R16::Bc => Register16::BC,
+        R16::De => Register16::DE,
+        R16::Hl => Register16::HL,
+        R16::Sp => Register16::SP,
+        R16::Af => Register16::AF
+    })
+}
+
+fn indirect(r: Indirect) -> Operand
+{
+    match r
+    {
+        Indirect::Bc => Operand::Indirect(Register16::BC),
+        Indirect::De => Operand::Indirect(Register16::DE),
+        Indirect::HlIncrement => Operand::IndirectIncrement,
+        Indirect::HlDecrement => Operand::IndirectDecrement
+    }
+}
+
+///The condition, if any, followed by the target
+fn branch(cond: Cond, target: Option<Operand>) -> Vec<Operand>
+{
+    let condition = match cond
+    {
+        Cond::Always => None,
+        Cond::NotFlag(CpuFlags::FLAG_Z) => Some(Condition::NZ),
+        Cond::Flag(CpuFlags::FLAG_Z) => Some(Condition::Z),
+        Cond::NotFlag(_) => Some(Condition::NC),
+        Cond::Flag(_) => Some(Condition::C)
+    };
+    condition.map(Operand::Condition).into_iter().chain(target).collect()
+}
+
+fn alu(operation: Alu, operand: Operand, length: u8) -> (&'static str, Vec<Operand>, u8)
+{
+    let mnemonic = ALU[operation as usize];
+    //RGBDS convention spells out A for the carry/add forms only
+    let operands = match operation
+    {
+        Alu::Add | Alu::Adc | Alu::Sbc => vec![Operand::Register8(Register8::A), operand],
+        _ => vec![operand]
+    };
+    (mnemonic, operands, length)
+}
+
+fn decode_cb(opcode: u8) -> (&'static str, Vec<Operand>)
+{
+    match CB_OPS[opcode as usize]
+    {
+        CbOp::Shift(shift, r) => (SHIFTS[shift as usize], vec![register(r)]),
+        CbOp::Bit(bit, r) => ("bit", vec![Operand::Bit(bit), register(r)]),
+        CbOp::Res(bit, r) => ("res", vec![Operand::Bit(bit), register(r)]),
+        CbOp::Set(bit, r) => ("set", vec![Operand::Bit(bit), register(r)])
+    }
+}
@@ -0,0 +1,108 @@
+use super::*;
+
+fn rgbds(bytes: &[u8], address: u16) -> String
+{
+    decode(bytes, address).unwrap().format(Syntax::Rgbds)
+}
+
+fn no_cash(bytes: &[u8], address: u16) -> String
+{
+    decode(bytes, address).unwrap().format(Syntax::NoCashGmb)
+}
+
+#[test]
+fn test_rgbds_syntax()
+{
+    let cases:[(&[u8], &str); 16] =
+    [
+        (&[0x00], "nop"),
+        (&[0x01, 0x34, 0x12], "ld bc, $1234"),
+        (&[0x08, 0x00, 0xC0], "ld [$C000], sp"),
+        (&[0x22], "ld [hl+], a"),
+        (&[0x3A], "ld a, [hl-]"),
+        (&[0x36, 0x7F], "ld [hl], $7F"),
+        (&[0x86], "add a, [hl]"),
+        (&[0xAF], "xor a"),
+        (&[0xE0, 0x44], "ldh [$FF44], a"),
+        (&[0xF2], "ldh a, [c]"),
+        (&[0xE8, 0xFB], "add sp, -5"),
+        (&[0xF8, 0x05], "ld hl, sp+5"),
+        (&[0xFA, 0x00, 0xD0], "ld a, [$D000]"),
+        (&[0xCB, 0x7E], "bit 7, [hl]"),
+        (&[0xCB, 0x37], "swap a"),
+        (&[0xD3], "db $D3")
+    ];
+    for (bytes, text) in cases
+    {
+        assert_eq!(rgbds(bytes, 0x0100), text);
+    }
+}
+
+#[test]
+fn test_no_cash_syntax()
+{
+    let cases:[(&[u8], &str); 8] =
+    [
+        (&[0x22], "ldi  (hl),a"),
+        (&[0x3A], "ldd  a,(hl)"),
+        (&[0xE0, 0x44], "ld   (FF00+44),a"),
+        (&[0xE2], "ld   (FF00+c),a"),
+        (&[0xE8, 0xFB], "add  sp,-05"),
+        (&[0xF8, 0x05], "ld   hl,sp+05"),
+        (&[0xC3, 0x50, 0x01], "jp   0150"),
+        (&[0x76], "halt")
+    ];
+    for (bytes, text) in cases
+    {
+        assert_eq!(no_cash(bytes, 0x0100), text);
+    }
+}
+
+#[test]
+fn test_targets()
+{
+    //JR is relative to the following instruction
+    assert_eq!(decode(&[0x18, 0xFE], 0x0150).unwrap().target(), Some(0x0150));
+    assert_eq!(rgbds(&[0x20, 0x05], 0x0150), "jr nz, $0157");
+    assert_eq!(rgbds(&[0xCC, 0x00, 0x40], 0x0000), "call z, $4000");
+    assert_eq!(rgbds(&[0xFF], 0x0000), "rst $0038");
+    assert_eq!(decode(&[0xE9], 0x0000).unwrap().target(), None);
+}
+
+#[test]
+fn test_lengths_and_cycles()
+{
+    for opcode in 0..=0xFF_u8
+    {
+        let bytes = [opcode, 0x00, 0x00];
+        let x = decode(&bytes, 0).unwrap();
+        if opcode == CB_PREFIX
+        {
+            continue;
+        }
+        assert_eq!(x.cycles, ZERO_INSTRUCTION_TIME_TABLE[opcode as usize], "{:02X}", opcode);
+        //Every immediate is read, so the decode fails when it's cut short
+        assert!(decode(&bytes[..x.length as usize - 1], 0).is_none(), "{:02X}", opcode);
+    }
+    for opcode in 0..=0xFF_u8
+    {
+        let x = decode(&[CB_PREFIX, opcode], 0).unwrap();
+        assert_eq!((x.length, x.cycles), (2, CB_INSTRUCTION_TIME_TABLE[opcode as usize]));
+    }
+
+    let call = decode(&[0xC4, 0x00, 0x40], 0).unwrap();
+    assert_eq!((call.length, call.cycles, call.taken_cycles), (3, 3, Some(6)));
+    let ret = decode(&[0xC9], 0).unwrap();
+    assert_eq!((ret.cycles, ret.taken_cycles), (4, None));
+    assert_eq!(decode(&[0xC0], 0).unwrap().taken_cycles, Some(5));
+    assert_eq!(decode(&[0x10, 0x00], 0).unwrap().length, 2);
+}
+
+#[test]
+fn test_disassemble()
+{
+    //LD A,0x01 / JR -4 / truncated LD HL,nn
+    let listing = disassemble(&[0x3E, 0x01, 0x18, 0xFC, 0x21, 0x00], 0x0200);
+    let text = listing.iter().map(|x| format!("{:04X} {}", x.address, x)).collect::<Vec<String>>();
+    assert_eq!(text, vec!["0200 ld a, $01", "0202 jr $0200"]);
+}
@@ -1,13 +1,24 @@
 use std::{cell::RefCell, rc::Rc};
 
+pub mod asm;
+pub mod boot;
+pub mod bus;
+pub mod callstack;
+pub mod cheat;
 pub mod cpu;
+pub mod disasm;
 pub mod ram;
 pub mod mainboard;
+pub mod patch;
 pub mod ppu;
+pub mod rtc;
 
+mod mbc;
 mod rom;
 mod timer;
 
+pub use rom::{CartridgeHeader, HeaderPolicy, HeaderValidation, MBCModel, RomError};
+
 #[cfg(test)]
 mod tests;
 
@@ -15,6 +26,21 @@ type HardwareHandle = Rc<RefCell<dyn crate::Frontend>>;
 pub trait Frontend
 {
     fn receive_rom_information(&mut self, title: &str);
+    ///Called with the decoded header when a ROM is loaded
+    fn receive_cartridge_header(&mut self, _header: &CartridgeHeader) {}
+    ///Called when a loaded ROM fails header checks and the policy let it through,
+    ///locked_up is true when HeaderPolicy::LockUp has hung the CPU over it
+    fn header_check_failed(&mut self, _validation: &HeaderValidation, _locked_up: bool) {}
     fn event_poll(&mut self) -> bool;
     fn video_update(&mut self, buffer: &[[u8; ppu::SCREEN_HEIGHT];ppu::SCREEN_WIDTH], frame_count: u64);
+    ///Called when a rumble cartridge turns its motor on or off
+    fn rumble_update(&mut self, _active: bool) {}
+    ///Called when battery backed cartridge RAM has settled after being changed, with the .sav written if there is one
+    fn save_ram_updated(&mut self) {}
+    ///Called before save_ram_updated when writing the .sav failed, the data is still in export_save_ram
+    fn save_failed(&mut self, _error: &std::io::Error) {}
+    ///Called when the CPU hits an illegal opcode and hangs, only loading a game again recovers it
+    fn cpu_locked_up(&mut self, _address: u16, _opcode: u8) {}
+    ///Called when writing to the instruction trace fails, tracing is turned off after it
+    fn trace_failed(&mut self, _error: &std::io::Error) {}
 }
\ No newline at end of file
@@ -1,7 +1,9 @@
-use std::{cell::{RefCell}, rc::Rc};
-use crate::{cpu::Cpu, ram::Ram, rom::Rom, timer::Timer, ppu::{self, Ppu}};
+use std::{cell::{RefCell}, path::PathBuf, rc::Rc};
+use crate::{boot::{self, BootMode}, bus::Bus, callstack::CallStack, cheat::{self, Cheat, CheatCode, CheatError}, cpu::{Cpu, CpuTiming, Registers}, patch, ram::{self, Button, Ram}, rom::{self, CartridgeHeader, HeaderPolicy, HeaderValidation, Rom, RomError}, rtc::TimeSourceHandle, timer::Timer, ppu::{self, Ppu}};
 
 pub const CLOCK_EDGE:f64 = 8_338_608_f64;
+//Frames without a cartridge RAM write before the .sav is written, games often write saves a few bytes a frame
+const SAVE_QUIET_FRAMES:u32 = 60;
 
 pub struct Mainboard
 {
@@ -9,10 +11,21 @@ pub struct Mainboard
     ram: Ram,
     ppu: Ppu,
     timer: Timer,
-    clock_enable: bool,
+    cpu_timing: CpuTiming,
+    cpu_cycles_ahead: u8,
     cycles: u64,
     t_cycles: u64,
     m_cycles: u64,
+    cartridge_header: Option<CartridgeHeader>,
+    cheats: Vec<Cheat>,
+    boot_mode: BootMode,
+    save_path: Option<PathBuf>,
+    ///Frames since cartridge RAM last changed, while a save is waiting to be written
+    save_quiet_frames: Option<u32>,
+    save_delay: u32,
+    header_policy: HeaderPolicy,
+    header_validation: Option<HeaderValidation>,
+    header_locked: bool,
     hardware_handle: crate::HardwareHandle
 }
 
@@ -26,65 +39,467 @@ impl Mainboard
             ram: Ram::new(),
             ppu: Ppu::new(),
             timer: Timer::new(),
-            clock_enable: true,
+            cpu_timing: CpuTiming::default(),
+            cpu_cycles_ahead: 0,
             cycles: 0,
             t_cycles: 0,
             m_cycles: 0,
+            cartridge_header: None,
+            cheats: Vec::new(),
+            boot_mode: BootMode::default(),
+            save_path: None,
+            save_quiet_frames: None,
+            save_delay: SAVE_QUIET_FRAMES,
+            header_policy: HeaderPolicy::default(),
+            header_validation: None,
+            header_locked: false,
             hardware_handle: Rc::new(RefCell::new(hardware_handle))
         }
     }
 
-    pub fn load_game(&mut self, path: &std::path::Path) -> Result<(), std::io::Error>
+    pub fn load_game(&mut self, path: &std::path::Path) -> Result<(), RomError>
     {
-        let file_result = std::fs::File::open(path);
+        let file_result = std::fs::read(path);
         match file_result
         {
-            Ok(f) =>
+            Ok(bytes) =>
             {
-                self.ram.load_rom(&Rom::new(f, Rc::clone(&self.hardware_handle)));
+                //Soft-patching, a same-named patch next to the ROM is applied without touching the file
+                let patch_path = patch::PATCH_EXTENSIONS.iter()
+                    .map(|x| path.with_extension(x))
+                    .find(|x| x.is_file());
+                match patch_path
+                {
+                    Some(x) => self.load_game_with_patch(bytes, &std::fs::read(x)?)?,
+                    None => self.load_game_from_bytes(bytes)?
+                }
+                if self.cartridge_header.as_ref().is_some_and(|x| x.has_battery)
+                {
+                    //Saves live next to the ROM with a .sav extension
+                    let save_path = path.with_extension("sav");
+                    match std::fs::read(&save_path)
+                    {
+                        Ok(data) => {self.ram.import_save_data(&data);},
+                        Err(x) if x.kind() == std::io::ErrorKind::NotFound => {},
+                        Err(x) => return Err(x.into())
+                    }
+                    self.save_path = Some(save_path);
+                }
                 Ok(())
             },
-            Err(x) => Err(x)
+            Err(x) => Err(x.into())
         }
     }
 
-    pub fn execute_frame(&mut self) -> bool
+    ///Loads a ROM image already in memory. There's no save file, use export_save_ram and import_save_ram instead.
+    pub fn load_game_from_bytes(&mut self, bytes: impl Into<Vec<u8>>) -> Result<(), RomError>
     {
-        for _ in 0..ppu::CYCLES_PER_FRAME
+        let rom = Rom::new(bytes.into())?;
+        self.insert_rom(rom)
+    }
+
+    ///Loads a ROM image from any reader. There's no save file, use export_save_ram and import_save_ram instead.
+    pub fn load_game_from_reader<R: std::io::Read>(&mut self, reader: R) -> Result<(), RomError>
+    {
+        let rom = Rom::from_reader(reader)?;
+        self.insert_rom(rom)
+    }
+
+    ///Applies an IPS, UPS or BPS patch to the image before loading it
+    pub fn load_game_with_patch(&mut self, bytes: impl Into<Vec<u8>>, patch: &[u8]) -> Result<(), RomError>
+    {
+        let bytes = patch::apply(&bytes.into(), patch)?;
+        self.load_game_from_bytes(bytes)
+    }
+
+    fn insert_rom(&mut self, rom: Rom) -> Result<(), RomError>
+    {
+        //The outgoing game's save goes to its own file before the cartridge is swapped
+        self.write_pending_save();
+        if !rom.validation.is_valid() && self.header_policy == HeaderPolicy::Reject
+        {
+            return Err(RomError::InvalidHeader(rom.validation));
+        }
+        //The frontend only hears about ROMs that get loaded
+        self.hardware_handle.borrow_mut().receive_rom_information(&rom.header.title);
+        self.hardware_handle.borrow_mut().receive_cartridge_header(&rom.header);
+        //Skipping the boot ROM skips its checks too
+        self.header_locked = self.header_policy == HeaderPolicy::LockUp && !rom.validation.is_bootable()
+            && !matches!(self.boot_mode, BootMode::Skip(_));
+        if !rom.validation.is_valid()
         {
-            if self.clock_enable
+            self.hardware_handle.borrow_mut().header_check_failed(&rom.validation, self.header_locked);
+        }
+        self.header_validation = Some(rom.validation.clone());
+        self.ram.load_rom(&rom, Rc::clone(&self.hardware_handle));
+        let trace = self.cpu.set_trace(None);
+        self.cpu = Cpu::new();
+        self.cpu.set_trace(trace);
+        match &self.boot_mode
+        {
+            BootMode::Builtin => self.ram.set_boot_rom(rom::BOOT_ROM.to_vec()),
+            BootMode::Rom(x) => self.ram.set_boot_rom(x.bytes.clone()),
+            BootMode::Skip(model) =>
             {
-                if self.cycles % 2 == 0 //T-cycle-pos (4,194,304 hz)
-                {
-                    self.t_cycles += 1;
-                }
-                else //T-cycle-neg (4,194,304 hz)
-                {
+                boot::skip_boot(*model, &rom.header, &mut self.cpu, &mut self.ram);
+                self.timer.set_divider(self.ram.read(ram::DIV));
+            }
+        }
+        self.cartridge_header = Some(rom.header);
+        self.save_path = None;
+        Ok(())
+    }
 
-                }
+    ///Header of the loaded ROM
+    pub fn cartridge_header(&self) -> Option<&CartridgeHeader>
+    {
+        self.cartridge_header.as_ref()
+    }
 
-                if self.cycles % 8 == 0 //M-cycle-pos (1,048,576 hz)
-                {
-                    self.cpu.execute(&mut self.ram);
-                    if !self.cpu.halted
-                    {
-                        self.ram.execute();
-                    }
-                    self.ppu.execute(&mut self.ram, Rc::clone(&self.hardware_handle));
+    ///Boot ROM to run, or the model whose post-boot state to start in. Applies to the next load.
+    pub fn set_boot_mode(&mut self, boot_mode: BootMode)
+    {
+        self.boot_mode = boot_mode;
+    }
 
-                    self.m_cycles += 1;
-                }
-                else if !self.cpu.halted //M-cycle-neg (1,048,576 hz)
-                {
-                    self.timer.execute(&mut self.ram, self.m_cycles);
-                }
+    ///Whether instructions run all at once or interleave their memory accesses with the PPU, timer and DMA
+    pub fn set_cpu_timing(&mut self, cpu_timing: CpuTiming)
+    {
+        self.cpu_timing = cpu_timing;
+    }
+
+    ///Logs the CPU state before every instruction in gameboy-doctor's format, for diffing against other emulators.
+    ///Pass None to stop, the previous sink is handed back either way. Tracing carries over when a game is loaded.
+    pub fn set_trace(&mut self, sink: Option<Box<dyn std::io::Write>>) -> Option<Box<dyn std::io::Write>>
+    {
+        self.cpu.set_trace(sink)
+    }
+
+    ///How ROMs failing the logo or checksum checks are treated, applies to the next load
+    pub fn set_header_policy(&mut self, policy: HeaderPolicy)
+    {
+        self.header_policy = policy;
+    }
+
+    ///Frames cartridge RAM has to go unchanged before the .sav is written, 60 by default
+    pub fn set_save_delay(&mut self, frames: u32)
+    {
+        self.save_delay = frames;
+    }
 
-                if self.cpu.stopped
+    ///Header check results for the loaded ROM
+    pub fn header_validation(&self) -> Option<&HeaderValidation>
+    {
+        self.header_validation.as_ref()
+    }
+
+    ///True when HeaderPolicy::LockUp has hung the CPU on a bad header
+    pub fn is_header_locked(&self) -> bool
+    {
+        self.header_locked
+    }
+
+    ///True once an illegal opcode has hung the CPU, cleared by loading a game
+    pub fn is_cpu_locked(&self) -> bool
+    {
+        self.cpu.locked
+    }
+
+    ///CPU registers as of the last instruction boundary
+    pub fn registers(&self) -> Registers
+    {
+        self.cpu.registers()
+    }
+
+    ///Calls, RSTs and interrupts the CPU is inside of, for showing a backtrace
+    pub fn call_stack(&self) -> &CallStack
+    {
+        self.cpu.call_stack()
+    }
+
+    ///Overwrites the CPU registers, an instruction already under way in CpuTiming::Instruction still finishes its cycles
+    pub fn set_registers(&mut self, registers: Registers)
+    {
+        self.cpu.set_registers(registers);
+    }
+
+    ///Buttons currently held. Pressing one on a selected line requests the joypad interrupt and wakes the CPU from STOP.
+    pub fn set_buttons(&mut self, buttons: Button)
+    {
+        self.ram.set_buttons(buttons);
+    }
+
+    ///Cartridge RAM (plus the RTC footer on MBC3) in the same layout as a .sav file
+    pub fn export_save_ram(&mut self) -> Option<Vec<u8>>
+    {
+        self.ram.export_save_data()
+    }
+
+    pub fn import_save_ram(&mut self, data: &[u8])
+    {
+        self.ram.import_save_data(data);
+    }
+
+    ///Writes the .sav file for battery backed games loaded from a path. This also happens
+    ///automatically once cartridge RAM has gone unchanged for a while, on loading another ROM and on drop.
+    pub fn flush_save(&mut self) -> Result<(), std::io::Error>
+    {
+        self.save_quiet_frames = None;
+        match (&self.save_path, self.ram.export_save_data())
+        {
+            (Some(path), Some(data)) => std::fs::write(path, data),
+            _ => Ok(())
+        }
+    }
+
+    fn write_pending_save(&mut self)
+    {
+        if self.save_quiet_frames.is_none()
+        {
+            return;
+        }
+        if let Err(x) = self.flush_save()
+        {
+            self.hardware_handle.borrow_mut().save_failed(&x);
+        }
+        self.hardware_handle.borrow_mut().save_ram_updated();
+    }
+
+    ///Decodes and enables a Game Genie or GameShark code, returns its index in cheats()
+    pub fn add_cheat(&mut self, code: &str) -> Result<usize, CheatError>
+    {
+        self.cheats.push(Cheat::new(code)?);
+        self.update_rom_patches();
+        Ok(self.cheats.len() - 1)
+    }
+
+    pub fn remove_cheat(&mut self, index: usize) -> Option<Cheat>
+    {
+        let cheat = (index < self.cheats.len()).then(|| self.cheats.remove(index));
+        self.update_rom_patches();
+        cheat
+    }
+
+    pub fn set_cheat_enabled(&mut self, index: usize, enabled: bool)
+    {
+        if let Some(x) = self.cheats.get_mut(index)
+        {
+            x.enabled = enabled;
+        }
+        self.update_rom_patches();
+    }
+
+    pub fn clear_cheats(&mut self)
+    {
+        self.cheats.clear();
+        self.update_rom_patches();
+    }
+
+    pub fn cheats(&self) -> &[Cheat]
+    {
+        &self.cheats
+    }
+
+    ///Reads memory as the CPU sees it, cheats included
+    pub fn read_memory(&self, address: u16) -> u8
+    {
+        self.ram.read(address)
+    }
+
+    fn update_rom_patches(&mut self)
+    {
+        self.ram.set_rom_patches(cheat::rom_patches(&self.cheats));
+    }
+
+    //GameShark codes are rewritten every frame so the game can't undo them
+    fn apply_ram_cheats(&mut self)
+    {
+        for x in self.cheats.iter().filter(|x| x.enabled)
+        {
+            //There's a single WRAM bank, so banked codes write to it like any other
+            if let CheatCode::GameShark { address, value, .. } = x.decoded
+            {
+                self.ram.write(address, value);
+            }
+        }
+    }
+
+    ///Replaces the wall clock used by cartridge RTCs. Call before loading a game.
+    pub fn set_time_source(&mut self, time_source: TimeSourceHandle)
+    {
+        self.ram.set_time_source(time_source);
+    }
+
+    fn instruction_m_cycle(&mut self)
+    {
+        let stopped = self.cpu.stopped;
+        //A failed boot check leaves the CPU spinning in the boot ROM
+        if !self.header_locked
+        {
+            self.cpu.execute(&mut self.ram);
+            self.report_cpu_failures();
+        }
+        //DMA, the PPU and the timer share the CPU's clock, so STOP holds them too
+        if !(stopped && self.cpu.stopped)
+        {
+            self.tick_board();
+        }
+        if self.cpu.stopped && !stopped
+        {
+            self.enter_stop();
+        }
+    }
+
+    //The CPU ticks the board itself for every cycle of an instruction, so the
+    //cycles it has already run through are skipped here
+    fn interleaved_m_cycle(&mut self)
+    {
+        if self.cpu_cycles_ahead > 0
+        {
+            self.cpu_cycles_ahead -= 1;
+            return;
+        }
+        if self.header_locked
+        {
+            self.tick_board();
+            return;
+        }
+        let stopped = self.cpu.stopped;
+        let mut bus = BoardBus
+        {
+            ram: &mut self.ram,
+            ppu: &mut self.ppu,
+            timer: &mut self.timer,
+            m_cycles: &mut self.m_cycles,
+            hardware_handle: &self.hardware_handle,
+            vblank_started: false
+        };
+        self.cpu_cycles_ahead = self.cpu.step(&mut bus) - 1;
+        if bus.vblank_started
+        {
+            self.apply_ram_cheats();
+        }
+        self.report_cpu_failures();
+        if self.cpu.stopped && !stopped
+        {
+            self.enter_stop();
+        }
+    }
+
+    ///One M-cycle of DMA, the timer and the PPU, the same schedule for both CPU timings
+    fn tick_board(&mut self)
+    {
+        let mut bus = BoardBus
+        {
+            ram: &mut self.ram,
+            ppu: &mut self.ppu,
+            timer: &mut self.timer,
+            m_cycles: &mut self.m_cycles,
+            hardware_handle: &self.hardware_handle,
+            vblank_started: false
+        };
+        bus.tick();
+        if bus.vblank_started
+        {
+            self.apply_ram_cheats();
+        }
+    }
+
+    fn enter_stop(&mut self)
+    {
+        self.timer.reset_divider(&mut self.ram);
+        self.ppu.stop(&self.ram, Rc::clone(&self.hardware_handle));
+    }
+
+    fn report_cpu_failures(&mut self)
+    {
+        if let Some((address, opcode)) = self.cpu.take_lock_up()
+        {
+            self.hardware_handle.borrow_mut().cpu_locked_up(address, opcode);
+        }
+        if let Some(error) = self.cpu.take_trace_error()
+        {
+            self.hardware_handle.borrow_mut().trace_failed(&error);
+        }
+    }
+
+    pub fn execute_frame(&mut self) -> bool
+    {
+        for _ in 0..ppu::CYCLES_PER_FRAME
+        {
+            if self.cycles.is_multiple_of(2) //T-cycle-pos (4,194,304 hz)
+            {
+                self.t_cycles += 1;
+            }
+            else //T-cycle-neg (4,194,304 hz)
+            {
+
+            }
+
+            if self.cycles.is_multiple_of(8) //M-cycle-pos (1,048,576 hz)
+            {
+                match self.cpu_timing
                 {
-                    self.clock_enable = false;
+                    CpuTiming::Instruction => self.instruction_m_cycle(),
+                    CpuTiming::MCycle => self.interleaved_m_cycle()
                 }
             }
         }
+        if self.ram.take_external_ram_dirty() && self.cartridge_header.as_ref().is_some_and(|x| x.has_battery)
+        {
+            self.save_quiet_frames = Some(0);
+        }
+        else if let Some(frames) = &mut self.save_quiet_frames
+        {
+            *frames += 1;
+            if *frames >= self.save_delay
+            {
+                self.write_pending_save();
+            }
+        }
         self.hardware_handle.borrow_mut().event_poll()
     }
-}
\ No newline at end of file
+}
+
+impl Drop for Mainboard
+{
+    fn drop(&mut self)
+    {
+        self.write_pending_save();
+    }
+}
+
+///The board as the CPU sees it, every tick advances DMA, the timer and the PPU by one M-cycle.
+///CpuTiming::MCycle ticks it between accesses, CpuTiming::Instruction once after each M-cycle.
+struct BoardBus<'a>
+{
+    ram: &'a mut Ram,
+    ppu: &'a mut Ppu,
+    timer: &'a mut Timer,
+    m_cycles: &'a mut u64,
+    hardware_handle: &'a crate::HardwareHandle,
+    vblank_started: bool
+}
+
+impl Bus for BoardBus<'_>
+{
+    fn read(&mut self, address: u16) -> u8
+    {
+        self.ram.read(address)
+    }
+
+    fn write(&mut self, address: u16, data: u8)
+    {
+        self.ram.write(address, data);
+    }
+
+    fn tick(&mut self)
+    {
+        self.ram.execute();
+        self.timer.execute(self.ram);
+        let scan_line = self.ram.read(ram::LY);
+        self.ppu.execute(self.ram, Rc::clone(self.hardware_handle));
+        self.vblank_started |= scan_line != ppu::SCREEN_HEIGHT as u8 && self.ram.read(ram::LY) == ppu::SCREEN_HEIGHT as u8;
+        *self.m_cycles += 1;
+    }
+}
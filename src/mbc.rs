@@ -0,0 +1,129 @@
+#[cfg(test)]
+mod tests;
+mod mbc1;
+mod mbc2;
+mod mbc3;
+mod mbc5;
+
+use crate::{rom::{MBCModel, Rom}, rtc::TimeSourceHandle};
+
+pub const ROM_BANK_SIZE:usize = 0x4000;
+pub const RAM_BANK_SIZE:usize = 0x2000;
+
+//----Cartridge Address Ranges----
+//0x0000-0x7FFF reads come from ROM, writes go to the controller registers
+//0xA000-0xBFFF is external (cartridge) RAM
+pub const EXTERNAL_RAM_START:u16 = 0xA000;
+
+///Memory bank controller on the cartridge. Addresses are passed unmodified from the CPU's memory map.
+pub trait Mbc
+{
+    fn read_rom(&self, address: u16) -> u8;
+    fn write_rom(&mut self, address: u16, data: u8);
+    fn read_ram(&self, address: u16) -> u8;
+    fn write_ram(&mut self, address: u16, data: u8);
+    ///Battery backed state, external RAM followed by anything else the controller keeps
+    fn save_data(&mut self) -> Vec<u8>;
+    fn load_save_data(&mut self, data: &[u8]);
+    ///Copy of the controller with its banks, RAM and clock, so a whole Ram can be cloned
+    fn clone_box(&self) -> Box<dyn Mbc>;
+}
+
+impl Clone for Box<dyn Mbc>
+{
+    fn clone(&self) -> Box<dyn Mbc>
+    {
+        self.clone_box()
+    }
+}
+
+pub fn new(rom: &Rom, time_source: TimeSourceHandle, hardware_handle: crate::HardwareHandle) -> Box<dyn Mbc>
+{
+    match rom.header.mbc_model
+    {
+        MBCModel::Mbc1_16_8 => Box::new(mbc1::Mbc1::new(rom)),
+        MBCModel::Mbc2 => Box::new(mbc2::Mbc2::new(rom)),
+        MBCModel::Mbc3 => Box::new(mbc3::Mbc3::new(rom, time_source)),
+        MBCModel::Mbc5 => Box::new(mbc5::Mbc5::new(rom, hardware_handle)),
+        _ => Box::new(RomOnly::new(rom))
+    }
+}
+
+//Out of range reads float high like an open bus
+fn rom_byte(bytes: &[u8], bank: usize, address: u16) -> u8
+{
+    let offset = bank * ROM_BANK_SIZE + (address as usize & (ROM_BANK_SIZE - 1));
+    *bytes.get(offset).unwrap_or(&0xFF)
+}
+
+fn ram_offset(bank: usize, address: u16) -> usize
+{
+    bank * RAM_BANK_SIZE + (address - EXTERNAL_RAM_START) as usize
+}
+
+//Shorter saves only fill the start of RAM, extra bytes are ignored
+fn load_ram(ram: &mut [u8], data: &[u8])
+{
+    let len = ram.len().min(data.len());
+    ram[..len].copy_from_slice(&data[..len]);
+}
+
+///Bank count rounded to a power of two so it can be used as a mask
+fn bank_count(size: usize, bank_size: usize) -> usize
+{
+    (size / bank_size).max(1).next_power_of_two()
+}
+
+///Cartridge without a controller, 32 KiB of ROM and optionally 8 KiB of RAM
+#[derive(Clone)]
+pub struct RomOnly
+{
+    bytes: Vec<u8>,
+    ram: Vec<u8>
+}
+
+impl RomOnly
+{
+    pub fn new(rom: &Rom) -> RomOnly
+    {
+        RomOnly { bytes: rom.bytes.clone(), ram: vec![0; rom.header.ram_size.min(RAM_BANK_SIZE)] }
+    }
+}
+
+impl Mbc for RomOnly
+{
+    fn read_rom(&self, address: u16) -> u8
+    {
+        *self.bytes.get(address as usize).unwrap_or(&0xFF)
+    }
+
+    fn write_rom(&mut self, _address: u16, _data: u8) {}
+
+    fn read_ram(&self, address: u16) -> u8
+    {
+        *self.ram.get(ram_offset(0, address)).unwrap_or(&0xFF)
+    }
+
+    fn write_ram(&mut self, address: u16, data: u8)
+    {
+        if let Some(x) = self.ram.get_mut(ram_offset(0, address))
+        {
+            *x = data;
+        }
+    }
+
+    fn save_data(&mut self) -> Vec<u8>
+    {
+        self.ram.clone()
+    }
+
+    fn load_save_data(&mut self, data: &[u8])
+    {
+        load_ram(&mut self.ram, data);
+    }
+
+    fn clone_box(&self) -> Box<dyn Mbc>
+    {
+        Box::new(self.clone())
+    }
+}
@@ -0,0 +1,136 @@
+use crate::rom::Rom;
+use super::{Mbc, RAM_BANK_SIZE, ROM_BANK_SIZE};
+
+//----MBC1 Registers (write only, selected by address)----
+//0x0000-0x1FFF: RAM enable, 0x0A in the lower nibble enables
+//0x2000-0x3FFF: lower 5 bits of the ROM bank, 0 is treated as 1
+//0x4000-0x5FFF: 2 bit RAM bank or upper ROM bank bits
+//0x6000-0x7FFF: banking mode select
+const RAM_ENABLE_VALUE:u8 = 0x0A;
+const ROM_BANK_MASK:u8 = 0b00011111;
+const UPPER_BANK_MASK:u8 = 0b00000011;
+
+#[derive(Clone)]
+pub struct Mbc1
+{
+    bytes: Vec<u8>,
+    ram: Vec<u8>,
+    rom_banks: usize,
+    ram_banks: usize,
+    ram_enabled: bool,
+    rom_bank: u8,
+    upper_bank: u8,
+    advanced_banking: bool
+}
+
+impl Mbc1
+{
+    pub fn new(rom: &Rom) -> Mbc1
+    {
+        Mbc1
+        {
+            bytes: rom.bytes.clone(),
+            ram: vec![0; rom.header.ram_size],
+            rom_banks: super::bank_count(rom.header.rom_size.max(rom.bytes.len()), ROM_BANK_SIZE),
+            ram_banks: super::bank_count(rom.header.ram_size, RAM_BANK_SIZE),
+            ram_enabled: false,
+            rom_bank: 1,
+            upper_bank: 0,
+            advanced_banking: false
+        }
+    }
+
+    //In mode 1 the upper bits also apply to the 0x0000-0x3FFF region
+    fn low_rom_bank(&self) -> usize
+    {
+        if self.advanced_banking
+        {
+            ((self.upper_bank as usize) << 5) & (self.rom_banks - 1)
+        }
+        else
+        {
+            0
+        }
+    }
+
+    fn high_rom_bank(&self) -> usize
+    {
+        (((self.upper_bank as usize) << 5) | self.rom_bank as usize) & (self.rom_banks - 1)
+    }
+
+    fn ram_bank(&self) -> usize
+    {
+        if self.advanced_banking
+        {
+            self.upper_bank as usize & (self.ram_banks - 1)
+        }
+        else
+        {
+            0
+        }
+    }
+}
+
+impl Mbc for Mbc1
+{
+    fn read_rom(&self, address: u16) -> u8
+    {
+        match address
+        {
+            0x0000..=0x3FFF => super::rom_byte(&self.bytes, self.low_rom_bank(), address),
+            _ => super::rom_byte(&self.bytes, self.high_rom_bank(), address)
+        }
+    }
+
+    fn write_rom(&mut self, address: u16, data: u8)
+    {
+        match address
+        {
+            0x0000..=0x1FFF => {self.ram_enabled = data & 0x0F == RAM_ENABLE_VALUE;},
+            0x2000..=0x3FFF =>
+            {
+                //Bank 0 can't be selected here, the zero check only looks at the 5 bit value
+                self.rom_bank = (data & ROM_BANK_MASK).max(1);
+            },
+            0x4000..=0x5FFF => {self.upper_bank = data & UPPER_BANK_MASK;},
+            _ => {self.advanced_banking = data & 1 != 0;}
+        }
+    }
+
+    fn read_ram(&self, address: u16) -> u8
+    {
+        if !self.ram_enabled
+        {
+            return 0xFF;
+        }
+        *self.ram.get(super::ram_offset(self.ram_bank(), address)).unwrap_or(&0xFF)
+    }
+
+    fn write_ram(&mut self, address: u16, data: u8)
+    {
+        if !self.ram_enabled
+        {
+            return;
+        }
+        let offset = super::ram_offset(self.ram_bank(), address);
+        if let Some(x) = self.ram.get_mut(offset)
+        {
+            *x = data;
+        }
+    }
+
+    fn save_data(&mut self) -> Vec<u8>
+    {
+        self.ram.clone()
+    }
+
+    fn load_save_data(&mut self, data: &[u8])
+    {
+        super::load_ram(&mut self.ram, data);
+    }
+
+    fn clone_box(&self) -> Box<dyn Mbc>
+    {
+        Box::new(self.clone())
+    }
+}
@@ -0,0 +1,96 @@
+use crate::rom::Rom;
+use super::{Mbc, ROM_BANK_SIZE};
+
+//----MBC2 Registers (write only, 0x0000-0x3FFF)----
+//Address bit 8 clear: RAM enable, 0x0A in the lower nibble enables
+//Address bit 8 set: 4 bit ROM bank, 0 is treated as 1
+const RAM_ENABLE_VALUE:u8 = 0x0A;
+const REGISTER_SELECT_BIT:u16 = 1 << 8;
+const ROM_BANK_MASK:u8 = 0b00001111;
+
+//Built in 512x4 bit RAM, repeated through all of 0xA000-0xBFFF
+const RAM_SIZE:usize = 512;
+const RAM_UNUSED_BITS:u8 = 0xF0;
+
+#[derive(Clone)]
+pub struct Mbc2
+{
+    bytes: Vec<u8>,
+    ram: [u8; RAM_SIZE],
+    rom_banks: usize,
+    ram_enabled: bool,
+    rom_bank: u8
+}
+
+impl Mbc2
+{
+    pub fn new(rom: &Rom) -> Mbc2
+    {
+        Mbc2
+        {
+            bytes: rom.bytes.clone(),
+            ram: [0; RAM_SIZE],
+            rom_banks: super::bank_count(rom.header.rom_size.max(rom.bytes.len()), ROM_BANK_SIZE),
+            ram_enabled: false,
+            rom_bank: 1
+        }
+    }
+}
+
+impl Mbc for Mbc2
+{
+    fn read_rom(&self, address: u16) -> u8
+    {
+        match address
+        {
+            0x0000..=0x3FFF => super::rom_byte(&self.bytes, 0, address),
+            _ => super::rom_byte(&self.bytes, self.rom_bank as usize & (self.rom_banks - 1), address)
+        }
+    }
+
+    fn write_rom(&mut self, address: u16, data: u8)
+    {
+        match address
+        {
+            0x0000..=0x3FFF if address & REGISTER_SELECT_BIT == 0 => {self.ram_enabled = data & 0x0F == RAM_ENABLE_VALUE;},
+            0x0000..=0x3FFF => {self.rom_bank = (data & ROM_BANK_MASK).max(1);},
+            _ => {}
+        }
+    }
+
+    fn read_ram(&self, address: u16) -> u8
+    {
+        if !self.ram_enabled
+        {
+            return 0xFF;
+        }
+        self.ram[address as usize % RAM_SIZE] | RAM_UNUSED_BITS
+    }
+
+    fn write_ram(&mut self, address: u16, data: u8)
+    {
+        if self.ram_enabled
+        {
+            self.ram[address as usize % RAM_SIZE] = data & !RAM_UNUSED_BITS;
+        }
+    }
+
+    fn save_data(&mut self) -> Vec<u8>
+    {
+        self.ram.to_vec()
+    }
+
+    fn load_save_data(&mut self, data: &[u8])
+    {
+        super::load_ram(&mut self.ram, data);
+        for x in self.ram.iter_mut()
+        {
+            *x &= !RAM_UNUSED_BITS;
+        }
+    }
+
+    fn clone_box(&self) -> Box<dyn Mbc>
+    {
+        Box::new(self.clone())
+    }
+}
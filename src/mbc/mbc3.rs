@@ -0,0 +1,141 @@
+use crate::{rom::Rom, rtc::{self, Rtc, TimeSourceHandle}};
+use super::{Mbc, RAM_BANK_SIZE, ROM_BANK_SIZE};
+
+//----MBC3 Registers (write only, selected by address)----
+//0x0000-0x1FFF: RAM and RTC enable, 0x0A in the lower nibble enables
+//0x2000-0x3FFF: 7 bit ROM bank, 0 is treated as 1
+//0x4000-0x5FFF: RAM bank 0x00-0x03 or RTC register 0x08-0x0C
+//0x6000-0x7FFF: latch clock data, writing 0x00 then 0x01 latches
+const RAM_ENABLE_VALUE:u8 = 0x0A;
+const ROM_BANK_MASK:u8 = 0b01111111;
+
+#[derive(Clone)]
+pub struct Mbc3
+{
+    bytes: Vec<u8>,
+    ram: Vec<u8>,
+    rtc: Option<Rtc>,
+    rom_banks: usize,
+    ram_banks: usize,
+    ram_enabled: bool,
+    rom_bank: u8,
+    ram_select: u8,
+    latch_armed: bool
+}
+
+impl Mbc3
+{
+    pub fn new(rom: &Rom, time_source: TimeSourceHandle) -> Mbc3
+    {
+        Mbc3
+        {
+            bytes: rom.bytes.clone(),
+            ram: vec![0; rom.header.ram_size],
+            rtc: if rom.header.has_rtc { Some(Rtc::new(time_source)) } else { None },
+            rom_banks: super::bank_count(rom.header.rom_size.max(rom.bytes.len()), ROM_BANK_SIZE),
+            ram_banks: super::bank_count(rom.header.ram_size, RAM_BANK_SIZE),
+            ram_enabled: false,
+            rom_bank: 1,
+            ram_select: 0,
+            latch_armed: false
+        }
+    }
+}
+
+impl Mbc for Mbc3
+{
+    fn read_rom(&self, address: u16) -> u8
+    {
+        match address
+        {
+            0x0000..=0x3FFF => super::rom_byte(&self.bytes, 0, address),
+            _ => super::rom_byte(&self.bytes, self.rom_bank as usize & (self.rom_banks - 1), address)
+        }
+    }
+
+    fn write_rom(&mut self, address: u16, data: u8)
+    {
+        match address
+        {
+            0x0000..=0x1FFF => {self.ram_enabled = data & 0x0F == RAM_ENABLE_VALUE;},
+            0x2000..=0x3FFF => {self.rom_bank = (data & ROM_BANK_MASK).max(1);},
+            0x4000..=0x5FFF => {self.ram_select = data;},
+            _ =>
+            {
+                //Latch on a 0 -> 1 transition
+                if self.latch_armed && data == 0x01
+                {
+                    if let Some(rtc) = &mut self.rtc
+                    {
+                        rtc.latch();
+                    }
+                }
+                self.latch_armed = data == 0x00;
+            }
+        }
+    }
+
+    fn read_ram(&self, address: u16) -> u8
+    {
+        if !self.ram_enabled
+        {
+            return 0xFF;
+        }
+        match (self.ram_select, &self.rtc)
+        {
+            (0x00..=0x03, _) =>
+            {
+                let bank = self.ram_select as usize & (self.ram_banks - 1);
+                *self.ram.get(super::ram_offset(bank, address)).unwrap_or(&0xFF)
+            },
+            (rtc::RTC_S..=rtc::RTC_DH, Some(rtc)) => rtc.read(self.ram_select),
+            _ => 0xFF
+        }
+    }
+
+    fn write_ram(&mut self, address: u16, data: u8)
+    {
+        if !self.ram_enabled
+        {
+            return;
+        }
+        match (self.ram_select, &mut self.rtc)
+        {
+            (0x00..=0x03, _) =>
+            {
+                let bank = self.ram_select as usize & (self.ram_banks - 1);
+                if let Some(x) = self.ram.get_mut(super::ram_offset(bank, address))
+                {
+                    *x = data;
+                }
+            },
+            (rtc::RTC_S..=rtc::RTC_DH, Some(rtc)) => {rtc.write(self.ram_select, data);},
+            _ => {}
+        }
+    }
+
+    fn save_data(&mut self) -> Vec<u8>
+    {
+        let mut data = self.ram.clone();
+        if let Some(rtc) = &mut self.rtc
+        {
+            data.extend_from_slice(&rtc.save());
+        }
+        data
+    }
+
+    fn load_save_data(&mut self, data: &[u8])
+    {
+        let ram_len = self.ram.len().min(data.len());
+        super::load_ram(&mut self.ram, &data[..ram_len]);
+        if let Some(rtc) = &mut self.rtc
+        {
+            rtc.load(&data[ram_len..]);
+        }
+    }
+
+    fn clone_box(&self) -> Box<dyn Mbc>
+    {
+        Box::new(self.clone())
+    }
+}
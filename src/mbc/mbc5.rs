@@ -0,0 +1,132 @@
+use crate::rom::Rom;
+use super::{Mbc, RAM_BANK_SIZE, ROM_BANK_SIZE};
+
+//----MBC5 Registers (write only, selected by address)----
+//0x0000-0x1FFF: RAM enable, 0x0A in the lower nibble enables
+//0x2000-0x2FFF: lower 8 bits of the ROM bank, bank 0 can be selected
+//0x3000-0x3FFF: 9th bit of the ROM bank
+//0x4000-0x5FFF: 4 bit RAM bank, bit 3 drives the motor on rumble cartridges
+const RAM_ENABLE_VALUE:u8 = 0x0A;
+const RAM_BANK_MASK:u8 = 0b00001111;
+const RUMBLE_RAM_BANK_MASK:u8 = 0b00000111;
+const RUMBLE_MOTOR:u8 = 1 << 3;
+
+#[derive(Clone)]
+pub struct Mbc5
+{
+    bytes: Vec<u8>,
+    ram: Vec<u8>,
+    rom_banks: usize,
+    ram_banks: usize,
+    ram_enabled: bool,
+    rom_bank: u16,
+    ram_bank: u8,
+    rumble: Option<Rumble>
+}
+
+#[derive(Clone)]
+struct Rumble
+{
+    active: bool,
+    hardware_handle: crate::HardwareHandle
+}
+
+impl Mbc5
+{
+    pub fn new(rom: &Rom, hardware_handle: crate::HardwareHandle) -> Mbc5
+    {
+        Mbc5
+        {
+            bytes: rom.bytes.clone(),
+            ram: vec![0; rom.header.ram_size],
+            rom_banks: super::bank_count(rom.header.rom_size.max(rom.bytes.len()), ROM_BANK_SIZE),
+            ram_banks: super::bank_count(rom.header.ram_size, RAM_BANK_SIZE),
+            ram_enabled: false,
+            rom_bank: 1,
+            ram_bank: 0,
+            rumble: if rom.header.has_rumble { Some(Rumble { active: false, hardware_handle }) } else { None }
+        }
+    }
+
+    fn ram_offset(&self, address: u16) -> usize
+    {
+        super::ram_offset(self.ram_bank as usize & (self.ram_banks - 1), address)
+    }
+}
+
+impl Mbc for Mbc5
+{
+    fn read_rom(&self, address: u16) -> u8
+    {
+        match address
+        {
+            0x0000..=0x3FFF => super::rom_byte(&self.bytes, 0, address),
+            _ => super::rom_byte(&self.bytes, self.rom_bank as usize & (self.rom_banks - 1), address)
+        }
+    }
+
+    fn write_rom(&mut self, address: u16, data: u8)
+    {
+        match address
+        {
+            0x0000..=0x1FFF => {self.ram_enabled = data & 0x0F == RAM_ENABLE_VALUE;},
+            0x2000..=0x2FFF => {self.rom_bank = (self.rom_bank & 0x100) | data as u16;},
+            0x3000..=0x3FFF => {self.rom_bank = (self.rom_bank & 0xFF) | (((data & 1) as u16) << 8);},
+            0x4000..=0x5FFF =>
+            {
+                match &mut self.rumble
+                {
+                    Some(rumble) =>
+                    {
+                        self.ram_bank = data & RUMBLE_RAM_BANK_MASK;
+                        let active = data & RUMBLE_MOTOR != 0;
+                        if active != rumble.active
+                        {
+                            rumble.active = active;
+                            rumble.hardware_handle.borrow_mut().rumble_update(active);
+                        }
+                    },
+                    None => {self.ram_bank = data & RAM_BANK_MASK;}
+                }
+            },
+            _ => {}
+        }
+    }
+
+    fn read_ram(&self, address: u16) -> u8
+    {
+        if !self.ram_enabled
+        {
+            return 0xFF;
+        }
+        *self.ram.get(self.ram_offset(address)).unwrap_or(&0xFF)
+    }
+
+    fn write_ram(&mut self, address: u16, data: u8)
+    {
+        if !self.ram_enabled
+        {
+            return;
+        }
+        let offset = self.ram_offset(address);
+        if let Some(x) = self.ram.get_mut(offset)
+        {
+            *x = data;
+        }
+    }
+
+    fn save_data(&mut self) -> Vec<u8>
+    {
+        self.ram.clone()
+    }
+
+    fn load_save_data(&mut self, data: &[u8])
+    {
+        super::load_ram(&mut self.ram, data);
+    }
+
+    fn clone_box(&self) -> Box<dyn Mbc>
+    {
+        Box::new(self.clone())
+    }
+}
@@ -0,0 +1,66 @@
+mod mbc1_tests;
+mod mbc2_tests;
+mod mbc3_tests;
+mod mbc5_tests;
+
+use std::{cell::RefCell, rc::Rc};
+use crate::{rom::{CartridgeHeader, MBCModel, Rom}, rtc::ManualClock, tests::TestFrontend};
+use super::Mbc;
+
+///Builds a cartridge where the first byte of every ROM bank holds the bank number
+fn test_rom(mbc_model: MBCModel, rom_size: usize, ram_size: usize) -> Rom
+{
+    let mut bytes = vec![0; rom_size];
+    for (bank, chunk) in bytes.chunks_mut(super::ROM_BANK_SIZE).enumerate()
+    {
+        chunk[0] = bank as u8;
+        chunk[1] = (bank >> 8) as u8;
+    }
+    Rom
+    {
+        bytes,
+        header: CartridgeHeader
+        {
+            title: String::from("TEST"),
+            manufacturer_code: None,
+            cgb_flag: 0,
+            new_licensee_code: String::new(),
+            sgb_flag: 0,
+            cartridge_type: 0,
+            mbc_model,
+            rom_size,
+            ram_size,
+            has_battery: false,
+            has_rtc: false,
+            has_rumble: false,
+            destination_code: 0,
+            old_licensee_code: 0,
+            mask_rom_version: 0,
+            header_checksum: 0,
+            global_checksum: 0
+        },
+        validation: Default::default()
+    }
+}
+
+fn test_frontend() -> Rc<RefCell<TestFrontend>>
+{
+    Rc::new(RefCell::new(TestFrontend::default()))
+}
+
+fn load(rom: &Rom) -> Box<dyn Mbc>
+{
+    super::new(rom, Rc::new(ManualClock::new(0)), test_frontend())
+}
+
+#[test]
+fn clone_is_independent()
+{
+    let mut mbc = load(&test_rom(MBCModel::Mbc1_16_8, 0x8000, 0x2000));
+    mbc.write_rom(0x0000, 0x0A);
+    mbc.write_ram(0xA000, 0x12);
+    let mut copy = mbc.clone();
+    copy.write_ram(0xA000, 0x34);
+    assert_eq!(mbc.read_ram(0xA000), 0x12);
+    assert_eq!(copy.read_ram(0xA000), 0x34);
+}
@@ -0,0 +1,125 @@
+use crate::ram::{Ram, SC_BOOT_ROM_DISABLE};
+use crate::rom::MBCModel;
+use crate::mbc::tests::{load, test_frontend, test_rom};
+
+#[test]
+fn test_mbc1_rom_bank_select()
+{
+    let mut mbc = load(&test_rom(MBCModel::Mbc1_16_8, 0x80000, 0));
+    assert_eq!(mbc.read_rom(0x0000), 0);
+    assert_eq!(mbc.read_rom(0x4000), 1);
+    mbc.write_rom(0x2000, 0x05);
+    assert_eq!(mbc.read_rom(0x4000), 5);
+    //Bank 0 maps to bank 1
+    mbc.write_rom(0x3FFF, 0x00);
+    assert_eq!(mbc.read_rom(0x4000), 1);
+    //Only 5 bits are used, and the bank wraps to the ROM size
+    mbc.write_rom(0x2000, 0xFF);
+    assert_eq!(mbc.read_rom(0x4000), 0x1F);
+}
+
+#[test]
+fn test_mbc1_zero_check_ignores_rom_size()
+{
+    //0x20 only has zeroes in the lower 5 bits, so it still maps to 1
+    let mut mbc = load(&test_rom(MBCModel::Mbc1_16_8, 0x40000, 0));
+    mbc.write_rom(0x2000, 0x20);
+    assert_eq!(mbc.read_rom(0x4000), 1);
+    //0x10 is masked by the 16 bank ROM to bank 0
+    mbc.write_rom(0x2000, 0x10);
+    assert_eq!(mbc.read_rom(0x4000), 0);
+}
+
+#[test]
+fn test_mbc1_upper_bank_bits()
+{
+    let mut mbc = load(&test_rom(MBCModel::Mbc1_16_8, 0x200000, 0));
+    mbc.write_rom(0x2000, 0x02);
+    mbc.write_rom(0x4000, 0x01);
+    assert_eq!(mbc.read_rom(0x4000), 0x22);
+    //Mode 0 keeps bank 0 at the bottom of the map
+    assert_eq!(mbc.read_rom(0x0000), 0x00);
+    mbc.write_rom(0x6000, 0x01);
+    assert_eq!(mbc.read_rom(0x0000), 0x20);
+    mbc.write_rom(0x4000, 0x03);
+    assert_eq!(mbc.read_rom(0x0000), 0x60);
+    assert_eq!(mbc.read_rom(0x4000), 0x62);
+}
+
+#[test]
+fn test_mbc1_ram_enable()
+{
+    let mut mbc = load(&test_rom(MBCModel::Mbc1_16_8, 0x8000, 0x2000));
+    mbc.write_ram(0xA000, 0x42);
+    assert_eq!(mbc.read_ram(0xA000), 0xFF);
+    mbc.write_rom(0x0000, 0x0A);
+    mbc.write_ram(0xA000, 0x42);
+    assert_eq!(mbc.read_ram(0xA000), 0x42);
+    mbc.write_rom(0x1FFF, 0x00);
+    assert_eq!(mbc.read_ram(0xA000), 0xFF);
+    mbc.write_rom(0x0000, 0x1A);
+    assert_eq!(mbc.read_ram(0xA000), 0x42);
+}
+
+#[test]
+fn test_mbc1_ram_banking()
+{
+    let mut mbc = load(&test_rom(MBCModel::Mbc1_16_8, 0x8000, 0x8000));
+    mbc.write_rom(0x0000, 0x0A);
+    mbc.write_ram(0xA000, 0x00);
+    mbc.write_rom(0x6000, 0x01);
+    for bank in 1..4
+    {
+        mbc.write_rom(0x4000, bank);
+        mbc.write_ram(0xA000, bank);
+    }
+    mbc.write_rom(0x4000, 2);
+    assert_eq!(mbc.read_ram(0xA000), 2);
+    //Mode 0 always uses RAM bank 0
+    mbc.write_rom(0x6000, 0x00);
+    assert_eq!(mbc.read_ram(0xA000), 0);
+}
+
+#[test]
+fn test_mbc1_memory_map()
+{
+    let mut ram = Ram::new();
+    ram.load_rom(&test_rom(MBCModel::Mbc1_16_8, 0x10000, 0x2000), test_frontend());
+    ram.write(SC_BOOT_ROM_DISABLE, 1);
+    ram.write(0x2000, 0x03);
+    assert_eq!(ram.read(0x4000), 3);
+    ram.write(0x0000, 0x0A);
+    ram.write(0xBFFF, 0x69);
+    assert_eq!(ram.read(0xBFFF), 0x69);
+    //Writes only reach the controller registers, never the ROM data
+    ram.write(0x6000, 0x00);
+    assert_eq!(ram.read(0x6000), 0x00);
+    assert_eq!(ram.read(0x4000), 3);
+}
+
+#[test]
+fn test_mbc1_save_data()
+{
+    let mut mbc = load(&test_rom(MBCModel::Mbc1_16_8, 0x8000, 0x8000));
+    let mut data = vec![0; 0x8000];
+    data[0x2001] = 0x42;
+    mbc.load_save_data(&data);
+    mbc.write_rom(0x0000, 0x0A);
+    mbc.write_rom(0x6000, 0x01);
+    mbc.write_rom(0x4000, 0x01);
+    assert_eq!(mbc.read_ram(0xA001), 0x42);
+    mbc.write_ram(0xA002, 0x69);
+    assert_eq!(mbc.save_data()[0x2002], 0x69);
+}
+
+#[test]
+fn test_external_ram_dirty()
+{
+    let mut ram = Ram::new();
+    ram.load_rom(&test_rom(MBCModel::Mbc1_16_8, 0x8000, 0x2000), test_frontend());
+    ram.write(0x0000, 0x0A);
+    assert!(!ram.take_external_ram_dirty());
+    ram.write(0xA000, 0x01);
+    assert!(ram.take_external_ram_dirty());
+    assert!(!ram.take_external_ram_dirty());
+}
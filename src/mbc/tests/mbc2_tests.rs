@@ -0,0 +1,56 @@
+use crate::mbc::tests::{load, test_rom};
+use crate::rom::MBCModel;
+
+#[test]
+fn test_mbc2_register_select()
+{
+    let mut mbc = load(&test_rom(MBCModel::Mbc2, 0x40000, 512));
+    //Bit 8 set selects the ROM bank
+    mbc.write_rom(0x2100, 0x05);
+    assert_eq!(mbc.read_rom(0x4000), 5);
+    mbc.write_rom(0x0100, 0x0F);
+    assert_eq!(mbc.read_rom(0x4000), 0x0F);
+    mbc.write_rom(0x3F00, 0x00);
+    assert_eq!(mbc.read_rom(0x4000), 1);
+    //Bit 8 clear is RAM enable and leaves the bank alone
+    mbc.write_rom(0x2000, 0x0A);
+    assert_eq!(mbc.read_rom(0x4000), 1);
+    mbc.write_ram(0xA000, 0x03);
+    assert_eq!(mbc.read_ram(0xA000), 0xF3);
+    mbc.write_rom(0x00FF, 0x00);
+    assert_eq!(mbc.read_ram(0xA000), 0xFF);
+    //Upper region writes do nothing
+    mbc.write_rom(0x4100, 0x03);
+    assert_eq!(mbc.read_rom(0x4000), 1);
+}
+
+#[test]
+fn test_mbc2_ram_nibbles()
+{
+    let mut mbc = load(&test_rom(MBCModel::Mbc2, 0x40000, 512));
+    mbc.write_rom(0x0000, 0x0A);
+    mbc.write_ram(0xA000, 0xAB);
+    assert_eq!(mbc.read_ram(0xA000), 0xFB);
+    mbc.write_ram(0xA1FF, 0x00);
+    assert_eq!(mbc.read_ram(0xA1FF), 0xF0);
+}
+
+#[test]
+fn test_mbc2_ram_echo()
+{
+    let mut mbc = load(&test_rom(MBCModel::Mbc2, 0x40000, 512));
+    mbc.write_rom(0x0000, 0x0A);
+    mbc.write_ram(0xA010, 0x07);
+    assert_eq!(mbc.read_ram(0xA210), 0xF7);
+    assert_eq!(mbc.read_ram(0xBE10), 0xF7);
+    mbc.write_ram(0xBFFF, 0x0C);
+    assert_eq!(mbc.read_ram(0xA1FF), 0xFC);
+}
+
+#[test]
+fn test_mbc2_save_data()
+{
+    let mut mbc = load(&test_rom(MBCModel::Mbc2, 0x40000, 512));
+    mbc.load_save_data(&[0xFA; 512]);
+    assert_eq!(mbc.save_data(), vec![0x0A; 512]);
+}
@@ -0,0 +1,190 @@
+use std::rc::Rc;
+use crate::mbc::*;
+use crate::mbc::tests::{load, test_frontend, test_rom};
+use crate::rom::MBCModel;
+use crate::rtc::{self, ManualClock};
+
+fn load_with_rtc(clock: &Rc<ManualClock>) -> Box<dyn Mbc>
+{
+    let mut rom = test_rom(MBCModel::Mbc3, 0x200000, 0x8000);
+    rom.header.has_rtc = true;
+    let mut mbc = new(&rom, Rc::clone(clock) as rtc::TimeSourceHandle, test_frontend());
+    mbc.write_rom(0x0000, 0x0A);
+    mbc
+}
+
+fn latch(mbc: &mut Box<dyn Mbc>)
+{
+    mbc.write_rom(0x6000, 0x00);
+    mbc.write_rom(0x6000, 0x01);
+}
+
+fn read_rtc(mbc: &mut Box<dyn Mbc>, register: u8) -> u8
+{
+    mbc.write_rom(0x4000, register);
+    mbc.read_ram(0xA000)
+}
+
+#[test]
+fn test_mbc3_rom_bank_select()
+{
+    let mut mbc = load(&test_rom(MBCModel::Mbc3, 0x200000, 0));
+    assert_eq!(mbc.read_rom(0x4000), 1);
+    mbc.write_rom(0x2000, 0x7F);
+    assert_eq!(mbc.read_rom(0x4000), 0x7F);
+    mbc.write_rom(0x2000, 0x00);
+    assert_eq!(mbc.read_rom(0x4000), 1);
+    //Unlike MBC1, 0x20 is a real bank
+    mbc.write_rom(0x2000, 0x20);
+    assert_eq!(mbc.read_rom(0x4000), 0x20);
+    assert_eq!(mbc.read_rom(0x0000), 0);
+}
+
+#[test]
+fn test_mbc3_ram_banks()
+{
+    let mut mbc = load(&test_rom(MBCModel::Mbc3, 0x8000, 0x8000));
+    mbc.write_rom(0x0000, 0x0A);
+    for bank in 0..4
+    {
+        mbc.write_rom(0x4000, bank);
+        mbc.write_ram(0xA123, bank + 0x10);
+    }
+    for bank in 0..4
+    {
+        mbc.write_rom(0x4000, bank);
+        assert_eq!(mbc.read_ram(0xA123), bank + 0x10);
+    }
+    //No clock on this cartridge
+    mbc.write_rom(0x4000, rtc::RTC_S);
+    assert_eq!(mbc.read_ram(0xA000), 0xFF);
+}
+
+#[test]
+fn test_mbc3_rtc_latch()
+{
+    let clock = Rc::new(ManualClock::new(1000));
+    let mut mbc = load_with_rtc(&clock);
+    clock.advance(3 * 3600 + 25 * 60 + 7);
+    //Registers don't move until latched
+    assert_eq!(read_rtc(&mut mbc, rtc::RTC_S), 0);
+    latch(&mut mbc);
+    assert_eq!(read_rtc(&mut mbc, rtc::RTC_S), 7);
+    assert_eq!(read_rtc(&mut mbc, rtc::RTC_M), 25);
+    assert_eq!(read_rtc(&mut mbc, rtc::RTC_H), 3);
+    //Writing 1 without a preceding 0 doesn't latch
+    clock.advance(10);
+    mbc.write_rom(0x6000, 0x01);
+    assert_eq!(read_rtc(&mut mbc, rtc::RTC_S), 7);
+    latch(&mut mbc);
+    assert_eq!(read_rtc(&mut mbc, rtc::RTC_S), 17);
+}
+
+#[test]
+fn test_mbc3_rtc_write_keeps_latch()
+{
+    let clock = Rc::new(ManualClock::new(0));
+    let mut mbc = load_with_rtc(&clock);
+    clock.advance(2 * 60 + 30);
+    latch(&mut mbc);
+    clock.advance(5 * 60);
+    //Only the written register changes, the others still show the last latch
+    mbc.write_rom(0x4000, rtc::RTC_S);
+    mbc.write_ram(0xA000, 10);
+    assert_eq!(read_rtc(&mut mbc, rtc::RTC_S), 10);
+    assert_eq!(read_rtc(&mut mbc, rtc::RTC_M), 2);
+    //The live counter picked up the write too
+    latch(&mut mbc);
+    assert_eq!(read_rtc(&mut mbc, rtc::RTC_S), 10);
+    assert_eq!(read_rtc(&mut mbc, rtc::RTC_M), 7);
+}
+
+#[test]
+fn test_mbc3_rtc_halt()
+{
+    let clock = Rc::new(ManualClock::new(0));
+    let mut mbc = load_with_rtc(&clock);
+    mbc.write_rom(0x4000, rtc::RTC_DH);
+    mbc.write_ram(0xA000, 0x40);
+    clock.advance(100);
+    latch(&mut mbc);
+    assert_eq!(read_rtc(&mut mbc, rtc::RTC_S), 0);
+    assert_eq!(read_rtc(&mut mbc, rtc::RTC_DH), 0x40);
+    //Set the time while halted, then resume
+    mbc.write_rom(0x4000, rtc::RTC_M);
+    mbc.write_ram(0xA000, 59);
+    mbc.write_rom(0x4000, rtc::RTC_DH);
+    mbc.write_ram(0xA000, 0x00);
+    clock.advance(61);
+    latch(&mut mbc);
+    assert_eq!(read_rtc(&mut mbc, rtc::RTC_S), 1);
+    assert_eq!(read_rtc(&mut mbc, rtc::RTC_M), 0);
+    assert_eq!(read_rtc(&mut mbc, rtc::RTC_H), 1);
+}
+
+#[test]
+fn test_mbc3_rtc_day_carry()
+{
+    let clock = Rc::new(ManualClock::new(0));
+    let mut mbc = load_with_rtc(&clock);
+    clock.advance(255 * 86400);
+    latch(&mut mbc);
+    assert_eq!(read_rtc(&mut mbc, rtc::RTC_DL), 255);
+    assert_eq!(read_rtc(&mut mbc, rtc::RTC_DH), 0x00);
+    clock.advance(86400);
+    latch(&mut mbc);
+    assert_eq!(read_rtc(&mut mbc, rtc::RTC_DL), 0);
+    assert_eq!(read_rtc(&mut mbc, rtc::RTC_DH), 0x01);
+    //Day 511 -> 0 sets the carry bit, which stays set
+    clock.advance(256 * 86400);
+    latch(&mut mbc);
+    assert_eq!(read_rtc(&mut mbc, rtc::RTC_DL), 0);
+    assert_eq!(read_rtc(&mut mbc, rtc::RTC_DH), 0x80);
+    clock.advance(86400);
+    latch(&mut mbc);
+    assert_eq!(read_rtc(&mut mbc, rtc::RTC_DL), 1);
+    assert_eq!(read_rtc(&mut mbc, rtc::RTC_DH), 0x80);
+    //Only a write clears it
+    mbc.write_ram(0xA000, 0x00);
+    assert_eq!(read_rtc(&mut mbc, rtc::RTC_DH), 0x00);
+}
+
+#[test]
+fn test_mbc3_save_footer()
+{
+    let clock = Rc::new(ManualClock::new(1_000_000));
+    let mut mbc = load_with_rtc(&clock);
+    mbc.write_rom(0x4000, 0x00);
+    mbc.write_ram(0xA000, 0x69);
+    clock.advance(86400 + 3600 + 60 + 1);
+    latch(&mut mbc);
+    clock.advance(1);
+    let data = mbc.save_data();
+    assert_eq!(data.len(), 0x8000 + rtc::SAVE_FOOTER_SIZE);
+    assert_eq!(data[0], 0x69);
+    let footer = &data[0x8000..];
+    //Live registers, 32 bits each
+    assert_eq!(&footer[0..20], &[2,0,0,0, 1,0,0,0, 1,0,0,0, 1,0,0,0, 0,0,0,0]);
+    //Latched registers
+    assert_eq!(&footer[20..40], &[1,0,0,0, 1,0,0,0, 1,0,0,0, 1,0,0,0, 0,0,0,0]);
+    assert_eq!(&footer[40..48], &(1_000_000_u64 + 86400 + 3600 + 62).to_le_bytes());
+}
+
+#[test]
+fn test_mbc3_load_footer_catches_up()
+{
+    let clock = Rc::new(ManualClock::new(5000));
+    let mut mbc = load_with_rtc(&clock);
+    let mut data = vec![0; 0x8000];
+    //10:30:00 on day 0, saved at 4000 seconds on a 32 bit timestamp
+    let footer = [0,0,0,0, 30,0,0,0, 10,0,0,0, 0,0,0,0, 0,0,0,0,
+                  0,0,0,0, 30,0,0,0, 10,0,0,0, 0,0,0,0, 0,0,0,0];
+    data.extend_from_slice(&footer);
+    data.extend_from_slice(&4000_u32.to_le_bytes());
+    mbc.load_save_data(&data);
+    assert_eq!(read_rtc(&mut mbc, rtc::RTC_M), 30);
+    latch(&mut mbc);
+    assert_eq!(read_rtc(&mut mbc, rtc::RTC_S), 40);
+    assert_eq!(read_rtc(&mut mbc, rtc::RTC_M), 46);
+    assert_eq!(read_rtc(&mut mbc, rtc::RTC_H), 10);
+}
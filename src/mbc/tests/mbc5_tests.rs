@@ -0,0 +1,67 @@
+use std::rc::Rc;
+use crate::mbc::*;
+use crate::mbc::tests::{load, test_frontend, test_rom};
+use crate::rom::MBCModel;
+use crate::rtc::ManualClock;
+
+#[test]
+fn test_mbc5_rom_bank_select()
+{
+    let mut mbc = load(&test_rom(MBCModel::Mbc5, 0x800000, 0));
+    assert_eq!(mbc.read_rom(0x4000), 1);
+    //Bank 0 is selectable in the upper region
+    mbc.write_rom(0x2000, 0x00);
+    assert_eq!(mbc.read_rom(0x4000), 0);
+    mbc.write_rom(0x2000, 0x34);
+    mbc.write_rom(0x3000, 0x01);
+    assert_eq!((mbc.read_rom(0x4000), mbc.read_rom(0x4001)), (0x34, 0x01));
+    mbc.write_rom(0x2FFF, 0xFF);
+    assert_eq!((mbc.read_rom(0x4000), mbc.read_rom(0x4001)), (0xFF, 0x01));
+    mbc.write_rom(0x3FFF, 0x00);
+    assert_eq!((mbc.read_rom(0x4000), mbc.read_rom(0x4001)), (0xFF, 0x00));
+    assert_eq!(mbc.read_rom(0x0000), 0);
+}
+
+#[test]
+fn test_mbc5_ram_banks()
+{
+    let mut mbc = load(&test_rom(MBCModel::Mbc5, 0x8000, 0x20000));
+    mbc.write_rom(0x0000, 0x0A);
+    for bank in 0..16
+    {
+        mbc.write_rom(0x4000, bank);
+        mbc.write_ram(0xB000, bank);
+    }
+    for bank in 0..16
+    {
+        mbc.write_rom(0x4000, bank);
+        assert_eq!(mbc.read_ram(0xB000), bank);
+    }
+}
+
+#[test]
+fn test_mbc5_rumble()
+{
+    let mut rom = test_rom(MBCModel::Mbc5, 0x8000, 0x8000);
+    rom.header.has_rumble = true;
+    let frontend = test_frontend();
+    let mut mbc = new(&rom, Rc::new(ManualClock::new(0)), frontend.clone());
+    mbc.write_rom(0x0000, 0x0A);
+    mbc.write_rom(0x4000, 0x01);
+    mbc.write_ram(0xA000, 0x11);
+    //Motor on, bit 3 isn't part of the RAM bank
+    mbc.write_rom(0x4000, 0x09);
+    assert_eq!(mbc.read_ram(0xA000), 0x11);
+    mbc.write_rom(0x4000, 0x0A);
+    mbc.write_rom(0x4000, 0x00);
+    assert_eq!(frontend.borrow().rumble_events, vec![true, false]);
+}
+
+#[test]
+fn test_mbc5_no_rumble_without_motor()
+{
+    let frontend = test_frontend();
+    let mut mbc = new(&test_rom(MBCModel::Mbc5, 0x8000, 0x20000), Rc::new(ManualClock::new(0)), frontend.clone());
+    mbc.write_rom(0x4000, 0x08);
+    assert!(frontend.borrow().rumble_events.is_empty());
+}
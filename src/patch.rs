@@ -0,0 +1,330 @@
+#[cfg(test)]
+mod tests;
+
+//----Patch Headers----
+const IPS_MAGIC:&[u8] = b"PATCH";
+const IPS_EOF:&[u8] = b"EOF";
+const UPS_MAGIC:&[u8] = b"UPS1";
+const BPS_MAGIC:&[u8] = b"BPS1";
+
+//UPS and BPS end with the source, target and patch CRC32s, little endian
+const CHECKSUM_FOOTER_SIZE:usize = 12;
+
+///File extensions checked next to a ROM for soft-patching, in order of preference
+pub const PATCH_EXTENSIONS:[&str; 3] = ["ips", "ups", "bps"];
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum PatchFormat {Ips, Ups, Bps}
+
+impl PatchFormat
+{
+    ///Identifies a patch by its magic bytes
+    pub fn detect(patch: &[u8]) -> Option<PatchFormat>
+    {
+        if patch.starts_with(IPS_MAGIC)
+        {
+            Some(PatchFormat::Ips)
+        }
+        else if patch.starts_with(UPS_MAGIC)
+        {
+            Some(PatchFormat::Ups)
+        }
+        else if patch.starts_with(BPS_MAGIC)
+        {
+            Some(PatchFormat::Bps)
+        }
+        else
+        {
+            None
+        }
+    }
+}
+
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub enum PatchError
+{
+    ///Magic bytes don't match any supported format
+    UnknownFormat,
+    ///The patch ends early or points outside the files it describes
+    Malformed(PatchFormat),
+    ///The ROM isn't the one the patch was made for
+    SourceChecksum { expected: u32, actual: u32 },
+    TargetChecksum { expected: u32, actual: u32 },
+    ///The patch file itself is damaged
+    PatchChecksum { expected: u32, actual: u32 }
+}
+
+impl std::fmt::Display for PatchError
+{
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result
+    {
+        match self
+        {
+            PatchError::UnknownFormat => write!(f, "Unknown patch format"),
+            PatchError::Malformed(x) => write!(f, "Malformed {:?} patch", x),
+            PatchError::SourceChecksum { expected, actual } => write!(f, "Patch expects a ROM with CRC32 {:08X}, got {:08X}", expected, actual),
+            PatchError::TargetChecksum { expected, actual } => write!(f, "Patched ROM CRC32 is {:08X}, expected {:08X}", actual, expected),
+            PatchError::PatchChecksum { expected, actual } => write!(f, "Patch CRC32 is {:08X}, expected {:08X}", actual, expected)
+        }
+    }
+}
+
+impl std::error::Error for PatchError {}
+
+///Applies an IPS, UPS or BPS patch, the format is picked from the patch header
+pub fn apply(rom: &[u8], patch: &[u8]) -> Result<Vec<u8>, PatchError>
+{
+    match PatchFormat::detect(patch)
+    {
+        Some(PatchFormat::Ips) => apply_ips(rom, patch),
+        Some(PatchFormat::Ups) => apply_ups(rom, patch),
+        Some(PatchFormat::Bps) => apply_bps(rom, patch),
+        None => Err(PatchError::UnknownFormat)
+    }
+}
+
+///Standard CRC32 (reflected, polynomial 0xEDB88320) as used by UPS and BPS
+pub fn crc32(bytes: &[u8]) -> u32
+{
+    let mut crc = 0xFFFFFFFF_u32;
+    for x in bytes
+    {
+        crc ^= *x as u32;
+        for _ in 0..8
+        {
+            crc = if crc & 1 != 0 { (crc >> 1) ^ 0xEDB88320 } else { crc >> 1 };
+        }
+    }
+    !crc
+}
+
+///Byte cursor over a patch, every read fails with Malformed past the end
+struct Reader<'a>
+{
+    bytes: &'a [u8],
+    pos: usize,
+    format: PatchFormat
+}
+
+impl Reader<'_>
+{
+    fn take(&mut self, len: usize) -> Result<&[u8], PatchError>
+    {
+        let end = self.pos.checked_add(len).filter(|x| *x <= self.bytes.len()).ok_or(PatchError::Malformed(self.format))?;
+        let slice = &self.bytes[self.pos..end];
+        self.pos = end;
+        Ok(slice)
+    }
+
+    fn byte(&mut self) -> Result<u8, PatchError>
+    {
+        Ok(self.take(1)?[0])
+    }
+
+    fn u16_be(&mut self) -> Result<usize, PatchError>
+    {
+        let x = self.take(2)?;
+        Ok(u16::from_be_bytes([x[0], x[1]]) as usize)
+    }
+
+    fn u24_be(&mut self) -> Result<usize, PatchError>
+    {
+        let x = self.take(3)?;
+        Ok(((x[0] as usize) << 16) | ((x[1] as usize) << 8) | x[2] as usize)
+    }
+
+    ///Variable length number shared by UPS and BPS, 7 bits per byte with the high bit ending it
+    fn number(&mut self) -> Result<usize, PatchError>
+    {
+        let mut value = 0_usize;
+        let mut shift = 1_usize;
+        loop
+        {
+            let x = self.byte()?;
+            value = (x as usize & 0x7F).checked_mul(shift)
+                .and_then(|y| value.checked_add(y))
+                .ok_or(PatchError::Malformed(self.format))?;
+            if x & 0x80 != 0
+            {
+                return Ok(value);
+            }
+            shift = shift.checked_shl(7).filter(|x| *x != 0).ok_or(PatchError::Malformed(self.format))?;
+            value = value.checked_add(shift).ok_or(PatchError::Malformed(self.format))?;
+        }
+    }
+}
+
+///IPS: 24 bit offset and 16 bit length records, a zero length means a run of one byte
+fn apply_ips(rom: &[u8], patch: &[u8]) -> Result<Vec<u8>, PatchError>
+{
+    let mut reader = Reader { bytes: patch, pos: IPS_MAGIC.len(), format: PatchFormat::Ips };
+    let mut target = rom.to_vec();
+    loop
+    {
+        if reader.bytes[reader.pos..].starts_with(IPS_EOF)
+        {
+            reader.pos += IPS_EOF.len();
+            break;
+        }
+        let offset = reader.u24_be()?;
+        let (data, len) = match reader.u16_be()?
+        {
+            0 =>
+            {
+                let len = reader.u16_be()?;
+                (None, len)
+            },
+            len => (Some(reader.take(len)?), len)
+        };
+        if target.len() < offset + len
+        {
+            target.resize(offset + len, 0);
+        }
+        match data
+        {
+            Some(x) => target[offset..offset + len].copy_from_slice(x),
+            None => target[offset..offset + len].fill(reader.byte()?)
+        }
+    }
+    //Optional truncation extension after EOF
+    if reader.bytes.len() - reader.pos == 3
+    {
+        let len = reader.u24_be()?;
+        target.truncate(len);
+    }
+    Ok(target)
+}
+
+///Checks the UPS/BPS footer against the patch and ROM, returns the expected target CRC
+fn check_footer(rom: &[u8], patch: &[u8], format: PatchFormat) -> Result<u32, PatchError>
+{
+    if patch.len() < CHECKSUM_FOOTER_SIZE + 4
+    {
+        return Err(PatchError::Malformed(format));
+    }
+    let footer = &patch[patch.len() - CHECKSUM_FOOTER_SIZE..];
+    let field = |i: usize| u32::from_le_bytes([footer[i * 4], footer[i * 4 + 1], footer[i * 4 + 2], footer[i * 4 + 3]]);
+
+    let actual = crc32(&patch[..patch.len() - 4]);
+    if actual != field(2)
+    {
+        return Err(PatchError::PatchChecksum { expected: field(2), actual });
+    }
+    let actual = crc32(rom);
+    if actual != field(0)
+    {
+        return Err(PatchError::SourceChecksum { expected: field(0), actual });
+    }
+    Ok(field(1))
+}
+
+fn check_target(target: Vec<u8>, expected: u32) -> Result<Vec<u8>, PatchError>
+{
+    let actual = crc32(&target);
+    if actual != expected
+    {
+        return Err(PatchError::TargetChecksum { expected, actual });
+    }
+    Ok(target)
+}
+
+///UPS: skip counts followed by runs XORed against the source, each run ends on a zero byte
+fn apply_ups(rom: &[u8], patch: &[u8]) -> Result<Vec<u8>, PatchError>
+{
+    let expected_target = check_footer(rom, patch, PatchFormat::Ups)?;
+    let end = patch.len() - CHECKSUM_FOOTER_SIZE;
+    let mut reader = Reader { bytes: &patch[..end], pos: UPS_MAGIC.len(), format: PatchFormat::Ups };
+    let _source_size = reader.number()?;
+    let target_size = reader.number()?;
+
+    let mut target = rom.to_vec();
+    target.resize(target_size, 0);
+    let mut offset = 0_usize;
+    while reader.pos < end
+    {
+        offset = offset.saturating_add(reader.number()?);
+        loop
+        {
+            let x = reader.byte()?;
+            if let Some(y) = target.get_mut(offset)
+            {
+                *y ^= x;
+            }
+            offset = offset.saturating_add(1);
+            if x == 0
+            {
+                break;
+            }
+        }
+    }
+    check_target(target, expected_target)
+}
+
+//----BPS Commands----
+const BPS_SOURCE_READ:usize = 0;
+const BPS_TARGET_READ:usize = 1;
+const BPS_SOURCE_COPY:usize = 2;
+
+///BPS: the target is built from runs copied out of the source, the patch, or itself
+fn apply_bps(rom: &[u8], patch: &[u8]) -> Result<Vec<u8>, PatchError>
+{
+    let expected_target = check_footer(rom, patch, PatchFormat::Bps)?;
+    let end = patch.len() - CHECKSUM_FOOTER_SIZE;
+    let malformed = PatchError::Malformed(PatchFormat::Bps);
+    let mut reader = Reader { bytes: &patch[..end], pos: BPS_MAGIC.len(), format: PatchFormat::Bps };
+    let _source_size = reader.number()?;
+    let target_size = reader.number()?;
+    let metadata_size = reader.number()?;
+    reader.take(metadata_size)?;
+
+    let mut target = Vec::with_capacity(target_size);
+    let mut source_offset = 0_usize;
+    let mut target_offset = 0_usize;
+    //Copy offsets are stored as a sign bit and magnitude relative to the last copy
+    let relative = |offset: usize, data: usize| -> Result<usize, PatchError>
+    {
+        let distance = data >> 1;
+        let result = if data & 1 != 0 { offset.checked_sub(distance) } else { offset.checked_add(distance) };
+        result.ok_or(PatchError::Malformed(PatchFormat::Bps))
+    };
+    while reader.pos < end
+    {
+        let data = reader.number()?;
+        let len = (data >> 2) + 1;
+        if target.len() + len > target_size
+        {
+            return Err(malformed);
+        }
+        match data & 0b11
+        {
+            BPS_SOURCE_READ =>
+            {
+                let start = target.len();
+                target.extend_from_slice(rom.get(start..start + len).ok_or(malformed.clone())?);
+            },
+            BPS_TARGET_READ => target.extend_from_slice(reader.take(len)?),
+            BPS_SOURCE_COPY =>
+            {
+                source_offset = relative(source_offset, reader.number()?)?;
+                target.extend_from_slice(rom.get(source_offset..source_offset + len).ok_or(malformed.clone())?);
+                source_offset += len;
+            },
+            _ =>
+            {
+                target_offset = relative(target_offset, reader.number()?)?;
+                //Byte at a time, the run is allowed to overlap what it's writing
+                for _ in 0..len
+                {
+                    let x = *target.get(target_offset).ok_or(malformed.clone())?;
+                    target.push(x);
+                    target_offset += 1;
+                }
+            }
+        }
+    }
+    if target.len() != target_size
+    {
+        return Err(malformed);
+    }
+    check_target(target, expected_target)
+}
@@ -0,0 +1,146 @@
+use super::*;
+
+fn number(mut x: usize, out: &mut Vec<u8>)
+{
+    loop
+    {
+        let bits = (x & 0x7F) as u8;
+        x >>= 7;
+        if x == 0
+        {
+            out.push(0x80 | bits);
+            return;
+        }
+        out.push(bits);
+        x -= 1;
+    }
+}
+
+fn footer(source: &[u8], target: &[u8], patch: &mut Vec<u8>)
+{
+    patch.extend_from_slice(&crc32(source).to_le_bytes());
+    patch.extend_from_slice(&crc32(target).to_le_bytes());
+    patch.extend_from_slice(&crc32(patch).to_le_bytes());
+}
+
+fn source() -> Vec<u8>
+{
+    (0..64).collect()
+}
+
+#[test]
+fn test_crc32()
+{
+    assert_eq!(crc32(b""), 0);
+    assert_eq!(crc32(b"123456789"), 0xCBF43926);
+}
+
+#[test]
+fn test_number_round_trip()
+{
+    for x in [0, 1, 127, 128, 255, 16511, 16512, 1 << 20]
+    {
+        let mut bytes = Vec::new();
+        number(x, &mut bytes);
+        let mut reader = Reader { bytes: &bytes, pos: 0, format: PatchFormat::Ups };
+        assert_eq!(reader.number().unwrap(), x);
+    }
+}
+
+#[test]
+fn test_ips()
+{
+    let mut patch = b"PATCH".to_vec();
+    //Two bytes at 0x04
+    patch.extend_from_slice(&[0x00, 0x00, 0x04, 0x00, 0x02, 0xAA, 0xBB]);
+    //Run of 3 0xCC at 0x10
+    patch.extend_from_slice(&[0x00, 0x00, 0x10, 0x00, 0x00, 0x00, 0x03, 0xCC]);
+    //Past the end grows the ROM
+    patch.extend_from_slice(&[0x00, 0x00, 0x41, 0x00, 0x01, 0xDD]);
+    patch.extend_from_slice(b"EOF");
+
+    let target = apply(&source(), &patch).unwrap();
+    assert_eq!(target.len(), 66);
+    assert_eq!(&target[0x03..0x07], &[0x03, 0xAA, 0xBB, 0x06]);
+    assert_eq!(&target[0x10..0x14], &[0xCC, 0xCC, 0xCC, 0x13]);
+    assert_eq!(&target[0x40..], &[0x00, 0xDD]);
+
+    //Truncation extension
+    patch.extend_from_slice(&[0x00, 0x00, 0x20]);
+    assert_eq!(apply(&source(), &patch).unwrap().len(), 0x20);
+
+    assert_eq!(apply(&source(), b"PATCH\x00\x00\x04\x00\x05\x01"), Err(PatchError::Malformed(PatchFormat::Ips)));
+    assert_eq!(apply(&source(), b"NOT A PATCH"), Err(PatchError::UnknownFormat));
+}
+
+#[test]
+fn test_ups()
+{
+    let source = source();
+    let mut target = source.clone();
+    target[2] = 0xFF;
+    target[3] = 0x00;
+    target.extend_from_slice(&[0x11, 0x22]);
+
+    let mut patch = b"UPS1".to_vec();
+    number(source.len(), &mut patch);
+    number(target.len(), &mut patch);
+    //Skip 2, XOR 0x02 and 0x03, then the zero terminator consumes offset 4
+    number(2, &mut patch);
+    patch.extend_from_slice(&[0x02 ^ 0xFF, 0x03, 0x00]);
+    //Skip to the end of the source and append
+    number(64 - 5, &mut patch);
+    patch.extend_from_slice(&[0x11, 0x22, 0x00]);
+    footer(&source, &target, &mut patch);
+
+    assert_eq!(apply(&source, &patch).unwrap(), target);
+
+    let mut wrong_source = source.clone();
+    wrong_source[0] = 1;
+    assert!(matches!(apply(&wrong_source, &patch), Err(PatchError::SourceChecksum { .. })));
+
+    let last = patch.len() - 1;
+    patch[last] ^= 1;
+    assert!(matches!(apply(&source, &patch), Err(PatchError::PatchChecksum { .. })));
+}
+
+#[test]
+fn test_bps()
+{
+    let source = source();
+    let mut target = source[..8].to_vec();
+    target.extend_from_slice(&[0xAB, 0xCD]);
+    target.extend_from_slice(&source[32..36]);
+    let copied = target[8..12].to_vec();
+    target.extend_from_slice(&copied);
+    //Run longer than the distance back, repeats what it just wrote
+    target.extend_from_slice(&[0x21, 0x21, 0x21]);
+
+    let mut patch = b"BPS1".to_vec();
+    number(source.len(), &mut patch);
+    number(target.len(), &mut patch);
+    number(3, &mut patch);
+    patch.extend_from_slice(b"abc");
+    //SourceRead 8
+    number((7 << 2) | BPS_SOURCE_READ, &mut patch);
+    //TargetRead 2
+    number((1 << 2) | BPS_TARGET_READ, &mut patch);
+    patch.extend_from_slice(&[0xAB, 0xCD]);
+    //SourceCopy 4 from +32
+    number((3 << 2) | BPS_SOURCE_COPY, &mut patch);
+    number(32 << 1, &mut patch);
+    //TargetCopy 4 from +8
+    number((3 << 2) | 3, &mut patch);
+    number(8 << 1, &mut patch);
+    //TargetCopy 3 from the last byte written (offset 12 -> 17)
+    number((2 << 2) | 3, &mut patch);
+    number(5 << 1, &mut patch);
+    footer(&source, &target, &mut patch);
+
+    assert_eq!(apply(&source, &patch).unwrap(), target);
+
+    //Footer that claims a different target
+    let mut bad = patch[..patch.len() - CHECKSUM_FOOTER_SIZE].to_vec();
+    footer(&source, &source, &mut bad);
+    assert!(matches!(apply(&source, &bad), Err(PatchError::TargetChecksum { .. })));
+}
@@ -24,11 +24,6 @@ bitflags::bitflags!
 const STAT_MODE:u8 = 0b00000011;
 const STAT_MATCH:u8 = 1 << 2;
 
-const PALETTE_DOT_00:u8 = 0b00000011;
-const PALETTE_DOT_01:u8 = 0b00001100;
-const PALETTE_DOT_10:u8 = 0b00110000;
-const PALETTE_DOT_11:u8 = 0b11000000;
-
 const OBJ_LCD_Y_RAM_OFFSET:u16 = 0;
 const OBJ_LCD_X_RAM_OFFSET:u16 = 1;
 const OBJ_CHR_CODE_OFFSET:u16 = 2;
@@ -53,7 +48,6 @@ pub struct Ppu
 {
     frame_progress: u64,
     buffer: [[u8;SCREEN_HEIGHT];SCREEN_WIDTH],
-    new_frame: bool,
     sprite_buffer: Vec<Sprite>,
     current_x: u8,
     frame_count: u64
@@ -67,7 +61,6 @@ impl Ppu
         {
             frame_progress: 0,
             buffer: [[0; SCREEN_HEIGHT]; SCREEN_WIDTH],
-            new_frame: false,
             sprite_buffer: Default::default(),
             current_x: 0,
             frame_count: 0
@@ -76,13 +69,13 @@ impl Ppu
 
     pub fn execute(&mut self, ram: &mut Ram, hardware_handle: crate::HardwareHandle)
     {
-        let scan_line = (self.frame_progress / (CYCLES_PER_SCANLINE as u64)) as u8;
+        let scan_line = (self.frame_progress / CYCLES_PER_SCANLINE) as u8;
         //4 pixels per cycle
         for _ in 0..4
         {
             self.pixel_update(ram, scan_line);
         }
-        let next_scan_line = (self.frame_progress / (CYCLES_PER_SCANLINE as u64)) as u8;
+        let next_scan_line = (self.frame_progress / CYCLES_PER_SCANLINE) as u8;
 
         // println!("{}", scan_line);
         if next_scan_line == 0 && scan_line != 0
@@ -94,32 +87,43 @@ impl Ppu
         }
     }
 
+    ///STOP halts the LCD controller with the rest of the board, a screen that's on goes blank
+    ///until it wakes and carries on with the frame it was drawing
+    pub fn stop(&mut self, ram: &Ram, hardware_handle: crate::HardwareHandle)
+    {
+        if ram.read(ram::LCDC) & LcdcFlag::LCD_CONTROLLER_OPERATION_ON.bits != 0
+        {
+            hardware_handle.borrow_mut().video_update(&[[0; SCREEN_HEIGHT]; SCREEN_WIDTH], self.frame_count);
+            self.frame_count += 1;
+        }
+    }
+
     fn pixel_update(&mut self, ram: &mut Ram, scan_line: u8)
     {
         let lcd_on = ram.read(ram::LCDC) & LcdcFlag::LCD_CONTROLLER_OPERATION_ON.bits != 0;
-        ram.write(ram::LY, scan_line);
+        ram.write_register(ram::LY, scan_line);
         let status = ram.read(ram::STAT);
 
         if lcd_on
         {
             let y_compare_match = ram.read(ram::LYC) == scan_line;
-            if y_compare_match && status & 0x04 == 0 && status & 0x40 != 0 //If match, fresh match, and interrupt mode set to compare match, fire interrupt
+            if y_compare_match && status & STAT_MATCH == 0 && status & 0x40 != 0 //If match, fresh match, and interrupt mode set to compare match, fire interrupt
             {
                 ram.set_interrupt(ram::InterruptFlag::LCDC)
             }
-            ram.write(ram::STAT, status & !((!y_compare_match as u8) << 2));
+            ram.write_register(ram::STAT, status & !((!y_compare_match as u8) << 2));
         }
 
         //Begin pixel write
         //Mode 0: H-blank (92c), 1: vblank (), 2: vram in use, 3: vram transfer
-        let mode = status & 0b00000011;
+        let mode = status & STAT_MODE;
         if scan_line >= 144 //Handle V-blank
         {
             if mode != 1
             {
                 ram.set_interrupt(ram::InterruptFlag::VB);
                 //Set mode to 1
-                ram.write(ram::STAT, ram.read(ram::STAT) & 0b11111101);
+                ram.write_register(ram::STAT, ram.read(ram::STAT) & 0b11111101);
 
             }
         }
@@ -131,7 +135,7 @@ impl Ppu
                 0..=91 if mode != 2 => //Mode 2
                 {
                     //Set mode to 2
-                    ram.write(ram::STAT, (ram.read(ram::STAT) & 0b11111100) | 0b00000010);
+                    ram.write_register(ram::STAT, (ram.read(ram::STAT) & 0b11111100) | 0b00000010);
                     if status & (1 << 5) != 0
                     {
                         ram.set_interrupt(ram::InterruptFlag::LCDC);
@@ -143,7 +147,7 @@ impl Ppu
                 92..=251 if mode != 3 => //Mode 3
                 {
                     //Set mode to 3
-                    ram.write(ram::STAT, (ram.read(ram::STAT) & 0b11111100) | 0b00000011);
+                    ram.write_register(ram::STAT, (ram.read(ram::STAT) & 0b11111100) | 0b00000011);
                     if lcd_on
                     {
                         let start = self.current_x;
@@ -158,7 +162,7 @@ impl Ppu
                 252..=455 if mode != 0 => //Mode 0
                 {
                     //Set mode to 0
-                    ram.write(ram::STAT, (ram.read(ram::STAT) & 0b11111100) | 0b11111100);
+                    ram.write_register(ram::STAT, (ram.read(ram::STAT) & 0b11111100) | 0b11111100);
                     if status & (1 << 3) != 0
                     {
                         ram.set_interrupt(ram::InterruptFlag::LCDC);
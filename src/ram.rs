@@ -1,6 +1,12 @@
-use std::ops::{RangeInclusive};
+use std::{ops::RangeInclusive, rc::Rc};
 
-use crate::{rom::{self, Rom}};
+use crate::{cheat::RomPatch, mbc::{self, Mbc}, rom::{self, Rom}, rtc::{SystemClock, TimeSourceHandle}};
+
+//----Joypad Registers----
+//P1: Joypad, bits 4 and 5 select which buttons are read back in bits 0-3
+pub const P1:u16 = 0xFF00;
+const P1_SELECT_DIRECTIONS:u8 = 1 << 4;
+const P1_SELECT_BUTTONS:u8 = 1 << 5;
 
 //----Timer Registers----
 //DIV: Divider
@@ -50,14 +56,102 @@ bitflags::bitflags!
     }
 }
 
+//----Joypad Buttons----
+//Directions share P1's lines with the action buttons, in the same order
+bitflags::bitflags!
+{
+    pub struct Button: u8
+    {
+        const RIGHT = 1 << 0;
+        const LEFT = 1 << 1;
+        const UP = 1 << 2;
+        const DOWN = 1 << 3;
+        const A = 1 << 4;
+        const B = 1 << 5;
+        const SELECT = 1 << 6;
+        const START = 1 << 7;
+    }
+}
+
 pub const SC_BOOT_ROM_DISABLE:u16 = 0xFF50;
 
+//----Cartridge Regions----
+pub const CARTRIDGE_ROM:RangeInclusive<u16> = 0x0000..=0x7FFF;
+pub const EXTERNAL_RAM:RangeInclusive<u16> = 0xA000..=0xBFFF;
+
+//----Internal Regions----
+pub const WORK_RAM:RangeInclusive<u16> = 0xC000..=0xDFFF;
+//Mirrors WRAM from 0xC000 up to 0xDDFF
+pub const ECHO_RAM:RangeInclusive<u16> = 0xE000..=0xFDFF;
+const ECHO_RAM_OFFSET:u16 = 0x2000;
+//Reads 0 on the DMG, writes go nowhere
+pub const UNUSABLE:RangeInclusive<u16> = 0xFEA0..=0xFEFF;
+pub const IO_REGISTERS:RangeInclusive<u16> = 0xFF00..=0xFF7F;
+pub const HIGH_RAM:RangeInclusive<u16> = 0xFF80..=0xFFFE;
+
+//----I/O Register Masks----
+//Bits that always read back as 1, and bits the CPU can write, DMG values from the Pan Docs.
+//Write-only sound bits read as 1, unmapped registers read 0xFF and ignore writes.
+#[derive(Clone, Copy)]
+struct IoMask
+{
+    unused: u8,
+    writable: u8
+}
+
+const fn io_mask(address: u16) -> IoMask
+{
+    let (unused, writable) = match address
+    {
+        P1 => (0xC0, 0x30),
+        0xFF01 => (0x00, 0xFF), //SB
+        0xFF02 => (0x7E, 0x81), //SC
+        DIV => (0x00, 0x00),
+        TIMA | TMA => (0x00, 0xFF),
+        TAC => (0xF8, 0x07),
+        IF => (0xE0, 0x1F),
+        0xFF10 => (0x80, 0x7F), //NR10
+        0xFF11 | 0xFF16 => (0x3F, 0xFF), //NR11, NR21
+        0xFF12 | 0xFF17 | 0xFF21 | 0xFF22 | 0xFF24 | 0xFF25 => (0x00, 0xFF), //NR12, NR22, NR42, NR43, NR50, NR51
+        0xFF13 | 0xFF18 | 0xFF1B | 0xFF1D | 0xFF20 => (0xFF, 0xFF), //NR13, NR23, NR31, NR33, NR41
+        0xFF14 | 0xFF19 | 0xFF1E | 0xFF23 => (0xBF, 0xC7), //NR14, NR24, NR34, NR44
+        0xFF1A => (0x7F, 0x80), //NR30
+        0xFF1C => (0x9F, 0x60), //NR32
+        0xFF26 => (0x70, 0x80), //NR52, the channel bits are read-only
+        0xFF30..=0xFF3F => (0x00, 0xFF), //Wave RAM
+        LCDC | SCY | SCX | LYC | DMA | BGP | OBP0 | OBP1 | WY | WX => (0x00, 0xFF),
+        STAT => (0x80, 0x78),
+        LY => (0x00, 0x00),
+        _ => (0xFF, 0x00)
+    };
+    IoMask { unused, writable }
+}
+
+const IO_MASKS:[IoMask;0x80] =
+{
+    let mut masks = [IoMask { unused: 0, writable: 0 }; 0x80];
+    let mut i = 0;
+    while i < masks.len()
+    {
+        masks[i] = io_mask(*IO_REGISTERS.start() + i as u16);
+        i += 1;
+    }
+    masks
+};
+
 #[derive(Clone)]
 pub struct Ram
 {
     mem: [u8;0x10000],
+    boot_rom: Vec<u8>,
     boot_rom_enabled: bool,
     dma: Dma,
+    cartridge: Option<Box<dyn Mbc>>,
+    external_ram_dirty: bool,
+    time_source: TimeSourceHandle,
+    rom_patches: Vec<RomPatch>,
+    buttons: Button,
+    divider_reset: bool
 }
 #[derive(Clone)]
 struct Dma
@@ -75,72 +169,173 @@ impl Ram
         Ram
         {
             mem: [0; 0x10000],
+            boot_rom: rom::BOOT_ROM.to_vec(),
             boot_rom_enabled: true,
-            dma: Dma { delay_start: false, pending_source: 0, source: 0, active: false }
+            dma: Dma { delay_start: false, pending_source: 0, source: 0, active: false },
+            cartridge: None,
+            external_ram_dirty: false,
+            time_source: Rc::new(SystemClock),
+            rom_patches: Vec::new(),
+            buttons: Button::empty(),
+            divider_reset: false
+        }
+    }
+
+    pub fn load_rom(&mut self, rom: &Rom, hardware_handle: crate::HardwareHandle)
+    {
+        self.cartridge = Some(mbc::new(rom, Rc::clone(&self.time_source), hardware_handle));
+    }
+
+    ///Battery backed cartridge contents, None without a cartridge
+    pub fn export_save_data(&mut self) -> Option<Vec<u8>>
+    {
+        self.cartridge.as_mut().map(|x| x.save_data())
+    }
+
+    pub fn import_save_data(&mut self, data: &[u8])
+    {
+        if let Some(cartridge) = &mut self.cartridge
+        {
+            cartridge.load_save_data(data);
         }
     }
 
-    pub fn load_rom(&mut self, rom: &Rom)
+    ///Returns true once for each stretch of writes that changed cartridge RAM
+    pub fn take_external_ram_dirty(&mut self) -> bool
+    {
+        std::mem::take(&mut self.external_ram_dirty)
+    }
+
+    ///Clock for cartridges with an RTC, takes effect on the next ROM load
+    pub fn set_time_source(&mut self, time_source: TimeSourceHandle)
+    {
+        self.time_source = time_source;
+    }
+
+    ///Maps a boot ROM over the start of the cartridge until 0xFF50 is written
+    pub fn set_boot_rom(&mut self, bytes: Vec<u8>)
     {
-        self.mem[0x0000..=0x3FFF].copy_from_slice(&rom.bytes[0x0000..=0x3FFF]);
+        self.boot_rom = bytes;
+        self.boot_rom_enabled = true;
     }
 
+    ///Game Genie substitutions applied to cartridge ROM reads
+    pub fn set_rom_patches(&mut self, patches: Vec<RomPatch>)
+    {
+        self.rom_patches = patches;
+    }
+
+    ///Writes as the CPU does, read-only bits and registers keep their value
     pub fn write(&mut self, address: u16, data: u8)
     {
         match address
         {
             //Boot rom disable
             SC_BOOT_ROM_DISABLE => {self.boot_rom_enabled = false;},
-            DMA =>
+            //Selecting a group with a button held pulls its line low too
+            P1 =>
             {
-                if data < 0xF1
-                {
-                    self.dma.pending_source = data;
-                    self.dma.delay_start = true;
-                }
+                let lines = self.joypad_lines();
+                self.write_masked(P1, data);
+                self.joypad_edge(lines);
+                return;
+            },
+            //Any write clears the whole divider
+            DIV =>
+            {
+                self.mem[DIV as usize] = 0;
+                self.divider_reset = true;
+                return;
+            },
+            DMA if data < 0xF1 =>
+            {
+                self.dma.pending_source = data;
+                self.dma.delay_start = true;
             }
             _ => {}
         }
 
+        //Without a cartridge inserted its regions behave as flat memory
+        if let Some(cartridge) = &mut self.cartridge
+        {
+            if CARTRIDGE_ROM.contains(&address)
+            {
+                cartridge.write_rom(address, data);
+                return;
+            }
+            if EXTERNAL_RAM.contains(&address)
+            {
+                //Writes with RAM disabled, or that store what was already there, have nothing to save
+                let before = cartridge.read_ram(address);
+                cartridge.write_ram(address, data);
+                self.external_ram_dirty |= cartridge.read_ram(address) != before;
+                return;
+            }
+        }
+
+        match address
+        {
+            _ if ECHO_RAM.contains(&address) => {self.mem[(address - ECHO_RAM_OFFSET) as usize] = data;},
+            _ if UNUSABLE.contains(&address) => {},
+            _ if IO_REGISTERS.contains(&address) => {self.write_masked(address, data);},
+            _ => {self.mem[address as usize] = data;}
+        }
+    }
+
+    //Keeps the bits of an I/O register the CPU can't write
+    fn write_masked(&mut self, address: u16, data: u8)
+    {
+        let writable = IO_MASKS[(address - IO_REGISTERS.start()) as usize].writable;
+        let register = &mut self.mem[address as usize];
+        *register = *register & !writable | data & writable;
+    }
+
+    ///Sets an I/O register the way the hardware behind it does, past the CPU's write masks
+    pub(crate) fn write_register(&mut self, address: u16, data: u8)
+    {
         self.mem[address as usize] = data;
     }
 
+    ///True once after each CPU write to DIV
+    pub(crate) fn take_divider_reset(&mut self) -> bool
+    {
+        std::mem::take(&mut self.divider_reset)
+    }
+
     pub fn write_rp(&mut self, msh: u8, lsh: u8, data: u8)
     {
         self.write(u16::from_le_bytes([lsh, msh]), data);
     }
 
+    ///Reads as the CPU does, with unused I/O bits set
     pub fn read(&self, address: u16) -> u8
     {
         match address
         {
-            0x0000..=0x00FF =>
+            0x0000..=0x00FF | 0x0200..=0x08FF if self.boot_rom_enabled && (address as usize) < self.boot_rom.len() => self.boot_rom[address as usize],
+            _ => match &self.cartridge
             {
-                if self.boot_rom_enabled
+                Some(cartridge) if CARTRIDGE_ROM.contains(&address) =>
                 {
-                    return rom::BOOT_ROM[address as usize];
-                }
-                self.mem[address as usize]
-            },
-            _ => self.mem[address as usize]
+                    let data = cartridge.read_rom(address);
+                    //Compare bytes let a code only hit the bank it was made for
+                    self.rom_patches.iter().find_map(|x| x.apply(address, data)).unwrap_or(data)
+                },
+                Some(cartridge) if EXTERNAL_RAM.contains(&address) => cartridge.read_ram(address),
+                _ if ECHO_RAM.contains(&address) => self.mem[(address - ECHO_RAM_OFFSET) as usize],
+                _ if UNUSABLE.contains(&address) => 0x00,
+                _ if address == P1 => 0xC0 | self.mem[P1 as usize] & (P1_SELECT_DIRECTIONS | P1_SELECT_BUTTONS) | self.joypad_lines(),
+                _ if IO_REGISTERS.contains(&address) => IO_MASKS[(address - IO_REGISTERS.start()) as usize].unused | self.mem[address as usize],
+                _ => self.mem[address as usize]
+            }
         }
     }
 
-    pub fn get_ref(&mut self, address: u16) -> &mut u8
-    {
-        &mut self.mem[address as usize]
-    }
-
     pub fn read_rp(&self, msh: u8, lsh: u8) -> u8
     {
         self.read(u16::from_le_bytes([lsh, msh]))
     }
 
-    pub fn get_rp_ref(&mut self, msh: u8, lsh: u8) -> &mut u8
-    {
-        self.get_ref(u16::from_le_bytes([lsh, msh]))
-    }
-
     pub fn execute(&mut self)
     {
         self.dma_update();
@@ -161,7 +356,8 @@ impl Ram
         if self.dma.source != 0 && (self.dma.source & 0xFF) < 160
         {
             self.dma.active = true;
-            self.write(self.dma.source & 0xFF, self.read(self.dma.source));
+            //DMA copies into OAM itself rather than through the CPU's view of it
+            self.mem[(*OAM.start() | self.dma.source & 0xFF) as usize] = self.read(self.dma.source);
             self.dma.source += 1;
         }
         else
@@ -170,6 +366,38 @@ impl Ram
         }
     }
 
+    ///Buttons currently held, a line going low requests the joypad interrupt
+    pub fn set_buttons(&mut self, buttons: Button)
+    {
+        let lines = self.joypad_lines();
+        self.buttons = buttons;
+        self.joypad_edge(lines);
+    }
+
+    //P1 bits 0-3, a line is low while a held button in a selected group is on it
+    fn joypad_lines(&self) -> u8
+    {
+        let select = self.mem[P1 as usize];
+        let mut pressed = 0;
+        if select & P1_SELECT_DIRECTIONS == 0
+        {
+            pressed |= self.buttons.bits & 0x0F;
+        }
+        if select & P1_SELECT_BUTTONS == 0
+        {
+            pressed |= self.buttons.bits >> 4;
+        }
+        !pressed & 0x0F
+    }
+
+    fn joypad_edge(&mut self, lines_before: u8)
+    {
+        if lines_before & !self.joypad_lines() != 0
+        {
+            self.set_interrupt(InterruptFlag::P1X_NEG_EDGE);
+        }
+    }
+
     //Interrupts
     //bit 0: vblank
     //bit 1: LCDC (STAT References)
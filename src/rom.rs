@@ -1,5 +1,4 @@
-use core::panic;
-use std::{fs::File, io::Read};
+use std::io::Read;
 
 pub const BOOT_ROM:[u8;256] =
 [
@@ -21,74 +20,279 @@ pub const BOOT_ROM:[u8;256] =
     0xF5,0x06,0x19,0x78,0x86,0x23,0x05,0x20,0xFB,0x86,0x20,0xFE,0x3E,0x01,0xE0,0x50
 ];
 
-#[derive(Clone, PartialEq, Debug)]
+#[derive(Clone, Copy, PartialEq, Debug)]
 pub enum MBCModel {MbcNone, Mbc1_16_8, Mbc2, Mbc3, Mbc5}
 
-#[derive(Clone)]
-pub struct Rom
+//The header ends at 0x14F, anything shorter can't be parsed
+const HEADER_END:usize = 0x150;
+
+//----Header Check Locations----
+pub(crate) const LOGO_START:usize = 0x104;
+pub(crate) const HEADER_CHECKSUM:usize = 0x14D;
+pub(crate) const GLOBAL_CHECKSUM:usize = 0x14E;
+
+//Compared byte for byte by the boot ROM, a mismatch locks up the console
+pub const NINTENDO_LOGO:[u8;48] =
+[
+    0xCE,0xED,0x66,0x66,0xCC,0x0D,0x00,0x0B,0x03,0x73,0x00,0x83,0x00,0x0C,0x00,0x0D,
+    0x00,0x08,0x11,0x1F,0x88,0x89,0x00,0x0E,0xDC,0xCC,0x6E,0xE6,0xDD,0xDD,0xD9,0x99,
+    0xBB,0xBB,0x67,0x63,0x6E,0x0E,0xEC,0xCC,0xDD,0xDC,0x99,0x9F,0xBB,0xB9,0x33,0x3E
+];
+
+///What to do with a ROM that fails header validation
+#[derive(Clone, Copy, PartialEq, Debug, Default)]
+pub enum HeaderPolicy
 {
-    pub bytes: Vec<u8>,
+    ///Refuse to load the ROM if any check fails
+    Reject,
+    ///Load anyway and report the failed checks to the frontend
+    #[default]
+    Warn,
+    ///Behave like the boot ROM, a bad logo or header checksum hangs the console.
+    ///The global checksum is never checked by hardware.
+    LockUp
+}
+
+///Results of the header checks, the stored values are kept so tools can show both
+#[derive(Clone, PartialEq, Debug, Default)]
+pub struct HeaderValidation
+{
+    pub logo_valid: bool,
+    pub header_checksum: u8,
+    pub computed_header_checksum: u8,
+    pub global_checksum: u16,
+    pub computed_global_checksum: u16
+}
+
+impl HeaderValidation
+{
+    pub fn new(bytes: &[u8]) -> HeaderValidation
+    {
+        let computed_header_checksum = bytes[0x134..HEADER_CHECKSUM].iter()
+            .fold(0_u8, |x, byte| x.wrapping_sub(*byte).wrapping_sub(1));
+        let computed_global_checksum = bytes.iter().enumerate()
+            .filter(|(i, _)| *i != GLOBAL_CHECKSUM && *i != GLOBAL_CHECKSUM + 1)
+            .fold(0_u16, |x, (_, byte)| x.wrapping_add(*byte as u16));
+        HeaderValidation
+        {
+            logo_valid: bytes[LOGO_START..LOGO_START + NINTENDO_LOGO.len()] == NINTENDO_LOGO,
+            header_checksum: bytes[HEADER_CHECKSUM],
+            computed_header_checksum,
+            global_checksum: u16::from_be_bytes([bytes[GLOBAL_CHECKSUM], bytes[GLOBAL_CHECKSUM + 1]]),
+            computed_global_checksum
+        }
+    }
+
+    pub fn header_checksum_valid(&self) -> bool
+    {
+        self.header_checksum == self.computed_header_checksum
+    }
+
+    pub fn global_checksum_valid(&self) -> bool
+    {
+        self.global_checksum == self.computed_global_checksum
+    }
+
+    ///Passes the checks the boot ROM does
+    pub fn is_bootable(&self) -> bool
+    {
+        self.logo_valid && self.header_checksum_valid()
+    }
+
+    pub fn is_valid(&self) -> bool
+    {
+        self.is_bootable() && self.global_checksum_valid()
+    }
+}
+
+impl std::fmt::Display for HeaderValidation
+{
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result
+    {
+        let mut failures = Vec::new();
+        if !self.logo_valid
+        {
+            failures.push(String::from("logo mismatch"));
+        }
+        if !self.header_checksum_valid()
+        {
+            failures.push(format!("header checksum 0x{:02X}, expected 0x{:02X}", self.header_checksum, self.computed_header_checksum));
+        }
+        if !self.global_checksum_valid()
+        {
+            failures.push(format!("global checksum 0x{:04X}, expected 0x{:04X}", self.global_checksum, self.computed_global_checksum));
+        }
+        if failures.is_empty()
+        {
+            write!(f, "header valid")
+        }
+        else
+        {
+            write!(f, "{}", failures.join(", "))
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum RomError
+{
+    ///The file ends before the cartridge header does
+    Truncated(usize),
+    ///Cartridge type byte (0x147) names hardware that isn't emulated
+    UnsupportedMapper(u8),
+    ///ROM size byte (0x148) isn't a known size code
+    InvalidRomSize(u8),
+    ///RAM size byte (0x149) isn't a known size code
+    InvalidRamSize(u8),
+    ///A header check failed under HeaderPolicy::Reject
+    InvalidHeader(HeaderValidation),
+    ///A patch couldn't be applied before the header was parsed
+    Patch(crate::patch::PatchError),
+    Io(std::io::Error)
+}
+
+impl std::fmt::Display for RomError
+{
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result
+    {
+        match self
+        {
+            RomError::Truncated(len) => write!(f, "ROM is {} bytes, too short to contain a header", len),
+            RomError::UnsupportedMapper(x) => write!(f, "Unsupported cartridge type 0x{:02X}", x),
+            RomError::InvalidRomSize(x) => write!(f, "Invalid ROM size code 0x{:02X}", x),
+            RomError::InvalidRamSize(x) => write!(f, "Invalid RAM size code 0x{:02X}", x),
+            RomError::InvalidHeader(x) => write!(f, "Invalid header: {}", x),
+            RomError::Patch(x) => write!(f, "Failed to patch ROM: {}", x),
+            RomError::Io(x) => write!(f, "Failed to read ROM: {}", x)
+        }
+    }
+}
+
+impl std::error::Error for RomError
+{
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)>
+    {
+        match self
+        {
+            RomError::Patch(x) => Some(x),
+            RomError::Io(x) => Some(x),
+            _ => None
+        }
+    }
+}
+
+impl From<std::io::Error> for RomError
+{
+    fn from(x: std::io::Error) -> Self { RomError::Io(x) }
+}
+
+impl From<crate::patch::PatchError> for RomError
+{
+    fn from(x: crate::patch::PatchError) -> Self { RomError::Patch(x) }
+}
+
+//----Header Fields----
+pub(crate) const TITLE:usize = 0x134;
+const MANUFACTURER_CODE:usize = 0x13F;
+const CGB_FLAG:usize = 0x143;
+const NEW_LICENSEE_CODE:usize = 0x144;
+const SGB_FLAG:usize = 0x146;
+const CARTRIDGE_TYPE:usize = 0x147;
+const ROM_SIZE:usize = 0x148;
+const RAM_SIZE:usize = 0x149;
+const DESTINATION_CODE:usize = 0x14A;
+const OLD_LICENSEE_CODE:usize = 0x14B;
+const MASK_ROM_VERSION:usize = 0x14C;
+
+//Old licensee value that means the new licensee code is used instead
+const USE_NEW_LICENSEE:u8 = 0x33;
+
+///Everything stored in the cartridge header (0x0134-0x014F), raw and decoded
+#[derive(Clone, PartialEq, Debug)]
+pub struct CartridgeHeader
+{
+    pub title: String,
+    ///Only present on later cartridges, where it takes the last 4 title bytes
+    pub manufacturer_code: Option<String>,
+    pub cgb_flag: u8,
+    pub new_licensee_code: String,
+    pub sgb_flag: u8,
+    pub cartridge_type: u8,
     pub mbc_model: MBCModel,
     pub rom_size: usize,
     pub ram_size: usize,
     pub has_battery: bool,
     pub has_rtc: bool,
     pub has_rumble: bool,
-    pub title: String
+    pub destination_code: u8,
+    pub old_licensee_code: u8,
+    pub mask_rom_version: u8,
+    pub header_checksum: u8,
+    pub global_checksum: u16
 }
 
-impl Rom
+impl CartridgeHeader
 {
-    pub fn new(mut f: File, frontend: crate::HardwareHandle) -> Rom
+    pub fn new(bytes: &[u8]) -> Result<CartridgeHeader, RomError>
     {
-        let mut bytes = Vec::new();
-        let _result = f.read_to_end(&mut bytes);
-        let mut rom = Rom
-        {
-            bytes,
-            mbc_model: MBCModel::MbcNone,
-            rom_size: 0,
-            ram_size: 0,
-            has_battery: false,
-            has_rtc: false,
-            has_rumble: false,
-            title: Default::default()
+        if bytes.len() < HEADER_END
+        {
+            return Err(RomError::Truncated(bytes.len()));
+        }
+
+        //Older titles can run into this area, so only take codes that look like one
+        let code = &bytes[MANUFACTURER_CODE..CGB_FLAG];
+        let manufacturer_code = if bytes[CGB_FLAG] & 0x80 != 0 && code.iter().all(|x| x.is_ascii_uppercase() || x.is_ascii_digit())
+        {
+            Some(code.iter().map(|x| *x as char).collect())
+        }
+        else
+        {
+            None
         };
 
+        //Newer headers take the end of the title for the manufacturer code and CGB flag
+        let title_length = match (&manufacturer_code, bytes[CGB_FLAG] & 0x80 != 0)
+        {
+            (Some(_), _) => 11,
+            (None, true) => 15,
+            (None, false) => 16
+        };
         let mut ttl:Vec<u8> = Vec::<u8>::new();
-        for i in 0..16
+        for i in 0..title_length
         {
-            let x = rom.bytes[0x134 + i];
+            let x = bytes[TITLE + i];
             if x <= 127 && !x.is_ascii_control()
             {
                 ttl.push(x);
             }
         }
-        rom.title = match String::from_utf8(ttl)
+        let title = match String::from_utf8(ttl)
         {
             Ok(res) => res,
             Err(_) => String::from("Unknown")
         };
 
-        rom.mbc_model = match rom.bytes[0x147]
+        let (mut has_battery, mut has_rtc, mut has_rumble) = (false, false, false);
+        let mbc_model = match bytes[CARTRIDGE_TYPE]
         {
             0x00 | 0x08 => MBCModel::MbcNone,
             0x01 | 0x02 => MBCModel::Mbc1_16_8,
-            0x03 => {rom.has_battery = true; MBCModel::Mbc1_16_8},
+            0x03 => {has_battery = true; MBCModel::Mbc1_16_8},
             0x05 => MBCModel::Mbc2,
-            0x06 => {rom.has_battery = true; MBCModel::Mbc2},
-            0x09 => {rom.has_battery = true; MBCModel::MbcNone},
-            0x0F | 0x10 => {rom.has_battery = true; rom.has_rtc = true; MBCModel::Mbc3},
+            0x06 => {has_battery = true; MBCModel::Mbc2},
+            0x09 => {has_battery = true; MBCModel::MbcNone},
+            0x0F | 0x10 => {has_battery = true; has_rtc = true; MBCModel::Mbc3},
             0x11 | 0x12 => {MBCModel::Mbc3},
-            0x13 => {rom.has_battery = true; MBCModel::Mbc3},
+            0x13 => {has_battery = true; MBCModel::Mbc3},
             0x19 | 0x1A => MBCModel::Mbc5,
-            0x1B => {rom.has_battery = true; MBCModel::Mbc5},
-            0x1C | 0x1D => {rom.has_rumble = true; MBCModel::Mbc5},
-            0x1E => {rom.has_battery = true; rom.has_rumble = true; MBCModel::Mbc5},
-            _ => panic!("Unsupported hardware in ROM")
+            0x1B => {has_battery = true; MBCModel::Mbc5},
+            0x1C | 0x1D => {has_rumble = true; MBCModel::Mbc5},
+            0x1E => {has_battery = true; has_rumble = true; MBCModel::Mbc5},
+            x => return Err(RomError::UnsupportedMapper(x))
         };
 
-        rom.rom_size = match rom.bytes[0x148] //Values are address count (*8 for size)
+        let rom_size = match bytes[ROM_SIZE] //Values are address count (*8 for size)
         {
             0x00 => 32768, /* 256 Kbit */
             0x01 => 65536, /* 512 Kbit */
@@ -97,29 +301,110 @@ impl Rom
             0x04 => 524288, /* 4 Mbit */
             0x05 => 1048576, /* 8 Mbit */
             0x06 => 2097152, /* 16 Mbit */
+            0x07 => 4194304, /* 32 Mbit */
+            0x08 => 8388608, /* 64 Mbit */
             0x52 => 1179648, /* 9 Mbit */
             0x53 => 1310720, /* 10 Mbit */
             0x54 => 1572864, /* 12 Mbit */
-            _ => panic!("Invalid rom size")
+            x => return Err(RomError::InvalidRomSize(x))
         };
 
-        rom.ram_size = match rom.bytes[0x149] //Values are address count (*8 for size)
+        let ram_size = match bytes[RAM_SIZE] //Values are address count (*8 for size)
         {
-            _ if rom.mbc_model == MBCModel::Mbc2 => 512,
+            _ if mbc_model == MBCModel::Mbc2 => 512,
             0 => 0,
             1 => 2048,
             2 => 8192,
             3 => 32768,
             4 => 131072,
-            _ => panic!("Invalid ram size")
+            5 => 65536,
+            x => return Err(RomError::InvalidRamSize(x))
         };
 
-        //Check checksum here
+        Ok(CartridgeHeader
+        {
+            title,
+            manufacturer_code,
+            cgb_flag: bytes[CGB_FLAG],
+            new_licensee_code: bytes[NEW_LICENSEE_CODE..SGB_FLAG].iter().map(|x| *x as char).collect(),
+            sgb_flag: bytes[SGB_FLAG],
+            cartridge_type: bytes[CARTRIDGE_TYPE],
+            mbc_model,
+            rom_size,
+            ram_size,
+            has_battery,
+            has_rtc,
+            has_rumble,
+            destination_code: bytes[DESTINATION_CODE],
+            old_licensee_code: bytes[OLD_LICENSEE_CODE],
+            mask_rom_version: bytes[MASK_ROM_VERSION],
+            header_checksum: bytes[HEADER_CHECKSUM],
+            global_checksum: u16::from_be_bytes([bytes[GLOBAL_CHECKSUM], bytes[GLOBAL_CHECKSUM + 1]])
+        })
+    }
+
+    ///Game Boy Color enhanced (0x80) or Color only (0xC0)
+    pub fn supports_cgb(&self) -> bool
+    {
+        self.cgb_flag & 0x80 != 0
+    }
+
+    pub fn cgb_only(&self) -> bool
+    {
+        self.cgb_flag == 0xC0
+    }
+
+    ///The SGB ignores its flag unless the old licensee code defers to the new one
+    pub fn supports_sgb(&self) -> bool
+    {
+        self.sgb_flag == 0x03 && self.old_licensee_code == USE_NEW_LICENSEE
+    }
+
+    pub fn is_japanese(&self) -> bool
+    {
+        self.destination_code == 0x00
+    }
 
-        //Print ROM details
-        println!("{:?}", rom);
-        frontend.borrow_mut().receive_rom_information(&rom.title);
-        rom
+    ///Licensee as shown by most tools, the two character new code when in use
+    pub fn licensee_code(&self) -> String
+    {
+        if self.old_licensee_code == USE_NEW_LICENSEE
+        {
+            self.new_licensee_code.clone()
+        }
+        else
+        {
+            format!("{:02X}", self.old_licensee_code)
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct Rom
+{
+    pub bytes: Vec<u8>,
+    pub header: CartridgeHeader,
+    pub validation: HeaderValidation
+}
+
+impl Rom
+{
+    pub fn new(bytes: Vec<u8>) -> Result<Rom, RomError>
+    {
+        Ok(Rom
+        {
+            header: CartridgeHeader::new(&bytes)?,
+            validation: HeaderValidation::new(&bytes),
+            bytes
+        })
+    }
+
+    ///Reads the whole image from a file, socket or any other reader
+    pub fn from_reader<R: Read>(mut reader: R) -> Result<Rom, RomError>
+    {
+        let mut bytes = Vec::new();
+        reader.read_to_end(&mut bytes)?;
+        Rom::new(bytes)
     }
 }
 
@@ -127,6 +412,6 @@ impl std::fmt::Debug for Rom
 {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result
     {
-        f.debug_struct("Rom").field("mbc_model", &self.mbc_model).field("rom_size", &self.rom_size).field("ram_size", &self.ram_size).field("has_battery", &self.has_battery).field("has_rtc", &self.has_rtc).field("has_rumble", &self.has_rumble).field("title", &self.title).finish()
+        f.debug_struct("Rom").field("header", &self.header).field("validation", &self.validation).finish()
     }
-}
\ No newline at end of file
+}
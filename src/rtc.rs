@@ -0,0 +1,238 @@
+use std::{cell::Cell, rc::Rc};
+
+pub type TimeSourceHandle = Rc<dyn TimeSource>;
+
+///Wall clock used by cartridge real-time clocks, in whole seconds
+pub trait TimeSource
+{
+    fn now(&self) -> u64;
+}
+
+///Reads the host's system time
+#[derive(Default)]
+pub struct SystemClock;
+
+impl TimeSource for SystemClock
+{
+    fn now(&self) -> u64
+    {
+        std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map(|x| x.as_secs())
+            .unwrap_or(0)
+    }
+}
+
+///Clock that only moves when told to, for deterministic tests and replays
+#[derive(Default)]
+pub struct ManualClock
+{
+    seconds: Cell<u64>
+}
+
+impl ManualClock
+{
+    pub fn new(seconds: u64) -> ManualClock
+    {
+        ManualClock { seconds: Cell::new(seconds) }
+    }
+
+    pub fn set(&self, seconds: u64)
+    {
+        self.seconds.set(seconds);
+    }
+
+    pub fn advance(&self, seconds: u64)
+    {
+        self.seconds.set(self.seconds.get() + seconds);
+    }
+}
+
+impl TimeSource for ManualClock
+{
+    fn now(&self) -> u64
+    {
+        self.seconds.get()
+    }
+}
+
+//----RTC Registers (MBC3 RAM bank select values)----
+pub const RTC_S:u8 = 0x08;
+pub const RTC_M:u8 = 0x09;
+pub const RTC_H:u8 = 0x0A;
+pub const RTC_DL:u8 = 0x0B;
+pub const RTC_DH:u8 = 0x0C;
+
+//RTC_DH bits
+const DH_DAY_HIGH:u8 = 1 << 0;
+const DH_HALT:u8 = 1 << 6;
+const DH_DAY_CARRY:u8 = 1 << 7;
+
+const SECONDS_PER_DAY:u64 = 86400;
+const DAY_COUNTER_LIMIT:u64 = 512;
+
+//Save file footer used by VBA-M, BGB, mGBA and others. Live registers, latched registers,
+//then the time of the save, every field little endian. Older files use a 32 bit timestamp.
+pub const SAVE_FOOTER_SIZE:usize = 48;
+pub const SAVE_FOOTER_SIZE_32:usize = 44;
+
+///MBC3 clock counter. Time advances from the time source, and the CPU only
+///ever sees the copy taken by the last latch.
+#[derive(Clone)]
+pub struct Rtc
+{
+    time_source: TimeSourceHandle,
+    last_update: u64,
+    seconds: u8,
+    minutes: u8,
+    hours: u8,
+    days: u16,
+    halted: bool,
+    day_carry: bool,
+    latched: [u8; 5]
+}
+
+impl Rtc
+{
+    pub fn new(time_source: TimeSourceHandle) -> Rtc
+    {
+        let last_update = time_source.now();
+        Rtc
+        {
+            time_source,
+            last_update,
+            seconds: 0,
+            minutes: 0,
+            hours: 0,
+            days: 0,
+            halted: false,
+            day_carry: false,
+            latched: [0; 5]
+        }
+    }
+
+    ///Moves the live counters forward by however long has passed on the time source
+    fn update(&mut self)
+    {
+        let now = self.time_source.now();
+        let elapsed = now.saturating_sub(self.last_update);
+        self.last_update = now;
+        if self.halted || elapsed == 0
+        {
+            return;
+        }
+
+        let total = self.days as u64 * SECONDS_PER_DAY
+            + self.hours as u64 * 3600
+            + self.minutes as u64 * 60
+            + self.seconds as u64
+            + elapsed;
+        let days = total / SECONDS_PER_DAY;
+        if days >= DAY_COUNTER_LIMIT
+        {
+            self.day_carry = true; //Sticky until the game clears it
+        }
+        self.days = (days % DAY_COUNTER_LIMIT) as u16;
+        self.hours = ((total % SECONDS_PER_DAY) / 3600) as u8;
+        self.minutes = ((total % 3600) / 60) as u8;
+        self.seconds = (total % 60) as u8;
+    }
+
+    fn registers(&self) -> [u8; 5]
+    {
+        [self.seconds, self.minutes, self.hours, self.days as u8, self.day_high()]
+    }
+
+    fn day_high(&self) -> u8
+    {
+        let mut dh = (self.days >> 8) as u8 & DH_DAY_HIGH;
+        if self.halted
+        {
+            dh |= DH_HALT;
+        }
+        if self.day_carry
+        {
+            dh |= DH_DAY_CARRY;
+        }
+        dh
+    }
+
+    ///Copies the live counters into the registers visible to the CPU
+    pub fn latch(&mut self)
+    {
+        self.update();
+        self.latched = self.registers();
+    }
+
+    pub fn read(&self, register: u8) -> u8
+    {
+        match register
+        {
+            RTC_S..=RTC_DH => self.latched[(register - RTC_S) as usize],
+            _ => 0xFF
+        }
+    }
+
+    pub fn write(&mut self, register: u8, data: u8)
+    {
+        self.update();
+        match register
+        {
+            RTC_S => {self.seconds = data & 0b00111111;},
+            RTC_M => {self.minutes = data & 0b00111111;},
+            RTC_H => {self.hours = data & 0b00011111;},
+            RTC_DL => {self.days = (self.days & 0x100) | data as u16;},
+            RTC_DH =>
+            {
+                self.days = (self.days & 0xFF) | (((data & DH_DAY_HIGH) as u16) << 8);
+                self.halted = data & DH_HALT != 0;
+                self.day_carry = data & DH_DAY_CARRY != 0;
+            },
+            _ => {}
+        }
+        //The written register shows up in the latched copy right away on hardware,
+        //the others keep what the last latch saw
+        if (RTC_S..=RTC_DH).contains(&register)
+        {
+            let index = (register - RTC_S) as usize;
+            self.latched[index] = self.registers()[index];
+        }
+    }
+
+    ///Clock state in the common save footer layout
+    pub fn save(&mut self) -> [u8; SAVE_FOOTER_SIZE]
+    {
+        self.update();
+        let mut footer = [0; SAVE_FOOTER_SIZE];
+        let fields = self.registers().into_iter().chain(self.latched);
+        for (i, x) in fields.enumerate()
+        {
+            footer[i * 4..i * 4 + 4].copy_from_slice(&(x as u32).to_le_bytes());
+        }
+        footer[40..48].copy_from_slice(&self.last_update.to_le_bytes());
+        footer
+    }
+
+    ///Restores a save footer, time spent since the save is caught up on the next access
+    pub fn load(&mut self, footer: &[u8])
+    {
+        if footer.len() != SAVE_FOOTER_SIZE && footer.len() != SAVE_FOOTER_SIZE_32
+        {
+            return;
+        }
+        let field = |i: usize| footer[i * 4];
+        self.seconds = field(0);
+        self.minutes = field(1);
+        self.hours = field(2);
+        self.days = field(3) as u16 | (((field(4) & DH_DAY_HIGH) as u16) << 8);
+        self.halted = field(4) & DH_HALT != 0;
+        self.day_carry = field(4) & DH_DAY_CARRY != 0;
+        for i in 0..5
+        {
+            self.latched[i] = field(5 + i);
+        }
+        let mut timestamp = [0; 8];
+        timestamp[..footer.len() - 40].copy_from_slice(&footer[40..]);
+        self.last_update = u64::from_le_bytes(timestamp);
+    }
+}
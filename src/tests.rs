@@ -1,7 +1,1040 @@
+use std::path::PathBuf;
+use crate::{mainboard::Mainboard, ppu, rom, HeaderPolicy, RomError};
+
 #[test]
 fn ram_write()
 {
     // let mut ram = Ram::new();
     // ram.write(0x0420, 69);
     // assert_eq!(ram.read(0x0420), 69);
-}
\ No newline at end of file
+}
+
+///Records the callbacks the core sends to the host
+#[derive(Default)]
+pub struct TestFrontend
+{
+    pub rumble_events: Vec<bool>,
+    pub save_updates: u32,
+    pub save_failures: Vec<std::io::ErrorKind>,
+    pub lock_ups: Vec<(u16, u8)>,
+    pub rom_titles: Vec<String>,
+    pub cartridge_headers: u32,
+    ///locked_up flag of each failed header check
+    pub header_failures: Vec<bool>,
+    pub trace_failures: Vec<std::io::ErrorKind>
+}
+
+impl crate::Frontend for TestFrontend
+{
+    fn receive_rom_information(&mut self, title: &str)
+    {
+        self.rom_titles.push(title.to_string());
+    }
+    fn receive_cartridge_header(&mut self, _header: &crate::CartridgeHeader)
+    {
+        self.cartridge_headers += 1;
+    }
+    fn header_check_failed(&mut self, _validation: &crate::HeaderValidation, locked_up: bool)
+    {
+        self.header_failures.push(locked_up);
+    }
+    fn event_poll(&mut self) -> bool { true }
+    fn video_update(&mut self, _buffer: &[[u8; ppu::SCREEN_HEIGHT];ppu::SCREEN_WIDTH], _frame_count: u64) {}
+    fn rumble_update(&mut self, active: bool)
+    {
+        self.rumble_events.push(active);
+    }
+    fn save_ram_updated(&mut self)
+    {
+        self.save_updates += 1;
+    }
+    fn save_failed(&mut self, error: &std::io::Error)
+    {
+        self.save_failures.push(error.kind());
+    }
+    fn cpu_locked_up(&mut self, address: u16, opcode: u8)
+    {
+        self.lock_ups.push((address, opcode));
+    }
+    fn trace_failed(&mut self, error: &std::io::Error)
+    {
+        self.trace_failures.push(error.kind());
+    }
+}
+
+///Lets a test keep hold of the TestFrontend it hands to a Mainboard
+pub struct SharedFrontend(pub std::rc::Rc<std::cell::RefCell<TestFrontend>>);
+
+impl crate::Frontend for SharedFrontend
+{
+    fn receive_rom_information(&mut self, title: &str)
+    {
+        self.0.borrow_mut().receive_rom_information(title);
+    }
+    fn receive_cartridge_header(&mut self, header: &crate::CartridgeHeader)
+    {
+        self.0.borrow_mut().receive_cartridge_header(header);
+    }
+    fn header_check_failed(&mut self, validation: &crate::HeaderValidation, locked_up: bool)
+    {
+        self.0.borrow_mut().header_check_failed(validation, locked_up);
+    }
+    fn event_poll(&mut self) -> bool { true }
+    fn video_update(&mut self, _buffer: &[[u8; ppu::SCREEN_HEIGHT];ppu::SCREEN_WIDTH], _frame_count: u64) {}
+    fn save_ram_updated(&mut self)
+    {
+        self.0.borrow_mut().save_ram_updated();
+    }
+    fn save_failed(&mut self, error: &std::io::Error)
+    {
+        self.0.borrow_mut().save_failed(error);
+    }
+    fn cpu_locked_up(&mut self, address: u16, opcode: u8)
+    {
+        self.0.borrow_mut().cpu_locked_up(address, opcode);
+    }
+    fn trace_failed(&mut self, error: &std::io::Error)
+    {
+        self.0.borrow_mut().trace_failed(error);
+    }
+}
+
+///Write sink a test can read back after handing it to the emulator
+#[derive(Clone, Default)]
+pub struct SharedBuffer(pub std::rc::Rc<std::cell::RefCell<Vec<u8>>>);
+
+impl SharedBuffer
+{
+    pub fn lines(&self) -> Vec<String>
+    {
+        String::from_utf8(self.0.borrow().clone()).unwrap().lines().map(String::from).collect()
+    }
+}
+
+impl std::io::Write for SharedBuffer
+{
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize>
+    {
+        self.0.borrow_mut().extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> { Ok(()) }
+}
+
+///Minimal cartridge image with a valid header for the given type, ROM and RAM size codes
+pub fn test_cartridge(cartridge_type: u8, rom_size: u8, ram_size: u8) -> Vec<u8>
+{
+    let mut bytes = vec![0; 0x8000 << rom_size];
+    bytes[0x134..0x138].copy_from_slice(b"TEST");
+    bytes[0x147] = cartridge_type;
+    bytes[0x148] = rom_size;
+    bytes[0x149] = ram_size;
+    bytes[0x104..0x134].copy_from_slice(&rom::NINTENDO_LOGO);
+    fix_checksums(&mut bytes);
+    bytes
+}
+
+pub fn fix_checksums(bytes: &mut [u8])
+{
+    bytes[0x14D] = bytes[0x134..0x14D].iter().fold(0_u8, |x, byte| x.wrapping_sub(*byte).wrapping_sub(1));
+    let global = bytes.iter().enumerate()
+        .filter(|(i, _)| *i != 0x14E && *i != 0x14F)
+        .fold(0_u16, |x, (_, byte)| x.wrapping_add(*byte as u16));
+    bytes[0x14E..0x150].copy_from_slice(&global.to_be_bytes());
+}
+
+///Writes a ROM to its own scratch directory so parallel tests don't share save files
+pub fn write_temp_rom(name: &str, bytes: &[u8]) -> PathBuf
+{
+    let dir = std::env::temp_dir().join(format!("gbi-test-{}-{}", name, std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let path = dir.join(format!("{}.gb", name));
+    std::fs::write(&path, bytes).unwrap();
+    let _ = std::fs::remove_file(path.with_extension("sav"));
+    path
+}
+
+#[test]
+fn battery_save_round_trip()
+{
+    //MBC1+RAM+BATTERY with 8 KiB of RAM
+    let path = write_temp_rom("battery", &test_cartridge(0x03, 0x00, 0x02));
+    let mut board = Mainboard::new(TestFrontend::default());
+    board.load_game(&path).unwrap();
+    let mut data = board.export_save_ram().unwrap();
+    assert_eq!(data.len(), 0x2000);
+    data[0x10] = 0x69;
+    board.import_save_ram(&data);
+    board.flush_save().unwrap();
+    assert_eq!(std::fs::read(path.with_extension("sav")).unwrap()[0x10], 0x69);
+
+    //A fresh load picks the save back up
+    let mut board = Mainboard::new(TestFrontend::default());
+    board.load_game(&path).unwrap();
+    assert_eq!(board.export_save_ram().unwrap()[0x10], 0x69);
+    std::fs::remove_dir_all(path.parent().unwrap()).unwrap();
+}
+
+#[test]
+fn no_save_file_without_battery()
+{
+    //MBC1+RAM
+    let path = write_temp_rom("no-battery", &test_cartridge(0x02, 0x00, 0x02));
+    let mut board = Mainboard::new(TestFrontend::default());
+    board.load_game(&path).unwrap();
+    board.flush_save().unwrap();
+    assert!(!path.with_extension("sav").exists());
+    std::fs::remove_dir_all(path.parent().unwrap()).unwrap();
+}
+
+///MBC1+RAM+BATTERY that runs the program from 0x150 after skipping the boot ROM
+fn battery_program_rom(program: &[u8]) -> Vec<u8>
+{
+    let mut bytes = test_cartridge(0x03, 0x00, 0x02);
+    bytes[0x100..0x103].copy_from_slice(&[0xC3, 0x50, 0x01]);
+    bytes[0x150..0x150 + program.len()].copy_from_slice(program);
+    fix_checksums(&mut bytes);
+    bytes
+}
+
+#[test]
+fn save_written_after_quiet_frames()
+{
+    use std::{cell::RefCell, rc::Rc};
+    use crate::boot::{BootMode, Model};
+
+    //Enable RAM, store 0x69 at 0xA010, then spin
+    let path = write_temp_rom("save-quiet", &battery_program_rom(&[0x3E, 0x0A, 0xEA, 0x00, 0x00, 0x3E, 0x69, 0xEA, 0x10, 0xA0, 0x18, 0xFE]));
+    let save = path.with_extension("sav");
+    let load = |frontend: &Rc<RefCell<TestFrontend>>|
+    {
+        let mut board = Mainboard::new(SharedFrontend(Rc::clone(frontend)));
+        board.set_boot_mode(BootMode::Skip(Model::Dmg));
+        board.set_save_delay(3);
+        board.load_game(&path).unwrap();
+        board.execute_frame();
+        board
+    };
+
+    let frontend = Rc::new(RefCell::new(TestFrontend::default()));
+    let mut board = load(&frontend);
+    assert!(!save.exists());
+    board.execute_frame();
+    board.execute_frame();
+    assert!(!save.exists());
+    board.execute_frame();
+    assert_eq!(std::fs::read(&save).unwrap()[0x10], 0x69);
+    assert_eq!(frontend.borrow().save_updates, 1);
+
+    //Rewriting the same byte from the loaded save changes nothing
+    drop(board);
+    let board = load(&frontend);
+    drop(board);
+    assert_eq!(frontend.borrow().save_updates, 1);
+
+    //A pending save is written when the board is dropped or another ROM is loaded
+    std::fs::remove_file(&save).unwrap();
+    drop(load(&frontend));
+    assert!(save.exists());
+    std::fs::remove_file(&save).unwrap();
+    let mut board = load(&frontend);
+    board.load_game_from_bytes(test_cartridge(0x00, 0x00, 0x00)).unwrap();
+    assert!(save.exists());
+    assert_eq!(frontend.borrow().save_updates, 3);
+    std::fs::remove_dir_all(path.parent().unwrap()).unwrap();
+}
+
+#[test]
+fn save_failure_reaches_frontend()
+{
+    use std::{cell::RefCell, rc::Rc};
+    use crate::boot::{BootMode, Model};
+
+    let path = write_temp_rom("save-failure", &battery_program_rom(&[0x3E, 0x0A, 0xEA, 0x00, 0x00, 0x3E, 0x69, 0xEA, 0x10, 0xA0, 0x18, 0xFE]));
+    let frontend = Rc::new(RefCell::new(TestFrontend::default()));
+    let mut board = Mainboard::new(SharedFrontend(Rc::clone(&frontend)));
+    board.set_boot_mode(BootMode::Skip(Model::Dmg));
+    board.set_save_delay(1);
+    board.load_game(&path).unwrap();
+    //A directory where the .sav should go can't be written over
+    std::fs::create_dir(path.with_extension("sav")).unwrap();
+    board.execute_frame();
+    board.execute_frame();
+    assert_eq!(frontend.borrow().save_failures.len(), 1);
+    assert_eq!(frontend.borrow().save_updates, 1);
+    assert_eq!(board.export_save_ram().unwrap()[0x10], 0x69);
+    drop(board);
+    std::fs::remove_dir_all(path.parent().unwrap()).unwrap();
+}
+
+#[test]
+fn save_ignores_disabled_ram_writes()
+{
+    use crate::boot::{BootMode, Model};
+
+    //Store to 0xA010 without enabling RAM first
+    let path = write_temp_rom("save-disabled", &battery_program_rom(&[0x3E, 0x69, 0xEA, 0x10, 0xA0, 0x18, 0xFE]));
+    let mut board = Mainboard::new(TestFrontend::default());
+    board.set_boot_mode(BootMode::Skip(Model::Dmg));
+    board.set_save_delay(1);
+    board.load_game(&path).unwrap();
+    for _ in 0..3
+    {
+        board.execute_frame();
+    }
+    drop(board);
+    assert!(!path.with_extension("sav").exists());
+    std::fs::remove_dir_all(path.parent().unwrap()).unwrap();
+}
+
+fn load_with_policy(name: &str, bytes: &[u8], policy: HeaderPolicy) -> Result<Mainboard, RomError>
+{
+    let path = write_temp_rom(name, bytes);
+    let mut board = Mainboard::new(TestFrontend::default());
+    board.set_header_policy(policy);
+    let result = board.load_game(&path);
+    std::fs::remove_dir_all(path.parent().unwrap()).unwrap();
+    result.map(|_| board)
+}
+
+fn load_error(name: &str, bytes: &[u8]) -> RomError
+{
+    load_with_policy(name, bytes, HeaderPolicy::Warn).err().unwrap()
+}
+
+#[test]
+fn rom_errors()
+{
+    assert!(matches!(load_error("truncated", &[0; 0x14F]), RomError::Truncated(0x14F)));
+    //MBC6
+    assert!(matches!(load_error("mapper", &test_cartridge(0x20, 0x00, 0x00)), RomError::UnsupportedMapper(0x20)));
+    let mut bytes = test_cartridge(0x00, 0x00, 0x00);
+    bytes[0x148] = 0x09;
+    assert!(matches!(load_error("rom-size", &bytes), RomError::InvalidRomSize(0x09)));
+    bytes[0x148] = 0x00;
+    bytes[0x149] = 0x06;
+    assert!(matches!(load_error("ram-size", &bytes), RomError::InvalidRamSize(0x06)));
+}
+
+#[test]
+fn header_size_codes()
+{
+    //(ROM size code, bytes), the larger MBC5 sizes included
+    let rom_sizes = [(0x00, 0x8000), (0x05, 0x100000), (0x06, 0x200000), (0x07, 0x400000), (0x08, 0x800000), (0x52, 0x120000)];
+    //MBC5+RAM+BATTERY
+    let mut bytes = test_cartridge(0x1B, 0x00, 0x00);
+    for (code, size) in rom_sizes
+    {
+        bytes[0x148] = code;
+        assert_eq!(crate::CartridgeHeader::new(&bytes).unwrap().rom_size, size, "{:02X}", code);
+    }
+    bytes[0x148] = 0x00;
+    for (code, size) in [(0x00, 0), (0x02, 0x2000), (0x03, 0x8000), (0x04, 0x20000), (0x05, 0x10000)]
+    {
+        bytes[0x149] = code;
+        assert_eq!(crate::CartridgeHeader::new(&bytes).unwrap().ram_size, size, "{:02X}", code);
+    }
+}
+
+#[test]
+fn rom_missing_file()
+{
+    let path = std::env::temp_dir().join("gbi-test-does-not-exist.gb");
+    let result = Mainboard::new(TestFrontend::default()).load_game(&path);
+    assert!(matches!(result, Err(RomError::Io(x)) if x.kind() == std::io::ErrorKind::NotFound));
+}
+
+#[test]
+fn header_validation()
+{
+    let board = load_with_policy("valid-header", &test_cartridge(0x00, 0x00, 0x00), HeaderPolicy::Reject).unwrap();
+    let validation = board.header_validation().unwrap();
+    assert!(validation.is_valid());
+    assert!(!board.is_header_locked());
+
+    let mut bytes = test_cartridge(0x00, 0x00, 0x00);
+    bytes[0x14D] ^= 0xFF;
+    let board = load_with_policy("bad-header-checksum", &bytes, HeaderPolicy::Warn).unwrap();
+    let validation = board.header_validation().unwrap();
+    assert!(validation.logo_valid);
+    assert!(!validation.header_checksum_valid());
+    assert_eq!(validation.header_checksum, !validation.computed_header_checksum);
+}
+
+#[test]
+fn header_policy_reject()
+{
+    let mut bytes = test_cartridge(0x00, 0x00, 0x00);
+    bytes[0x105] = 0x00;
+    fix_checksums(&mut bytes);
+    match load_with_policy("reject-logo", &bytes, HeaderPolicy::Reject)
+    {
+        Err(RomError::InvalidHeader(x)) => assert!(!x.logo_valid && x.header_checksum_valid()),
+        _ => panic!("Bad logo wasn't rejected")
+    }
+    //Reject also covers the global checksum
+    let mut bytes = test_cartridge(0x00, 0x00, 0x00);
+    bytes[0x14F] ^= 1;
+    assert!(matches!(load_with_policy("reject-global", &bytes, HeaderPolicy::Reject), Err(RomError::InvalidHeader(_))));
+}
+
+#[test]
+fn header_policy_lock_up()
+{
+    //Hardware never looks at the global checksum
+    let mut bytes = test_cartridge(0x00, 0x00, 0x00);
+    bytes[0x14F] ^= 1;
+    let board = load_with_policy("lock-global", &bytes, HeaderPolicy::LockUp).unwrap();
+    assert!(!board.is_header_locked());
+
+    let mut bytes = test_cartridge(0x00, 0x00, 0x00);
+    bytes[0x14D] ^= 1;
+    let board = load_with_policy("lock-header", &bytes, HeaderPolicy::LockUp).unwrap();
+    assert!(board.is_header_locked());
+}
+
+#[test]
+fn header_check_reported()
+{
+    use std::{cell::RefCell, rc::Rc};
+
+    let frontend = Rc::new(RefCell::new(TestFrontend::default()));
+    let mut board = Mainboard::new(SharedFrontend(Rc::clone(&frontend)));
+    board.load_game_from_bytes(test_cartridge(0x00, 0x00, 0x00)).unwrap();
+    assert!(frontend.borrow().header_failures.is_empty());
+
+    let mut bad_header = test_cartridge(0x00, 0x00, 0x00);
+    bad_header[0x14D] ^= 1;
+    let mut bad_global = test_cartridge(0x00, 0x00, 0x00);
+    bad_global[0x14F] ^= 1;
+    board.load_game_from_bytes(bad_header.clone()).unwrap();
+    board.set_header_policy(HeaderPolicy::LockUp);
+    board.load_game_from_bytes(bad_global).unwrap();
+    //The frontend hears about a lock-up at load, the hung CPU never reports anything
+    board.load_game_from_bytes(bad_header).unwrap();
+    assert_eq!(frontend.borrow().header_failures, vec![false, false, true]);
+}
+
+#[test]
+fn rejected_rom_not_reported()
+{
+    use std::{cell::RefCell, rc::Rc};
+
+    let frontend = Rc::new(RefCell::new(TestFrontend::default()));
+    let mut board = Mainboard::new(SharedFrontend(Rc::clone(&frontend)));
+    board.set_header_policy(HeaderPolicy::Reject);
+    let mut bad_header = test_cartridge(0x00, 0x00, 0x00);
+    bad_header[0x14D] ^= 1;
+    assert!(board.load_game_from_bytes(bad_header).is_err());
+    assert!(frontend.borrow().rom_titles.is_empty());
+    assert_eq!(frontend.borrow().cartridge_headers, 0);
+
+    board.load_game_from_bytes(test_cartridge(0x00, 0x00, 0x00)).unwrap();
+    assert_eq!(frontend.borrow().rom_titles, vec!["TEST"]);
+    assert_eq!(frontend.borrow().cartridge_headers, 1);
+}
+
+#[test]
+fn cartridge_header()
+{
+    let mut bytes = test_cartridge(0x1E, 0x02, 0x03);
+    bytes[0x134..0x13F].copy_from_slice(b"POKEMON PIN");
+    bytes[0x13F..0x143].copy_from_slice(b"VPHE");
+    bytes[0x143] = 0x80;
+    bytes[0x144..0x146].copy_from_slice(b"01");
+    bytes[0x146] = 0x03;
+    bytes[0x14A] = 0x01;
+    bytes[0x14B] = 0x33;
+    bytes[0x14C] = 0x02;
+    fix_checksums(&mut bytes);
+    let board = load_with_policy("cartridge-header", &bytes, HeaderPolicy::Reject).unwrap();
+    let header = board.cartridge_header().unwrap();
+    //The title stops where the manufacturer code starts
+    assert_eq!(header.title, "POKEMON PIN");
+    assert_eq!(header.manufacturer_code.as_deref(), Some("VPHE"));
+    assert!(header.supports_cgb() && !header.cgb_only());
+    assert!(header.supports_sgb());
+    assert_eq!(header.licensee_code(), "01");
+    assert!(!header.is_japanese());
+    assert_eq!(header.mask_rom_version, 2);
+    assert_eq!((header.cartridge_type, header.mbc_model), (0x1E, crate::MBCModel::Mbc5));
+    assert_eq!((header.rom_size, header.ram_size), (131072, 32768));
+    assert!(header.has_battery && header.has_rumble && !header.has_rtc);
+    assert_eq!(header.header_checksum, bytes[0x14D]);
+    assert_eq!(header.global_checksum, u16::from_be_bytes([bytes[0x14E], bytes[0x14F]]));
+
+    //Older cartridges have no manufacturer code and the SGB flag needs the new licensee
+    let mut bytes = test_cartridge(0x00, 0x00, 0x00);
+    bytes[0x146] = 0x03;
+    bytes[0x14B] = 0x01;
+    fix_checksums(&mut bytes);
+    let board = load_with_policy("cartridge-header-old", &bytes, HeaderPolicy::Reject).unwrap();
+    let header = board.cartridge_header().unwrap();
+    assert_eq!(header.manufacturer_code, None);
+    assert!(!header.supports_sgb());
+    assert_eq!(header.licensee_code(), "01");
+
+    //A CGB flag without a manufacturer code only takes the last byte, older titles use all 16
+    let mut bytes = test_cartridge(0x00, 0x00, 0x00);
+    bytes[0x134..0x144].copy_from_slice(b"SIXTEEN BYTES OK");
+    let header = crate::CartridgeHeader::new(&bytes).unwrap();
+    assert_eq!(header.title, "SIXTEEN BYTES OK");
+    bytes[0x13F..0x143].copy_from_slice(b"ab-c");
+    bytes[0x143] = 0xC0;
+    let header = crate::CartridgeHeader::new(&bytes).unwrap();
+    assert_eq!(header.manufacturer_code, None);
+    assert_eq!(header.title, "SIXTEEN BYTab-c");
+}
+
+#[test]
+fn load_from_memory()
+{
+    let bytes = test_cartridge(0x03, 0x00, 0x02);
+    let mut board = Mainboard::new(TestFrontend::default());
+    board.load_game_from_bytes(&bytes[..]).unwrap();
+    assert!(board.cartridge_header().unwrap().has_battery);
+    //Without a path the save only goes through the export API
+    assert!(board.flush_save().is_ok());
+    assert_eq!(board.export_save_ram().unwrap().len(), 8192);
+
+    let mut board = Mainboard::new(TestFrontend::default());
+    board.load_game_from_bytes(bytes.clone()).unwrap();
+    assert_eq!(board.cartridge_header().unwrap().mbc_model, crate::MBCModel::Mbc1_16_8);
+
+    let mut board = Mainboard::new(TestFrontend::default());
+    board.load_game_from_reader(std::io::Cursor::new(bytes)).unwrap();
+    assert_eq!(board.cartridge_header().unwrap().cartridge_type, 0x03);
+
+    let result = Mainboard::new(TestFrontend::default()).load_game_from_reader(&[0u8; 0x100][..]);
+    assert!(matches!(result, Err(RomError::Truncated(0x100))));
+}
+
+#[test]
+fn soft_patching()
+{
+    //IPS patch renaming the game, the title change is fixed up by a second record for the checksum
+    let bytes = test_cartridge(0x00, 0x00, 0x00);
+    let mut patched = bytes.clone();
+    patched[0x134..0x138].copy_from_slice(b"HACK");
+    fix_checksums(&mut patched);
+    let mut patch = b"PATCH".to_vec();
+    patch.extend_from_slice(&[0x00, 0x01, 0x34, 0x00, 0x04]);
+    patch.extend_from_slice(b"HACK");
+    patch.extend_from_slice(&[0x00, 0x01, 0x4D, 0x00, 0x03]);
+    patch.extend_from_slice(&patched[0x14D..0x150]);
+    patch.extend_from_slice(b"EOF");
+
+    let path = write_temp_rom("soft-patch", &bytes);
+    std::fs::write(path.with_extension("ips"), &patch).unwrap();
+    let mut board = Mainboard::new(TestFrontend::default());
+    board.set_header_policy(HeaderPolicy::Reject);
+    board.load_game(&path).unwrap();
+    assert_eq!(board.cartridge_header().unwrap().title, "HACK");
+    //The ROM on disk is untouched
+    assert_eq!(std::fs::read(&path).unwrap(), bytes);
+
+    std::fs::write(path.with_extension("ips"), b"PATCH\x00").unwrap();
+    assert!(matches!(board.load_game(&path), Err(RomError::Patch(_))));
+    std::fs::remove_dir_all(path.parent().unwrap()).unwrap();
+
+    let mut board = Mainboard::new(TestFrontend::default());
+    board.load_game_with_patch(bytes, &patch).unwrap();
+    assert_eq!(board.cartridge_header().unwrap().title, "HACK");
+}
+
+#[test]
+fn cheats()
+{
+    let mut bytes = test_cartridge(0x00, 0x00, 0x00);
+    bytes[0x4AF0] = 0x12;
+    fix_checksums(&mut bytes);
+    let mut board = Mainboard::new(TestFrontend::default());
+    board.load_game_from_bytes(bytes).unwrap();
+
+    //Compare byte 0x12 matches, compare byte 0x00 doesn't
+    let hit = board.add_cheat("3EA-F0B-AC2").unwrap();
+    assert_eq!(board.read_memory(0x4AF0), 0x3E);
+    board.set_cheat_enabled(hit, false);
+    assert_eq!(board.read_memory(0x4AF0), 0x12);
+    board.add_cheat("3EA-F0B-E0A").unwrap();
+    assert_eq!(board.read_memory(0x4AF0), 0x12);
+
+    let shark = board.add_cheat("0142F0C0").unwrap();
+    assert_eq!(board.cheats().len(), 3);
+    assert!(!board.cheats()[hit].enabled && board.cheats()[shark].enabled);
+    assert_eq!(board.cheats()[shark].decoded, crate::cheat::CheatCode::GameShark { code_type: 0x01, address: 0xC0F0, value: 0x42 });
+    for _ in 0..3
+    {
+        board.execute_frame();
+    }
+    assert_eq!(board.read_memory(0xC0F0), 0x42);
+
+    assert!(board.remove_cheat(shark).is_some());
+    assert!(board.remove_cheat(shark).is_none());
+    assert!(matches!(board.add_cheat("not a code"), Err(crate::cheat::CheatError::InvalidFormat(_))));
+    board.clear_cheats();
+    assert!(board.cheats().is_empty());
+}
+
+#[test]
+fn boot_modes()
+{
+    use crate::boot::{BootMode, BootRom, BootRomError, Model};
+
+    let mut bytes = test_cartridge(0x00, 0x00, 0x00);
+    bytes[0x0000] = 0x11;
+    bytes[0x0200] = 0x22;
+    fix_checksums(&mut bytes);
+
+    //Bundled DMG boot ROM starts mapped
+    let mut board = Mainboard::new(TestFrontend::default());
+    board.load_game_from_bytes(bytes.clone()).unwrap();
+    assert_eq!(board.read_memory(0x0000), crate::rom::BOOT_ROM[0]);
+
+    let mut board = Mainboard::new(TestFrontend::default());
+    board.set_boot_mode(BootMode::Rom(BootRom::new(Model::Mgb, vec![0xAA; 0x100]).unwrap()));
+    board.load_game_from_bytes(bytes.clone()).unwrap();
+    assert_eq!((board.read_memory(0x0000), board.read_memory(0x0200)), (0xAA, 0x22));
+
+    //CGB images skip over the cartridge header
+    let mut board = Mainboard::new(TestFrontend::default());
+    board.set_boot_mode(BootMode::Rom(BootRom::new(Model::Cgb, vec![0xBB; 0x900]).unwrap()));
+    board.load_game_from_bytes(bytes.clone()).unwrap();
+    assert_eq!(board.read_memory(0x0000), 0xBB);
+    assert_eq!(board.read_memory(0x0134), bytes[0x0134]);
+    assert_eq!(board.read_memory(0x0200), 0xBB);
+    assert_eq!(BootRom::new(Model::Cgb, vec![0; 0x100]), Err(BootRomError::InvalidSize(0x900, 0x100)));
+
+    let mut board = Mainboard::new(TestFrontend::default());
+    board.set_boot_mode(BootMode::Skip(Model::Dmg));
+    board.load_game_from_bytes(bytes.clone()).unwrap();
+    assert_eq!(board.read_memory(0x0000), 0x11);
+    assert_eq!(board.read_memory(crate::ram::LCDC), 0x91);
+    assert_eq!(board.read_memory(crate::ram::BGP), 0xFC);
+    assert_eq!(board.read_memory(crate::ram::DIV), 0xAB);
+
+    //Skipping also skips the boot ROM's header check
+    bytes[0x14D] ^= 1;
+    let mut board = Mainboard::new(TestFrontend::default());
+    board.set_header_policy(HeaderPolicy::LockUp);
+    board.set_boot_mode(BootMode::Skip(Model::Dmg0));
+    board.load_game_from_bytes(bytes).unwrap();
+    assert!(!board.is_header_locked());
+    assert_eq!(board.read_memory(crate::ram::DIV), 0x18);
+}
+
+#[test]
+fn cpu_timing()
+{
+    use crate::{boot::{BootMode, Model}, cpu::CpuTiming};
+
+    //JP 0x0150, then LD HL,0xC000 and INC (HL) in a loop
+    let mut bytes = test_cartridge(0x00, 0x00, 0x00);
+    bytes[0x100..0x103].copy_from_slice(&[0xC3, 0x50, 0x01]);
+    bytes[0x150..0x156].copy_from_slice(&[0x21, 0x00, 0xC0, 0x34, 0x18, 0xFD]);
+    fix_checksums(&mut bytes);
+
+    let mut counts = Vec::new();
+    for timing in [CpuTiming::Instruction, CpuTiming::MCycle]
+    {
+        let mut board = Mainboard::new(TestFrontend::default());
+        board.set_boot_mode(BootMode::Skip(Model::Dmg));
+        board.set_cpu_timing(timing);
+        board.load_game_from_bytes(bytes.clone()).unwrap();
+        board.execute_frame();
+        counts.push(board.read_memory(0xC000));
+    }
+    //Same number of instructions fit in a frame either way
+    assert_ne!(counts[0], 0);
+    assert_eq!(counts[0], counts[1]);
+}
+
+#[test]
+fn cpu_timing_shares_board_schedule()
+{
+    use crate::{asm, boot::{BootMode, Model}, cpu::CpuTiming, ram};
+
+    //Start TIMA at its fastest clock, then either spin or halt with no interrupts enabled
+    let source = "
+    ld a, $05
+    ldh [$07], a
+    xor a
+    ldh [$FF], a
+.loop:
+    {}
+    jr .loop
+";
+    for wait in ["nop", "halt"]
+    {
+        let bytes = asm::assemble_rom(&source.replace("{}", wait), "TIMER").unwrap();
+        let mut registers = Vec::new();
+        for timing in [CpuTiming::Instruction, CpuTiming::MCycle]
+        {
+            let mut board = Mainboard::new(TestFrontend::default());
+            board.set_boot_mode(BootMode::Skip(Model::Dmg));
+            board.set_cpu_timing(timing);
+            board.load_game_from_bytes(bytes.clone()).unwrap();
+            for _ in 0..3
+            {
+                board.execute_frame();
+            }
+            assert_eq!(board.registers().halted, wait == "halt");
+            registers.push((board.read_memory(ram::DIV), board.read_memory(ram::TIMA)));
+        }
+        assert_eq!(registers[0], registers[1], "{}", wait);
+    }
+}
+
+#[test]
+fn timer_rates()
+{
+    use crate::{asm, boot::{BootMode, Model}, cpu::CpuTiming};
+
+    //Reset DIV, wait about 1000 M-cycles, then store DIV and TIMA in WRAM
+    //M-cycles from the DIV reset to each read
+    const DIV_READ:u32 = 1004;
+    const TIMA_READ:u32 = 1012;
+    //M-cycles per TIMA increment for each TAC clock select
+    const TIMA_PERIODS:[u32; 4] = [256, 4, 16, 64];
+    let source = "
+    ld a, {}
+    ldh [$07], a
+    xor a
+    ldh [$05], a
+    ldh [$04], a
+    ld b, 250
+.wait:
+    dec b
+    jr nz, .wait
+    ldh a, [$04]
+    ld [$C000], a
+    ldh a, [$05]
+    ld [$C001], a
+.done:
+    jr .done
+";
+    for tac in 0..8u8
+    {
+        let bytes = asm::assemble_rom(&source.replace("{}", &format!("${:02X}", tac)), "TIMER").unwrap();
+        for timing in [CpuTiming::Instruction, CpuTiming::MCycle]
+        {
+            let mut board = Mainboard::new(TestFrontend::default());
+            board.set_boot_mode(BootMode::Skip(Model::Dmg));
+            board.set_cpu_timing(timing);
+            board.load_game_from_bytes(bytes.clone()).unwrap();
+            board.execute_frame();
+
+            //DIV counts every 64 M-cycles
+            let div = board.read_memory(0xC000) as u32;
+            assert!(div.abs_diff(DIV_READ / 64) <= 1, "TAC {:02X} {:?}: DIV {}", tac, timing, div);
+            let tima = board.read_memory(0xC001) as u32;
+            let expected = if tac & 0x04 != 0 { TIMA_READ / TIMA_PERIODS[(tac & 0x03) as usize] } else { 0 };
+            assert!(tima.abs_diff(expected) <= 1, "TAC {:02X} {:?}: TIMA {} expected {}", tac, timing, tima, expected);
+        }
+    }
+}
+
+#[test]
+fn illegal_opcode_lock_up()
+{
+    use std::{cell::RefCell, rc::Rc};
+    use crate::{boot::{BootMode, Model}, cpu::CpuTiming};
+
+    //NOP then an illegal opcode at the entry point
+    let mut bytes = test_cartridge(0x00, 0x00, 0x00);
+    bytes[0x100..0x102].copy_from_slice(&[0x00, 0xDD]);
+    fix_checksums(&mut bytes);
+
+    for timing in [CpuTiming::Instruction, CpuTiming::MCycle]
+    {
+        let frontend = Rc::new(RefCell::new(TestFrontend::default()));
+        let mut board = Mainboard::new(SharedFrontend(Rc::clone(&frontend)));
+        board.set_boot_mode(BootMode::Skip(Model::Dmg));
+        board.set_cpu_timing(timing);
+        board.load_game_from_bytes(bytes.clone()).unwrap();
+        //The rest of the board keeps running
+        assert!(board.execute_frame());
+        assert!(board.is_cpu_locked());
+        assert_eq!(frontend.borrow().lock_ups, vec![(0x0101, 0xDD)]);
+
+        board.load_game_from_bytes(bytes.clone()).unwrap();
+        assert!(!board.is_cpu_locked());
+    }
+}
+
+#[test]
+fn assembled_rom()
+{
+    use crate::{asm, boot::{BootMode, Model}};
+
+    //Copies a string into WRAM then spins
+    let source = "
+    ld hl, message
+    ld de, $C000
+    ld b, message_end - message
+.copy:
+    ld a, [hl+]
+    ld [de], a
+    inc de
+    dec b
+    jr nz, .copy
+.done:
+    jr .done
+message:
+    db \"HELLO\"
+message_end:
+";
+    let bytes = asm::assemble_rom(source, "HELLO").unwrap();
+    let mut board = Mainboard::new(TestFrontend::default());
+    board.set_boot_mode(BootMode::Skip(Model::Dmg));
+    board.load_game_from_bytes(bytes).unwrap();
+    board.execute_frame();
+    let copied = (0xC000..0xC005).map(|x| board.read_memory(x)).collect::<Vec<u8>>();
+    assert_eq!(copied, b"HELLO");
+    assert_eq!(board.cartridge_header().unwrap().title, "HELLO");
+}
+
+#[test]
+fn instruction_trace()
+{
+    use crate::boot::{BootMode, Model};
+
+    let mut bytes = test_cartridge(0x00, 0x00, 0x00);
+    bytes[0x100..0x104].copy_from_slice(&[0x00, 0xC3, 0x50, 0x01]);
+    bytes[0x150..0x152].copy_from_slice(&[0x18, 0xFE]);
+    fix_checksums(&mut bytes);
+
+    let trace = SharedBuffer::default();
+    let mut board = Mainboard::new(TestFrontend::default());
+    board.set_boot_mode(BootMode::Skip(Model::Dmg));
+    board.set_trace(Some(Box::new(trace.clone())));
+    //Tracing survives loading a game
+    board.load_game_from_bytes(bytes).unwrap();
+    board.execute_frame();
+    let lines = trace.lines();
+    assert_eq!(lines[0], "A:01 F:B0 B:00 C:13 D:00 E:D8 H:01 L:4D SP:FFFE PC:0100 PCMEM:00,C3,50,01");
+    assert_eq!(lines[1], "A:01 F:B0 B:00 C:13 D:00 E:D8 H:01 L:4D SP:FFFE PC:0101 PCMEM:C3,50,01,CE");
+    assert_eq!(lines[2], "A:01 F:B0 B:00 C:13 D:00 E:D8 H:01 L:4D SP:FFFE PC:0150 PCMEM:18,FE,00,00");
+
+    assert!(board.set_trace(None).is_some());
+    let count = trace.lines().len();
+    board.execute_frame();
+    assert_eq!(trace.lines().len(), count);
+}
+
+///Trace sink that rejects every write
+struct FullDisk;
+
+impl std::io::Write for FullDisk
+{
+    fn write(&mut self, _buf: &[u8]) -> std::io::Result<usize>
+    {
+        Err(std::io::Error::new(std::io::ErrorKind::StorageFull, "disk full"))
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> { Ok(()) }
+}
+
+#[test]
+fn trace_failure_reaches_frontend()
+{
+    use crate::{boot::{BootMode, Model}, cpu::CpuTiming};
+
+    for timing in [CpuTiming::Instruction, CpuTiming::MCycle]
+    {
+        let frontend = std::rc::Rc::new(std::cell::RefCell::new(TestFrontend::default()));
+        let mut board = Mainboard::new(SharedFrontend(std::rc::Rc::clone(&frontend)));
+        board.set_boot_mode(BootMode::Skip(Model::Dmg));
+        board.set_cpu_timing(timing);
+        board.load_game_from_bytes(test_cartridge(0x00, 0x00, 0x00)).unwrap();
+        board.set_trace(Some(Box::new(FullDisk)));
+        board.execute_frame();
+        //Reported once, then tracing is off
+        assert_eq!(frontend.borrow().trace_failures, vec![std::io::ErrorKind::StorageFull], "{:?}", timing);
+        assert!(board.set_trace(None).is_none());
+    }
+}
+
+#[test]
+fn register_access()
+{
+    use crate::boot::{BootMode, Model};
+
+    //LD (0xC000),A then spin
+    let mut bytes = test_cartridge(0x00, 0x00, 0x00);
+    bytes[0x150..0x155].copy_from_slice(&[0xEA, 0x00, 0xC0, 0x18, 0xFE]);
+    fix_checksums(&mut bytes);
+
+    let mut board = Mainboard::new(TestFrontend::default());
+    board.set_boot_mode(BootMode::Skip(Model::Dmg));
+    board.load_game_from_bytes(bytes).unwrap();
+    let mut registers = board.registers();
+    assert_eq!((registers.af(), registers.bc(), registers.pc), (0x01B0, 0x0013, 0x0100));
+
+    registers.a = 0x99;
+    registers.pc = 0x0150;
+    board.set_registers(registers);
+    board.execute_frame();
+    assert_eq!(board.read_memory(0xC000), 0x99);
+    assert_eq!(board.registers().pc, 0x0153);
+}
+
+#[test]
+fn joypad_register()
+{
+    use crate::ram::{self, Button, InterruptFlag, Ram};
+
+    let mut ram = Ram::new();
+    ram.write(ram::P1, 0x30);
+    assert_eq!(ram.read(ram::P1), 0xFF);
+    //Held buttons only show on a selected group's lines
+    ram.set_buttons(Button::START | Button::LEFT);
+    assert_eq!(ram.read(ram::P1), 0xFF);
+    assert_eq!(ram.read(ram::IF) & 0x1F, 0x00);
+    ram.write(ram::P1, 0x10);
+    assert_eq!(ram.read(ram::P1), 0xD7);
+    assert_eq!(ram.read(ram::IF) & 0x1F, InterruptFlag::P1X_NEG_EDGE.bits());
+    ram.reset_interrupt(InterruptFlag::P1X_NEG_EDGE);
+    //Selecting the directions too pulls LEFT's line low
+    ram.write(ram::P1, 0x00);
+    assert_eq!(ram.read(ram::P1), 0xC5);
+    assert_eq!(ram.read(ram::IF) & 0x1F, InterruptFlag::P1X_NEG_EDGE.bits());
+    ram.reset_interrupt(InterruptFlag::P1X_NEG_EDGE);
+    //Releasing is a rising edge
+    ram.set_buttons(Button::empty());
+    assert_eq!(ram.read(ram::P1), 0xCF);
+    assert_eq!(ram.read(ram::IF) & 0x1F, 0x00);
+    ram.set_buttons(Button::A);
+    assert_eq!(ram.read(ram::IF) & 0x1F, InterruptFlag::P1X_NEG_EDGE.bits());
+}
+
+#[test]
+fn stop_until_button_press()
+{
+    use crate::{asm, boot::{BootMode, Model}, cpu::CpuTiming, ram::{self, Button}};
+
+    //Selects the action buttons, stops, then marks WRAM once woken
+    let source = "
+    ld a, $10
+    ldh [$00], a
+    stop
+    ld a, 1
+    ld [$C000], a
+.done:
+    jr .done
+";
+    let bytes = asm::assemble_rom(source, "STOP").unwrap();
+    for timing in [CpuTiming::Instruction, CpuTiming::MCycle]
+    {
+        let mut board = Mainboard::new(TestFrontend::default());
+        board.set_boot_mode(BootMode::Skip(Model::Dmg));
+        board.set_cpu_timing(timing);
+        board.load_game_from_bytes(bytes.clone()).unwrap();
+        board.execute_frame();
+        board.execute_frame();
+        assert!(board.registers().stopped, "{:?}", timing);
+        //DIV was reset and its clock is stopped too
+        assert_eq!(board.read_memory(ram::DIV), 0x00, "{:?}", timing);
+        assert_eq!(board.read_memory(0xC000), 0x00, "{:?}", timing);
+
+        //A direction isn't on a selected line
+        board.set_buttons(Button::UP);
+        board.execute_frame();
+        assert!(board.registers().stopped, "{:?}", timing);
+
+        board.set_buttons(Button::START);
+        board.execute_frame();
+        assert!(!board.registers().stopped, "{:?}", timing);
+        assert_eq!(board.read_memory(0xC000), 0x01, "{:?}", timing);
+        assert_ne!(board.read_memory(ram::DIV), 0x00, "{:?}", timing);
+    }
+}
+
+#[test]
+fn call_stack_backtrace()
+{
+    use crate::{asm, boot::{BootMode, Model}, callstack::FrameKind};
+
+    //Spins two calls deep
+    let source = "
+    call outer
+outer:
+    rst $38
+";
+    let mut bytes = asm::assemble_rom(source, "BACKTRACE").unwrap();
+    //RST 0x38 jumps into a JR to itself
+    bytes[0x38..0x3A].copy_from_slice(&[0x18, 0xFE]);
+    fix_checksums(&mut bytes);
+    let mut board = Mainboard::new(TestFrontend::default());
+    board.set_boot_mode(BootMode::Skip(Model::Dmg));
+    board.load_game_from_bytes(bytes).unwrap();
+    board.execute_frame();
+    let backtrace = board.call_stack().backtrace().map(|x| (x.kind, x.source, x.target)).collect::<Vec<_>>();
+    assert_eq!(backtrace, [(FrameKind::Rst, 0x0153, 0x0038), (FrameKind::Call, 0x0150, 0x0153)]);
+    assert_eq!(board.call_stack().mismatch_count(), 0);
+}
+
+#[test]
+fn memory_map()
+{
+    use crate::ram::{self, Ram};
+
+    let mut ram = Ram::new();
+    ram.write(ram::SC_BOOT_ROM_DISABLE, 1);
+
+    //Echo RAM mirrors WRAM both ways
+    ram.write(0xC123, 0x42);
+    assert_eq!(ram.read(0xE123), 0x42);
+    ram.write(0xFDFF, 0x99);
+    assert_eq!(ram.read(0xDDFF), 0x99);
+
+    ram.write(0xFEA0, 0x55);
+    assert_eq!(ram.read(0xFEA0), 0x00);
+
+    //Unused bits read as 1, read-only bits keep their value
+    ram.write(ram::IF, 0x00);
+    assert_eq!(ram.read(ram::IF), 0xE0);
+    ram.write(ram::TAC, 0x05);
+    assert_eq!(ram.read(ram::TAC), 0xFD);
+    ram.write_register(ram::STAT, 0x02);
+    ram.write(ram::STAT, 0xFF);
+    assert_eq!(ram.read(ram::STAT), 0xFA);
+    ram.write_register(ram::LY, 0x10);
+    ram.write(ram::LY, 0x55);
+    assert_eq!(ram.read(ram::LY), 0x10);
+    //Write-only and unmapped registers
+    ram.write(0xFF13, 0x12);
+    assert_eq!(ram.read(0xFF13), 0xFF);
+    ram.write(0xFF4C, 0x12);
+    assert_eq!(ram.read(0xFF4C), 0xFF);
+    assert_eq!(ram.read(ram::SC_BOOT_ROM_DISABLE), 0xFF);
+
+    //Any write clears DIV
+    ram.write_register(ram::DIV, 0x33);
+    ram.write(ram::DIV, 0x77);
+    assert_eq!(ram.read(ram::DIV), 0x00);
+    assert!(ram.take_divider_reset());
+}
+
+#[test]
+fn oam_dma()
+{
+    use crate::ram::{self, Ram};
+
+    let mut ram = Ram::new();
+    ram.write(ram::SC_BOOT_ROM_DISABLE, 1);
+    for x in 0..0xA0
+    {
+        ram.write(0xC000 + x, x as u8 + 1);
+    }
+    ram.write(ram::DMA, 0xC0);
+    for _ in 0..0xA2
+    {
+        ram.execute();
+    }
+    let oam = ram::OAM.map(|x| ram.read(x)).collect::<Vec<u8>>();
+    assert_eq!(oam, (1..=0xA0).collect::<Vec<u8>>());
+    //The copy doesn't spill into the start of the map
+    assert_eq!(ram.read(0x0000), 0x00);
+}
@@ -1,15 +1,19 @@
 use crate::ram::{self, Ram};
 
-//TIMER USES A T-CYCLE-NEG
+//Ticked by the board on the same cycle as DMA and the PPU, in either CPU timing mode
 
-//Timer clock select values
-const INPUT_CLOCK_SELECT_CYCLE_COUNT:[u64; 4] = [1024, 16, 64, 256];
+//The internal counter counts M-cycles, DIV is its top 8 bits out of 14
+const DIV_SHIFT:u8 = 6;
+//Internal counter bit that clocks TIMA on its falling edge, for each TAC clock select
+const TAC_COUNTER_BITS:[u8; 4] = [7, 1, 3, 5];
+const TAC_ENABLE:u8 = 1 << 2;
+const TAC_CLOCK_SELECT:u8 = 0b11;
 
 pub struct Timer
 {
     internal_counter: u16,
-    tima_start: u64,
-    tima_enabled: bool,
+    ///Selected counter bit ANDed with the enable, TIMA counts when it falls
+    timer_input: bool,
     tima_overflow: bool
 }
 
@@ -17,27 +21,33 @@ impl Timer
 {
     pub fn new() -> Timer
     {
-        Timer { internal_counter: 0, tima_start: 0, tima_enabled: false, tima_overflow: false }
+        Timer { internal_counter: 0, timer_input: false, tima_overflow: false }
     }
 
-    pub fn execute(&mut self, ram: &mut Ram, m_cycles: u64)
+    ///Starts DIV from a given value without that looking like a reset
+    pub fn set_divider(&mut self, value: u8)
+    {
+        self.internal_counter = (value as u16) << DIV_SHIFT;
+    }
+
+    ///Clears the whole internal counter, as STOP does
+    pub fn reset_divider(&mut self, ram: &mut Ram)
+    {
+        self.internal_counter = 0;
+        ram.write_register(ram::DIV, 0);
+    }
+
+    pub fn execute(&mut self, ram: &mut Ram)
     {
         //Writing to the divider resets the internal counter
-        let divider = ram.read(ram::DIV);
-        if divider != self.internal_counter.to_le_bytes()[1]
+        if ram.take_divider_reset()
         {
             self.internal_counter = 0;
         }
 
         //Increment internal counter
         self.internal_counter = self.internal_counter.wrapping_add(1);
-        let bytes = self.internal_counter.to_le_bytes();
-        ram.write(ram::DIV, bytes[1]);
-
-        let tac_val = ram.read(ram::TAC);
-        let old_timer_enable = self.tima_enabled;
-        self.tima_enabled = tac_val & (1 << 2) != 0;
-        let timer_clock = INPUT_CLOCK_SELECT_CYCLE_COUNT[(tac_val & 0b00000011_u8) as usize];
+        ram.write_register(ram::DIV, (self.internal_counter >> DIV_SHIFT) as u8);
 
         //TIMA overflow timing may be incorrect
         if self.tima_overflow
@@ -48,19 +58,15 @@ impl Timer
             ram.write(ram::TIMA, ram.read(ram::TMA));
         }
 
-        if self.tima_enabled
+        //TIMA follows the divider, so it keeps the same phase however the timer was started
+        let tac_val = ram.read(ram::TAC);
+        let counter_bit = TAC_COUNTER_BITS[(tac_val & TAC_CLOCK_SELECT) as usize];
+        let timer_input = tac_val & TAC_ENABLE != 0 && (self.internal_counter >> counter_bit) & 1 != 0;
+        if self.timer_input && !timer_input
         {
-            if !old_timer_enable
-            {
-                self.tima_start = m_cycles;
-            }
-            else if m_cycles - self.tima_start >= timer_clock
-            {
-                self.tima_overflow = self.timer_increment(ram); //After X cycles, increment
-            }
+            self.tima_overflow = self.timer_increment(ram);
         }
-
-
+        self.timer_input = timer_input;
     }
 
     fn timer_increment(&mut self, ram: &mut Ram) -> bool